@@ -0,0 +1,83 @@
+use std::{
+    fs::read_dir,
+    io,
+    path::{Path, PathBuf},
+};
+use crate::{
+    AppError,
+    glob::glob,
+    model::Project,
+};
+use std::sync::Arc;
+
+/// Folder of the log folder of an experiment into which its declared outputs are collected, and
+/// which its commands are given as `REPLIKATE_OUT`.
+pub const ARTIFACTS_FOLDER: &str = "artifacts";
+
+/// Prints, for each experiment declaring `outputs`, the artifacts collected by its latest
+/// successful run that match each declared pattern, the patterns matching none, and the collected
+/// files no pattern declares anymore. Only reads the configuration and the logs folder. Fails
+/// when any declared output is missing.
+pub fn list_outputs(p: &Project) -> Result<(), AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    let mut missing = 0;
+    for exp in p.experiments.iter().filter(|exp| !exp.outputs.is_empty()) {
+        let artifacts = p.run_logs_path().join(exp.log_folder()).join(ARTIFACTS_FOLDER);
+        println!("{}:", exp.name);
+
+        let mut declared = Vec::new();
+        for pattern in &exp.outputs {
+            // Outputs outside the working directory are collected under their file name.
+            let collected_as = match pattern.strip_prefix('/') {
+                Some(absolute) => absolute.rsplit('/').next().unwrap_or_default(),
+                None => pattern.as_str(),
+            };
+            let files = glob(&artifacts, collected_as).map_err(into_err(&artifacts))?
+                .into_iter()
+                .filter(|file| file.is_file())
+                .collect::<Vec<_>>();
+            if files.is_empty() {
+                println!("  {}: missing", pattern);
+                missing += 1;
+            }
+            for file in &files {
+                println!("  {}: {}", pattern, relative(file, &artifacts));
+            }
+            declared.extend(files);
+        }
+
+        let mut collected = Vec::new();
+        if artifacts.is_dir() {
+            files_under(&artifacts, &mut collected).map_err(into_err(&artifacts))?;
+        }
+        collected.sort();
+        for file in collected.iter().filter(|file| !declared.contains(file)) {
+            println!("  undeclared: {}", relative(file, &artifacts));
+        }
+    }
+
+    match missing {
+        0 => Ok(()),
+        missing => Err(AppError::ExternalError(format!("{} declared output(s) missing in {}.", missing, p.name))),
+    }
+}
+
+fn files_under(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files_under(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn relative(file: &Path, base: &Path) -> String {
+    file.strip_prefix(base).unwrap_or(file).display().to_string()
+}
@@ -0,0 +1,97 @@
+use std::{
+    env,
+    process::{Command, Stdio},
+};
+use crate::{
+    warning,
+    AppError,
+    model::{Repository, SecretSource},
+    trace::traced_output,
+};
+
+/// User name sent with the token when `auth` gives none, accepted by most git forges.
+const DEFAULT_USERNAME: &str = "x-access-token";
+
+/// Environment giving git the credentials of `repository` through `GIT_CONFIG_*` variables, as
+/// an authorization header scoped to its url. The token is neither written to disk nor visible
+/// on the command line, and must never be logged.
+pub fn git_auth_env(repository: &Repository) -> Result<Vec<(String, String)>, AppError> {
+    let auth = match &repository.auth {
+        Some(auth) => auth,
+        None => return Ok(Vec::new()),
+    };
+    if !repository.url.starts_with("https://") && !repository.url.starts_with("http://") {
+        warning!(IgnoredSetting, "auth of {} only applies to http(s) urls, it is ignored", repository.name);
+        return Ok(Vec::new());
+    }
+
+    let token = match &auth.source {
+        SecretSource::Env(variable) => env::var(variable).map_err(|_| AppError::ExternalError(format!(
+            "The token of {} is read from ${}, which is not set.", repository.name, variable
+        )))?,
+        SecretSource::Keyring { service, account } => keyring_secret(service, account).map_err(|reason| {
+            AppError::ExternalError(format!(
+                "Cannot read the keyring entry {}/{} of {}: {}.", service, account, repository.name, reason
+            ))
+        })?,
+    };
+    let credentials = format!("{}:{}", auth.username.as_deref().unwrap_or(DEFAULT_USERNAME), token.trim_end());
+
+    Ok(vec![
+        ("GIT_CONFIG_COUNT".to_owned(), "1".to_owned()),
+        ("GIT_CONFIG_KEY_0".to_owned(), format!("http.{}.extraHeader", repository.url)),
+        ("GIT_CONFIG_VALUE_0".to_owned(), format!("Authorization: Basic {}", base64(credentials.as_bytes()))),
+    ])
+}
+
+/// Reads a secret from the OS keyring with the tool of the platform: `security` on macOS,
+/// `secret-tool` from libsecret elsewhere.
+fn keyring_secret(service: &str, account: &str) -> Result<String, String> {
+    let (tool, args) = if cfg!(target_os = "macos") {
+        ("security", vec!["find-generic-password", "-s", service, "-a", account, "-w"])
+    } else if cfg!(unix) {
+        ("secret-tool", vec!["lookup", "service", service, "account", account])
+    } else {
+        return Err("no keyring is supported on this platform, use token_env instead".to_owned());
+    };
+
+    let output = traced_output(Command::new(tool)
+        .args(&args)
+        .stdin(Stdio::null()))
+        .map_err(|e| format!("{} is not available ({}), use token_env instead", tool, e))?;
+    let secret = String::from_utf8_lossy(&output.stdout).trim_end().to_owned();
+    if !output.status.success() || secret.is_empty() {
+        return Err(format!("{} found no such entry, or the keyring is locked", tool));
+    }
+    Ok(secret)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::base64;
+
+    #[test]
+    fn encodes_with_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"x-access-token:secret"), "eC1hY2Nlc3MtdG9rZW46c2VjcmV0");
+    }
+}
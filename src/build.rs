@@ -0,0 +1,95 @@
+use std::fs::create_dir_all;
+use crate::{
+    info,
+    AppError,
+    hooks::run_hook,
+    model::{Project, build_levels},
+    scheduler::{CancellationToken, ProcessLimit, Scheduler, Task},
+};
+use std::sync::Arc;
+
+/// Builds the repositories in `build_depends_on` order, running up to `jobs` independent builds
+/// at once, each holding a permit of `limit`. Each build writes its output to
+/// `<logs>/build/<repository>.log`. Builds depending on a failed one are not started.
+pub fn build(p: &Project, jobs: usize, limit: Option<Arc<ProcessLimit>>) -> Result<(), AppError> {
+    let src = p.src_path();
+    let logs = p.logs_path().join("build");
+    let levels = build_levels(&p.repositories).map_err(AppError::Parsing)?;
+    let scheduler = Scheduler::new(jobs).with_limit(limit);
+
+    // Repositories whose build failed, or was not started because one they depend on failed.
+    let mut failed: Vec<&str> = Vec::new();
+    let mut errors = Vec::new();
+    for level in 0..=levels.iter().copied().max().unwrap_or(0) {
+        let mut repositories = Vec::new();
+        for (repository, _) in p.repositories.iter().zip(&levels).filter(|(_, repository_level)| **repository_level == level) {
+            match repository.build_depends_on.iter().find(|dependency| failed.contains(&dependency.as_str())) {
+                Some(dependency) => {
+                    if repository.build.is_some() {
+                        info!("Skipping the build of {}, the build of its dependency {} failed", repository.name, dependency);
+                    }
+                    failed.push(&repository.name);
+                }
+                None => repositories.extend(repository.build.as_ref().map(|build| (repository, build))),
+            }
+        }
+        let tasks = repositories.iter()
+            .map(|(repository, build)| {
+                let working_dir = src.join(&repository.name);
+                let log = logs.join(format!("{}.log", repository.name));
+                Box::new(move |_: &CancellationToken| {
+                    info!("Building {}", repository.name);
+                    let log_dir = log.parent().unwrap_or(&log);
+                    create_dir_all(log_dir)
+                        .map_err(|e| AppError::IOError(log_dir.to_str().unwrap().to_owned(), Arc::new(e)))?;
+                    run_hook("build", &[build.to_string()], &[], &working_dir, &log)
+                }) as Task<Result<(), AppError>>
+            })
+            .collect();
+
+        // Lets the whole level finish, so independent builds are not left half done.
+        for ((repository, _), result) in repositories.iter().zip(scheduler.run(tasks)) {
+            if let Some(Err(err)) = result {
+                failed.push(&repository.name);
+                errors.push((repository.name.as_str(), err));
+            }
+        }
+    }
+
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0).1),
+        _ => {
+            for (name, err) in &errors {
+                info!("The build of {} failed: {}", name, err);
+            }
+            let names = errors.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            Err(AppError::ExternalError(format!("The builds of {} failed.", names.join(", "))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yaml_rust::YamlLoader;
+    use crate::model::Project;
+    use super::build;
+
+    #[test]
+    fn keeps_building_the_repositories_independent_of_a_failed_one() {
+        let dir = std::env::temp_dir().join(format!("replikate-build-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = "repositories:\n  - url: https://example.com/broken.git\n    build: \"false\"\n\
+            \x20 - url: https://example.com/user.git\n    build: touch built\n    build_depends_on: broken\n\
+            \x20 - url: https://example.com/other.git\n    build: touch built\n";
+        let p = Project::from_config(&YamlLoader::load_from_str(config).unwrap()[0], &dir).unwrap().set_path(dir.to_str().unwrap());
+        for repository in ["broken", "user", "other"] {
+            std::fs::create_dir_all(p.src_path().join(repository)).unwrap();
+        }
+
+        assert!(build(&p, 1, None).is_err());
+        assert!(p.src_path().join("other/built").exists());
+        assert!(!p.src_path().join("user/built").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
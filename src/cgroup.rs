@@ -0,0 +1,239 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    process::Command,
+};
+
+/// Period, in microseconds, over which the CPU time of a `cpu_limit` is shared out.
+#[cfg(target_os = "linux")]
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// Limits of an experiment the kernel enforces on it and on every process it starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// Bytes of memory, swap included, beyond which the experiment is killed.
+    pub memory: Option<u64>,
+    /// Average number of cores the experiment may keep busy, it is throttled beyond.
+    pub cpus: Option<f64>,
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_none() && self.cpus.is_none()
+    }
+}
+
+/// A cgroup v2 of its own, created for one run of an experiment under the cgroup replikate started
+/// in, next to the `replikate` leaf it moves into, and removed when dropped along with the processes still in it.
+pub struct Cgroup {
+    path: PathBuf,
+    /// `cgroup.procs`, opened before the command starts so that the child joins the cgroup
+    /// before running anything.
+    procs: File,
+}
+
+impl Cgroup {
+    /// Creates a cgroup enforcing `limits` and makes `command` start inside it. Fails with the
+    /// reason when cgroups v2 are not available, as on other systems than Linux, or cannot be
+    /// created without privileges.
+    #[cfg(target_os = "linux")]
+    pub fn confine(command: &mut Command, name: &str, limits: Limits) -> Result<Cgroup, String> {
+        use std::{
+            os::unix::{io::AsRawFd, process::CommandExt},
+            path::Path,
+            sync::{atomic::{AtomicUsize, Ordering}, Mutex},
+        };
+
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        // The cgroup replikate started in, once replikate moved out of it.
+        static PARENT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+        // On hybrid hierarchies the v2 one is only mounted aside, without controllers.
+        if !Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+            return Err("cgroups v2 are not mounted at /sys/fs/cgroup".to_owned());
+        }
+        let mut moved = PARENT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let parent = match moved.as_ref() {
+            Some(parent) => parent.clone(),
+            None => {
+                let own = fs::read_to_string("/proc/self/cgroup").map_err(|e| format!("cannot read /proc/self/cgroup ({})", e))?;
+                let own = own.lines()
+                    .find_map(|line| line.strip_prefix("0::"))
+                    .ok_or_else(|| "cgroups v2 are not mounted".to_owned())?;
+                let (parent, leaf) = hierarchy(Path::new("/sys/fs/cgroup"), own);
+                // A cgroup with processes cannot enable controllers for its children, but the
+                // root one: replikate first moves into a leaf of its own.
+                if let Some(leaf) = leaf {
+                    if !leaf.exists() {
+                        fs::create_dir(&leaf).map_err(|e| format!("cannot create {} ({})", leaf.display(), e))?;
+                    }
+                    fs::write(leaf.join("cgroup.procs"), "0").map_err(|e| {
+                        format!("cannot move replikate into {} ({})", leaf.display(), e)
+                    })?;
+                }
+                *moved = Some(parent.clone());
+                parent
+            }
+        };
+
+        // Controllers must be enabled in the parent for its children to get their files.
+        let controllers = controllers(limits);
+        let available = fs::read_to_string(parent.join("cgroup.controllers")).unwrap_or_default();
+        if let Some(controller) = controllers.iter().find(|controller| !available.split_whitespace().any(|other| other == **controller)) {
+            return Err(format!("the {} controller is not available in {}", controller, parent.display()));
+        }
+        let enabled = fs::read_to_string(parent.join("cgroup.subtree_control")).map_err(|e| {
+            format!("cannot read the controllers of {} ({})", parent.display(), e)
+        })?;
+        let missing = missing_controllers(&controllers, &enabled);
+        if !missing.is_empty() {
+            fs::write(parent.join("cgroup.subtree_control"), missing.join(" ")).map_err(|e| {
+                format!("cannot enable the {} controller(s) in {} ({})", controllers.join(" and "), parent.display(), e)
+            })?;
+        }
+        drop(moved);
+
+        let path = parent.join(format!(
+            "replikate-{}-{}-{}", std::process::id(), CREATED.fetch_add(1, Ordering::SeqCst), name.replace('/', "_")
+        ));
+        fs::create_dir(&path).map_err(|e| format!("cannot create {} ({})", path.display(), e))?;
+        let procs = File::options().write(true).open(path.join("cgroup.procs"));
+        let cgroup = match procs {
+            Ok(procs) => Cgroup { path, procs },
+            Err(e) => {
+                let _ = fs::remove_dir(&path);
+                return Err(format!("cannot open {} ({})", path.join("cgroup.procs").display(), e));
+            }
+        };
+
+        let write = |file: &str, value: String| fs::write(cgroup.path.join(file), value).map_err(|e| {
+            format!("cannot write {} ({})", cgroup.path.join(file).display(), e)
+        });
+        if let Some(memory) = limits.memory {
+            write("memory.max", memory.to_string())?;
+            // Without swap the limit holds exactly, the file is missing when swap is not accounted.
+            let _ = write("memory.swap.max", "0".to_owned());
+        }
+        if let Some(cpus) = limits.cpus {
+            let quota = ((cpus * CPU_PERIOD_US as f64) as u64).max(1000);
+            write("cpu.max", format!("{} {}", quota, CPU_PERIOD_US))?;
+        }
+
+        let procs = cgroup.procs.as_raw_fd();
+        // SAFETY: only write(2) runs between fork and exec, which is async-signal-safe. Writing 0
+        // to `cgroup.procs` moves the writing process, here the child, into the cgroup.
+        unsafe {
+            command.pre_exec(move || match libc::write(procs, b"0".as_ptr() as *const libc::c_void, 1) {
+                1 => Ok(()),
+                _ => Err(std::io::Error::last_os_error()),
+            });
+        }
+        Ok(cgroup)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn confine(_command: &mut Command, _name: &str, _limits: Limits) -> Result<Cgroup, String> {
+        Err("cgroups are only supported on Linux".to_owned())
+    }
+
+    /// Limits the experiment ran into: `memory` when the kernel killed one of its processes for
+    /// running out of it, `cpu` when it was throttled.
+    pub fn limits_hit(&self) -> Vec<String> {
+        let mut hit = Vec::new();
+        if self.counter("memory.events", "oom_kill") > 0 {
+            hit.push("memory".to_owned());
+        }
+        if self.counter("cpu.stat", "nr_throttled") > 0 {
+            hit.push("cpu".to_owned());
+        }
+        hit
+    }
+
+    /// Value of `key` in the flat keyed `file` of the cgroup, 0 when missing.
+    fn counter(&self, file: &str, key: &str) -> u64 {
+        fs::read_to_string(self.path.join(file)).unwrap_or_default()
+            .lines()
+            .find_map(|line| match line.split_once(' ') {
+                Some((name, value)) if name == key => value.trim().parse().ok(),
+                _ => None,
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// Cgroup under which the experiment cgroups are created, for a process in the cgroup `own` of
+/// the hierarchy mounted at `root`, and the leaf that process must move into first. There is none
+/// in the root cgroup, which may enable controllers while it has processes.
+#[cfg(target_os = "linux")]
+fn hierarchy(root: &std::path::Path, own: &str) -> (PathBuf, Option<PathBuf>) {
+    match own.trim().trim_matches('/') {
+        "" => (root.to_owned(), None),
+        own => {
+            let parent = root.join(own);
+            let leaf = parent.join("replikate");
+            (parent, Some(leaf))
+        }
+    }
+}
+
+/// Controllers the experiment cgroups need for `limits`.
+#[cfg(target_os = "linux")]
+fn controllers(limits: Limits) -> Vec<&'static str> {
+    let mut controllers = Vec::new();
+    if limits.memory.is_some() {
+        controllers.push("memory");
+    }
+    if limits.cpus.is_some() {
+        controllers.push("cpu");
+    }
+    controllers
+}
+
+/// Lines to write to `cgroup.subtree_control` to enable the `controllers` not among `enabled`,
+/// its current content.
+#[cfg(target_os = "linux")]
+fn missing_controllers(controllers: &[&str], enabled: &str) -> Vec<String> {
+    controllers.iter()
+        .filter(|controller| !enabled.split_whitespace().any(|other| other == **controller))
+        .map(|controller| format!("+{}", controller))
+        .collect()
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // Processes that left the process group of the experiment are still in its cgroup. The
+        // `cgroup.kill` file needs Linux 5.14.
+        let _ = fs::write(self.path.join("cgroup.kill"), "1");
+        for _ in 0..50 {
+            if fs::remove_dir(&self.path).is_ok() {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use super::{controllers, hierarchy, missing_controllers, Limits};
+
+    #[test]
+    fn creates_the_experiment_cgroups_next_to_the_leaf_of_replikate() {
+        let root = Path::new("/sys/fs/cgroup");
+        assert_eq!(
+            hierarchy(root, "/user.slice/session-2.scope\n"),
+            (root.join("user.slice/session-2.scope"), Some(root.join("user.slice/session-2.scope/replikate")))
+        );
+        assert_eq!(hierarchy(root, "/"), (PathBuf::from("/sys/fs/cgroup"), None));
+    }
+
+    #[test]
+    fn enables_only_the_missing_controllers() {
+        let both = controllers(Limits { memory: Some(1 << 20), cpus: Some(0.5) });
+        assert_eq!(both, vec!["memory", "cpu"]);
+        assert_eq!(missing_controllers(&both, "cpuset cpu io\n"), vec!["+memory".to_owned()]);
+        assert_eq!(missing_controllers(&both, "memory cpu"), Vec::<String>::new());
+        assert!(controllers(Limits::default()).is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    fs::{read_dir, remove_dir_all},
+    time::{Duration, SystemTime},
+};
+use crate::{
+    info,
+    AppError,
+    RESERVED_RUN_IDS,
+    logs::latest_run,
+    model::Project,
+};
+use std::sync::Arc;
+
+pub fn clean(p: &Project) -> Result<(), AppError> {
+    let logs = p.logs_path();
+
+    if logs.exists() {
+        remove_dir_all(&logs)
+            .map_err(|e| AppError::IOError(logs.to_str().unwrap().to_owned(), Arc::new(e)))?;
+    }
+
+    Ok(())
+}
+
+/// Removes the run folders of the logs of `p`, but the `keep_last` most recent ones and those
+/// modified less than `older_than` ago. With both, a run is kept when either keeps it; the runs
+/// named in `pinned` always are, and so is the latest run unless `force`. With `dry_run`, only
+/// lists what would go.
+pub fn prune(
+    p: &Project, keep_last: Option<usize>, older_than: Option<Duration>, pinned: &[String], force: bool, dry_run: bool,
+) -> Result<(), AppError> {
+    let logs = p.logs_path();
+    if !logs.exists() {
+        return Ok(());
+    }
+    let into_err = |e| AppError::IOError(logs.to_str().unwrap().to_owned(), Arc::new(e));
+
+    // Any other folder is a run, but those of experiments logged before run IDs existed.
+    let mut runs = Vec::new();
+    for entry in read_dir(&logs).map_err(into_err)? {
+        let entry = entry.map_err(into_err)?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let reserved = RESERVED_RUN_IDS.contains(&name.as_str()) || p.experiments.iter().any(|exp| exp.log_folder() == name);
+        if entry.file_type().map_err(into_err)?.is_dir() && !reserved {
+            let modified = entry.metadata().and_then(|metadata| metadata.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+            runs.push((modified, name));
+        }
+    }
+    // Most recent first.
+    runs.sort_by(|a, b| b.cmp(a));
+
+    let latest = latest_run(&logs);
+    let now = SystemTime::now();
+    for (index, (modified, name)) in runs.iter().enumerate() {
+        let recent = keep_last.map(|keep| index < keep);
+        let young = older_than.map(|age| now.duration_since(*modified).map(|elapsed| elapsed < age).unwrap_or(true));
+        let kept = recent.unwrap_or(false) || young.unwrap_or(false) || pinned.contains(name) || (!force && latest.as_ref() == Some(name));
+        if kept {
+            continue;
+        }
+        let run = logs.join(name);
+        if dry_run {
+            println!("{}", run.display());
+        } else {
+            info!("Removing the run {}", name);
+            remove_dir_all(&run).map_err(|e| AppError::IOError(run.to_str().unwrap().to_owned(), Arc::new(e)))?;
+        }
+    }
+
+    Ok(())
+}
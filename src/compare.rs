@@ -0,0 +1,156 @@
+use crate::{
+    AppError,
+    json::Json,
+    report::read_report,
+};
+
+/// Slowdown, in percent, above which a change of duration is reported by default.
+pub const DEFAULT_THRESHOLD: f64 = 10.0;
+
+/// Changes of the experiments from one report to another.
+#[derive(Debug, Default, PartialEq)]
+struct Comparison {
+    /// Experiment with its old and new outcome.
+    status_changes: Vec<(String, String, String)>,
+    /// Experiment with its old and new duration in milliseconds, beyond the threshold.
+    slower: Vec<(String, f64, f64)>,
+    faster: Vec<(String, f64, f64)>,
+    added: Vec<String>,
+    removed: Vec<String>,
+    unchanged: usize,
+}
+
+impl Comparison {
+    /// Experiments that stopped succeeding or got slower than the threshold.
+    fn regressions(&self) -> usize {
+        self.status_changes.iter().filter(|(_, old, new)| old == SUCCEEDED && new != SUCCEEDED).count() + self.slower.len()
+    }
+}
+
+const SUCCEEDED: &str = "succeeded";
+
+/// Prints the changes from the report `old` to the report `new`: experiments whose outcome
+/// changed, those slower or faster by more than `threshold` percent, and those added or removed.
+/// Fails when an experiment stopped succeeding or got slower.
+pub fn compare(old: &str, new: &str, threshold: f64) -> Result<(), AppError> {
+    let (old_report, new_report) = (read_report(old)?, read_report(new)?);
+    let comparison = compare_reports(&old_report, &new_report, threshold);
+
+    let described = |path: &str, report: &Json| match report.get("run_id").and_then(Json::as_str) {
+        Some(run_id) => format!("{} (run {})", path, run_id),
+        None => path.to_owned(),
+    };
+    println!("From {} to {}:", described(old, &old_report), described(new, &new_report));
+    if !comparison.status_changes.is_empty() {
+        println!("  outcome changed:");
+        for (name, old, new) in &comparison.status_changes {
+            println!("    {}: {} -> {}", name, old, new);
+        }
+    }
+    for (label, changes) in [("slower", &comparison.slower), ("faster", &comparison.faster)] {
+        if !changes.is_empty() {
+            println!("  {}:", label);
+            for (name, old, new) in changes {
+                println!("    {}: {:.3}s -> {:.3}s ({:+.1}%)", name, old / 1000.0, new / 1000.0, (new - old) / old * 100.0);
+            }
+        }
+    }
+    if !comparison.added.is_empty() {
+        println!("  new: {}", comparison.added.join(", "));
+    }
+    if !comparison.removed.is_empty() {
+        println!("  removed: {}", comparison.removed.join(", "));
+    }
+    println!("  {} unchanged", comparison.unchanged);
+
+    match comparison.regressions() {
+        0 => Ok(()),
+        regressions => Err(AppError::ExternalError(format!("{} regression(s) from {} to {}.", regressions, old, new))),
+    }
+}
+
+fn compare_reports(old: &Json, new: &Json, threshold: f64) -> Comparison {
+    let old = experiments(old);
+    let new = experiments(new);
+    let mut comparison = Comparison::default();
+
+    for (name, old_outcome, old_duration) in &old {
+        let (new_outcome, new_duration) = match new.iter().find(|(other, _, _)| other == name) {
+            Some((_, outcome, duration)) => (outcome, *duration),
+            None => {
+                comparison.removed.push(name.to_owned());
+                continue;
+            }
+        };
+        if old_outcome != new_outcome {
+            comparison.status_changes.push((name.to_owned(), old_outcome.to_owned(), new_outcome.to_owned()));
+            continue;
+        }
+        // Durations of failed runs say little about the experiment itself.
+        let change = (new_duration - old_duration) / old_duration * 100.0;
+        if old_outcome == SUCCEEDED && *old_duration > 0.0 && change > threshold {
+            comparison.slower.push((name.to_owned(), *old_duration, new_duration));
+        } else if old_outcome == SUCCEEDED && *old_duration > 0.0 && -change > threshold {
+            comparison.faster.push((name.to_owned(), *old_duration, new_duration));
+        } else {
+            comparison.unchanged += 1;
+        }
+    }
+    comparison.added = new.iter()
+        .filter(|(name, _, _)| !old.iter().any(|(other, _, _)| other == name))
+        .map(|(name, _, _)| name.to_owned())
+        .collect();
+    comparison
+}
+
+/// Name, outcome and duration in milliseconds of the experiments of `report`. Reports written
+/// before outcomes were recorded only tell whether the experiment succeeded.
+fn experiments(report: &Json) -> Vec<(String, String, f64)> {
+    match report.get("experiments") {
+        Some(Json::Array(entries)) => entries.iter()
+            .filter_map(|entry| {
+                let name = entry.get("name")?.as_str()?.to_owned();
+                let outcome = match (entry.get("outcome").and_then(Json::as_str), entry.get("success")) {
+                    (Some(outcome), _) => outcome.to_owned(),
+                    (None, Some(Json::Bool(true))) => SUCCEEDED.to_owned(),
+                    (None, _) => "failed".to_owned(),
+                };
+                let duration = entry.get("duration_ms").and_then(Json::as_f64).unwrap_or(0.0);
+                Some((name, outcome, duration))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Comparison, compare_reports};
+    use crate::json::Json;
+
+    fn report(experiments: &[(&str, &str, f64)]) -> Json {
+        Json::object(vec![("experiments", Json::Array(experiments.iter()
+            .map(|(name, outcome, duration)| Json::object(vec![
+                ("name", Json::string(name)),
+                ("outcome", Json::string(outcome)),
+                ("duration_ms", Json::Number(*duration)),
+            ]))
+            .collect()))])
+    }
+
+    #[test]
+    fn reports_outcome_and_duration_changes() {
+        let old = report(&[("a", "succeeded", 1000.0), ("b", "succeeded", 1000.0), ("c", "succeeded", 1000.0), ("d", "exit_code", 5.0)]);
+        let new = report(&[("a", "succeeded", 1500.0), ("b", "succeeded", 1050.0), ("c", "timed_out", 9000.0), ("e", "succeeded", 1.0)]);
+        let comparison = compare_reports(&old, &new, 10.0);
+        assert_eq!(comparison, Comparison {
+            status_changes: vec![("c".to_owned(), "succeeded".to_owned(), "timed_out".to_owned())],
+            slower: vec![("a".to_owned(), 1000.0, 1500.0)],
+            faster: Vec::new(),
+            added: vec!["e".to_owned()],
+            removed: vec!["d".to_owned()],
+            unchanged: 1,
+        });
+        assert_eq!(comparison.regressions(), 2);
+    }
+}
@@ -0,0 +1,152 @@
+/// Lines of context kept around each change.
+const CONTEXT: usize = 3;
+
+/// Above this many line pairs the diff is not computed, only the first difference is reported.
+const MAX_COMPARISONS: usize = 25_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Keep(usize, usize),
+    Remove(usize),
+    Add(usize),
+}
+
+/// Unified diff turning `expected` into `actual`, empty when they are identical.
+pub fn unified_diff(expected: &str, actual: &str, expected_name: &str, actual_name: &str) -> String {
+    if expected == actual {
+        return String::new();
+    }
+    let old = expected.lines().collect::<Vec<_>>();
+    let new = actual.lines().collect::<Vec<_>>();
+
+    let mut output = format!("--- {}\n+++ {}\n", expected_name, actual_name);
+    if old.len().saturating_mul(new.len()) > MAX_COMPARISONS {
+        let line = old.iter().zip(new.iter()).take_while(|(old, new)| old == new).count();
+        output.push_str(&format!("too large to diff, first difference at line {}\n", line + 1));
+        return output;
+    }
+
+    let edits = edits(&old, &new);
+    let changes = edits.iter().enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Keep(..)))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if changes.is_empty() {
+        // Only the trailing newline differs.
+        output.push_str("\\ No newline at end of file\n");
+        return output;
+    }
+
+    // Groups the changes closer than twice the context into hunks.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for change in changes {
+        let start = change.saturating_sub(CONTEXT);
+        let end = (change + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let (old_start, new_start) = position(&edits[..start]);
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Add(_))).count();
+        let new_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Remove(_))).count();
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + usize::from(old_count > 0), old_count, new_start + usize::from(new_count > 0), new_count
+        ));
+        for edit in hunk {
+            match *edit {
+                Edit::Keep(line, _) => output.push_str(&format!(" {}\n", old[line])),
+                Edit::Remove(line) => output.push_str(&format!("-{}\n", old[line])),
+                Edit::Add(line) => output.push_str(&format!("+{}\n", new[line])),
+            }
+        }
+    }
+    output
+}
+
+/// Number of old and new lines consumed by `edits`.
+fn position(edits: &[Edit]) -> (usize, usize) {
+    edits.iter().fold((0, 0), |(old, new), edit| match edit {
+        Edit::Keep(..) => (old + 1, new + 1),
+        Edit::Remove(_) => (old + 1, new),
+        Edit::Add(_) => (old, new + 1),
+    })
+}
+
+/// Shortest edit script between `old` and `new` from their longest common subsequence.
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut edits = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Keep(i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            edits.push(Edit::Remove(i));
+            i += 1;
+        } else {
+            edits.push(Edit::Add(j));
+            j += 1;
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    #[test]
+    fn identical_texts_have_no_diff() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "expected", "actual"), "");
+    }
+
+    #[test]
+    fn reports_changed_lines_with_context() {
+        let expected = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        let actual = "1\n2\n3\n4\nfive\n6\n7\n8\n";
+        assert_eq!(
+            unified_diff(expected, actual, "expected", "actual"),
+            "--- expected\n+++ actual\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn reports_added_and_removed_lines() {
+        assert_eq!(
+            unified_diff("a\nb\n", "b\nc\n", "expected", "actual"),
+            "--- expected\n+++ actual\n@@ -1,2 +1,2 @@\n-a\n b\n+c\n"
+        );
+        assert_eq!(
+            unified_diff("", "a\n", "expected", "actual"),
+            "--- expected\n+++ actual\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+
+    #[test]
+    fn distant_changes_get_separate_hunks() {
+        let expected = (1..=20).map(|n| format!("{}\n", n)).collect::<String>();
+        let actual = (1..=20)
+            .map(|n| if n == 2 || n == 19 { format!("changed {}\n", n) } else { format!("{}\n", n) })
+            .collect::<String>();
+        let diff = unified_diff(&expected, &actual, "expected", "actual");
+        assert_eq!(diff.matches("@@ -").count(), 2);
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    fs::read_dir,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Growth in bytes of the folders an experiment writes into, over their size when it started.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DiskUsage {
+    /// Largest growth measured while it ran.
+    pub peak: u64,
+    /// Growth once it ended, what it leaves behind.
+    pub last: u64,
+}
+
+/// Measures the size of folders from another thread until stopped, to find how much an
+/// experiment wrote into them at its peak and in the end.
+pub struct DiskSampler {
+    folders: Vec<PathBuf>,
+    baseline: u64,
+    stop: Sender<()>,
+    sampling: JoinHandle<u64>,
+}
+
+impl DiskSampler {
+    /// Starts measuring `folders` every `interval`. A folder inside another one is only
+    /// counted once, with it.
+    pub fn start(folders: &[&Path], interval: Duration) -> DiskSampler {
+        let mut folders = folders.iter().map(|folder| folder.to_path_buf()).collect::<Vec<_>>();
+        folders.sort();
+        folders.dedup();
+        let folders = folders.iter()
+            .filter(|folder| !folders.iter().any(|other| other != *folder && folder.starts_with(other)))
+            .cloned()
+            .collect::<Vec<_>>();
+        let baseline = total_size(&folders);
+        let (stop, stopped) = mpsc::channel();
+        let sampled = folders.clone();
+        let sampling = thread::spawn(move || {
+            let mut peak = baseline;
+            loop {
+                peak = peak.max(total_size(&sampled));
+                match stopped.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return peak,
+                }
+            }
+        });
+        DiskSampler { folders, baseline, stop, sampling }
+    }
+
+    /// Stops measuring and measures one last time.
+    pub fn stop(self) -> DiskUsage {
+        // The sampling thread only fails to receive once it returned.
+        let _ = self.stop.send(());
+        let peak = self.sampling.join().unwrap_or(self.baseline);
+        let last = total_size(&self.folders);
+        DiskUsage {
+            peak: peak.max(last).saturating_sub(self.baseline),
+            last: last.saturating_sub(self.baseline),
+        }
+    }
+}
+
+fn total_size(folders: &[PathBuf]) -> u64 {
+    folders.iter().map(|folder| dir_size(folder)).sum()
+}
+
+/// Bytes available to unprivileged users on the file system holding `path`, `None` when it
+/// cannot be determined on this platform.
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid NUL terminated string and `stats` is only read once filled in.
+    let stats = unsafe {
+        if libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stats.assume_init()
+    };
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Total size in bytes of the files under `path`, without following symbolic links. Entries that
+/// vanish or cannot be read while walking, as in a folder still being written, are not counted.
+pub fn dir_size(path: &Path) -> u64 {
+    let entries = match read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries.filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
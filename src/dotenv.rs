@@ -0,0 +1,128 @@
+use std::{
+    fs::read_to_string,
+    io::ErrorKind,
+    path::Path,
+};
+use crate::{
+    AppError,
+    artifacts::ARTIFACTS_FOLDER,
+    model::{EnvFile, Experiment, Project},
+};
+use std::sync::Arc;
+
+/// Environment variable receiving the seed of each run when the project declares a `seed`.
+const SEED_VARIABLE: &str = "REPLIKATE_SEED";
+
+/// Environment variable giving each run the absolute path of the artifacts folder of its logs,
+/// where it may write its outputs instead of declaring them.
+const OUTPUT_VARIABLE: &str = "REPLIKATE_OUT";
+
+/// Prefix of the environment variables giving each run the params of its experiment, named in
+/// upper case with dashes turned into underscores.
+const PARAM_PREFIX: &str = "REPLIKATE_PARAM_";
+
+/// Seed of the run numbered `repetition`, counting from 0: the project seed plus the repetition,
+/// wrapping around at 2^64. It is part of the configuration contract and must not change.
+fn repetition_seed(seed: u64, repetition: u64) -> u64 {
+    seed.wrapping_add(repetition)
+}
+
+/// Environment variables set for the run numbered `repetition` of `exp` logging into `log_dir`:
+/// its `REPLIKATE_OUT`, `REPLIKATE_SEED` and params, those of the project `env_file`, then of its
+/// own `env_file`, then the `overrides` of `--env`, then its `env` entries, each overriding the
+/// previous ones.
+pub fn experiment_env(
+    p: &Project, exp: &Experiment, repetition: u64, log_dir: &Path, overrides: &[(String, String)],
+) -> Result<Vec<(String, String)>, AppError> {
+    let artifacts = log_dir.join(ARTIFACTS_FOLDER);
+    let artifacts = std::path::absolute(&artifacts)
+        .map_err(|e| AppError::IOError(artifacts.to_str().unwrap().to_owned(), Arc::new(e)))?;
+    let mut env = vec![(OUTPUT_VARIABLE.to_owned(), artifacts.to_string_lossy().into_owned())];
+    if let Some(seed) = p.seed {
+        env.push((SEED_VARIABLE.to_owned(), repetition_seed(seed, repetition).to_string()));
+    }
+    env.extend(exp.params.iter().map(|(name, value)| (param_variable(name), value.to_owned())));
+    if let Some(env_file) = &p.env_file {
+        env.extend(load_env_file(env_file, Path::new(&p.path))?);
+    }
+    if let Some(env_file) = &exp.env_file {
+        env.extend(load_env_file(env_file, &p.working_dir(exp))?);
+    }
+    env.extend(overrides.iter().cloned());
+    env.extend(exp.env.iter().map(|(name, value)| (name.to_owned(), value.to_owned())));
+    Ok(env)
+}
+
+/// Environment variable holding the param `name`.
+fn param_variable(name: &str) -> String {
+    format!("{}{}", PARAM_PREFIX, name.to_uppercase().replace('-', "_"))
+}
+
+fn load_env_file(env_file: &EnvFile, base: &Path) -> Result<Vec<(String, String)>, AppError> {
+    let path = base.join(&env_file.path);
+    let content = match read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound && env_file.optional => return Ok(Vec::new()),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Err(AppError::MissingEnvFile(path.to_str().unwrap().to_owned()));
+        }
+        Err(err) => return Err(AppError::IOError(path.to_str().unwrap().to_owned(), Arc::new(err))),
+    };
+    parse_env(&content)
+        .map_err(|line| AppError::ExternalError(format!("{}:{}: expected KEY=VALUE.", path.display(), line)))
+}
+
+/// `KEY=VALUE` lines of a `.env` file, or the number of the first invalid line. Blank lines and
+/// `#` comments are skipped, an `export ` prefix is allowed and values may be quoted. Unquoted
+/// values end at a ` #` comment.
+fn parse_env(content: &str) -> Result<Vec<(String, String)>, usize> {
+    let mut variables = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line.split_once('=').ok_or(index + 1)?;
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(index + 1);
+        }
+
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => value[1..].strip_suffix(quote).ok_or(index + 1)?,
+            _ => value.split(" #").next().unwrap_or_default().trim_end(),
+        };
+        variables.push((name.to_owned(), value.to_owned()));
+    }
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{param_variable, parse_env};
+
+    #[test]
+    fn parses_assignments_and_skips_comments() {
+        let content = "# secrets\nTOKEN=abc\n\nexport URL = http://host/#anchor # comment\nQUOTED=\"a # b\"\nEMPTY=\n";
+        assert_eq!(parse_env(content).unwrap(), vec![
+            ("TOKEN".to_owned(), "abc".to_owned()),
+            ("URL".to_owned(), "http://host/#anchor".to_owned()),
+            ("QUOTED".to_owned(), "a # b".to_owned()),
+            ("EMPTY".to_owned(), String::new()),
+        ]);
+    }
+
+    #[test]
+    fn reports_the_first_invalid_line() {
+        assert_eq!(parse_env("A=1\nnot an assignment\n"), Err(2));
+        assert_eq!(parse_env("A=\"unterminated\n"), Err(1));
+    }
+
+    #[test]
+    fn names_params_in_upper_case() {
+        assert_eq!(param_variable("n-runs"), "REPLIKATE_PARAM_N_RUNS");
+        assert_eq!(param_variable("file"), "REPLIKATE_PARAM_FILE");
+    }
+}
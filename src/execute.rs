@@ -0,0 +1,1495 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    env,
+    fs::{File, copy, create_dir_all, hard_link, read_dir, read_to_string, remove_file, rename, write},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use chrono::{DateTime, Local};
+use wait_timeout::ChildExt;
+use crate::{
+    info,
+    warning,
+    AppError,
+    artifacts::ARTIFACTS_FOLDER,
+    cgroup::{Cgroup, Limits},
+    disk::{DiskSampler, DiskUsage},
+    dotenv::experiment_env,
+    glob::glob,
+    golden::check_golden,
+    hooks::{GroupHooks, run_hook},
+    inputs::verify_inputs,
+    logs::{latest_run, normalize_log, record_run},
+    metrics::{Metrics, metric_cell, metric_text, read_metrics},
+    process::{catch_interrupt, interrupted, isolate, kill_group},
+    report::write_result,
+    profile::{PROFILING_SUPPORTED, ResourceUsage, try_wait_with_usage, wait_with_usage},
+    units::format_size,
+    model::{Capture, Golden, Project, Experiment, ParsingError, SCRIPT_PLACEHOLDER, StdinSource, dependency_levels, split_words},
+    tsv::{TSVHeader, TSVSerializable, TSVTable},
+    trace::{trace_exit, trace_start},
+    scheduler::{CancellationToken, ProcessLimit, Scheduler, Slot, Task},
+};
+use std::sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}};
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum LogMode {
+    /// Each run replaces `stdout.log` and `stderr.log`.
+    #[default]
+    Overwrite,
+    /// Each run writes its own `run-<timestamp>.log`; older runs beyond `keep_runs` files or
+    /// `keep_size` bytes in total are pruned.
+    Append { keep_runs: Option<usize>, keep_size: Option<u64> },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionOptions {
+    pub log_mode: LogMode,
+    /// Number of experiments run concurrently, at least one.
+    pub jobs: usize,
+    /// Cancels the remaining experiments once this many failed, `Some(1)` for `--fail-fast`.
+    pub max_failures: Option<usize>,
+    /// Replaces the golden files by the output of successful runs instead of comparing them.
+    pub update_golden: bool,
+    /// Records the peak memory and CPU time of each experiment into `profile.tsv`.
+    pub profile: bool,
+    /// Converts the captured logs to UTF-8 with LF line endings once the experiment exited,
+    /// instead of keeping its output byte for byte.
+    pub normalize_logs: bool,
+    /// Wall-clock limit of the run: experiments estimated to end after it are not started, and
+    /// those still running when it passes are stopped.
+    pub deadline: Option<Instant>,
+    /// Run time beyond which a successful experiment is reported as slow, unless it sets its
+    /// own `soft_timeout`.
+    pub soft_timeout: Option<Duration>,
+    /// `--max-parallel`, shared with the other phases: each running experiment holds a permit.
+    pub limit: Option<Arc<ProcessLimit>>,
+    /// Experiment names in the order a previous run started them, which `--replay` starts them
+    /// in again. Experiments missing from it start after the others.
+    pub replay: Option<Vec<String>>,
+    /// Variables of `--env` set for every experiment, below its own `env`.
+    pub env: Vec<(String, String)>,
+    /// Experiments reported as skipped instead of being run, with the reason, as those needing an
+    /// optional requirement that is not satisfied.
+    pub skipped: BTreeMap<String, String>,
+}
+
+/// Interval at which running experiments check their timeout and cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often `--profile` measures the disk usage of a running experiment.
+const DISK_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Why an experiment ended the way it did.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExperimentOutcome {
+    Succeeded,
+    /// Exited with this non-zero code.
+    Failed(i32),
+    TimedOut,
+    /// Killed by this signal, `None` when the platform does not tell which.
+    Signaled(Option<i32>),
+    /// The program could not be started, with the reason.
+    SpawnFailed(String),
+    /// Exited successfully but its output differs from the golden file, or could not be compared
+    /// to it, with the reason.
+    GoldenMismatch(String),
+    /// Killed because the run was stopped by `--fail-fast` or `--max-failures`.
+    Cancelled,
+    /// Not started because it would not have finished before the `--deadline`.
+    SkippedForTime,
+    /// Killed because it was still running at the `--deadline`.
+    DeadlineReached,
+    /// Not started because this dependency did not succeed.
+    Blocked(String),
+    /// Exited successfully but its `check` did not, with the reason.
+    CheckFailed(String),
+    /// Not started because the setup of this group failed.
+    SetupFailed(String),
+    /// Not started because one of its `inputs` is missing or does not match its checksum, with
+    /// the reason.
+    InputRejected(String),
+    /// Not started because its environment could not be prepared, as for a malformed `env_file`,
+    /// with the reason.
+    EnvFailed(String),
+    /// Not started on purpose, as for an optional requirement that is not satisfied, with the
+    /// reason.
+    Skipped(String),
+    /// Started, but could not be waited for or its `outputs` could not be collected, with the
+    /// reason.
+    Errored(String),
+}
+
+impl ExperimentOutcome {
+    /// Stable identifier of the outcome, recorded in `result.tsv` and the reports.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ExperimentOutcome::Succeeded => "succeeded",
+            ExperimentOutcome::Failed(_) => "exit_code",
+            ExperimentOutcome::TimedOut => "timed_out",
+            ExperimentOutcome::Signaled(_) => "signaled",
+            ExperimentOutcome::SpawnFailed(_) => "spawn_failed",
+            ExperimentOutcome::GoldenMismatch(_) => "golden_mismatch",
+            ExperimentOutcome::Cancelled => "cancelled",
+            ExperimentOutcome::SkippedForTime => "skipped_for_time",
+            ExperimentOutcome::DeadlineReached => "deadline_reached",
+            ExperimentOutcome::Blocked(_) => "blocked",
+            ExperimentOutcome::CheckFailed(_) => "check_failed",
+            ExperimentOutcome::SetupFailed(_) => "setup_failed",
+            ExperimentOutcome::InputRejected(_) => "input_rejected",
+            ExperimentOutcome::EnvFailed(_) => "env_failed",
+            ExperimentOutcome::Skipped(_) => "skipped",
+            ExperimentOutcome::Errored(_) => "errored",
+        }
+    }
+}
+
+impl std::fmt::Display for ExperimentOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ExperimentOutcome::Succeeded => write!(f, "succeeded"),
+            ExperimentOutcome::Failed(code) => write!(f, "exit code {}", code),
+            ExperimentOutcome::TimedOut => write!(f, "timed out"),
+            ExperimentOutcome::Signaled(Some(signal)) => write!(f, "terminated by signal {}", signal),
+            ExperimentOutcome::Signaled(None) => write!(f, "terminated by a signal"),
+            ExperimentOutcome::SpawnFailed(reason) => write!(f, "could not be started: {}", reason),
+            ExperimentOutcome::GoldenMismatch(reason) => write!(f, "{}", reason),
+            ExperimentOutcome::Cancelled => write!(f, "cancelled"),
+            ExperimentOutcome::SkippedForTime => write!(f, "skipped, it would not finish before the deadline"),
+            ExperimentOutcome::DeadlineReached => write!(f, "stopped at the deadline"),
+            ExperimentOutcome::Blocked(dependency) => write!(f, "blocked, its dependency {} did not succeed", dependency),
+            ExperimentOutcome::CheckFailed(reason) => write!(f, "rejected by its check: {}", reason),
+            ExperimentOutcome::SetupFailed(group) => write!(f, "not started, the setup of its group {} failed", group),
+            ExperimentOutcome::InputRejected(reason) => write!(f, "not started: {}", reason),
+            ExperimentOutcome::EnvFailed(reason) => write!(f, "not started, its environment could not be set up: {}", reason),
+            ExperimentOutcome::Skipped(reason) => write!(f, "skipped, {}", reason),
+            ExperimentOutcome::Errored(reason) => write!(f, "failed: {}", reason),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExperimentResult {
+    pub name: String,
+    /// Command that was run, its template filled with the experiment's `params` and wrapped by
+    /// its executor.
+    pub command: String,
+    pub params: BTreeMap<String, String>,
+    /// Inline script the command ran.
+    pub script: Option<String>,
+    pub start: DateTime<Local>,
+    pub duration: Duration,
+    /// Exit code, `None` when the experiment did not exit by itself.
+    pub status: Option<i32>,
+    pub outcome: ExperimentOutcome,
+    /// Whether the experiment was given a `stdin`.
+    pub stdin: bool,
+    /// Streams recorded into the logs.
+    pub capture: Capture,
+    /// Runs needed to get this result, more than one after retries.
+    pub attempts: u32,
+    /// `nice` value and `cpus` the command was run with, none where they are not supported.
+    pub nice: Option<i32>,
+    pub cpus: Vec<usize>,
+    /// Nothing was captured while something was, and neither `outputs` nor a `check` are declared
+    /// nor anything written into `REPLIKATE_OUT`.
+    pub no_output: bool,
+    /// Measured under `--profile`.
+    pub usage: Option<ResourceUsage>,
+    /// Growth of its working directory and artifacts folder, measured under `--profile`.
+    /// Experiments sharing a working directory count what the others write meanwhile.
+    pub disk: Option<DiskUsage>,
+    /// Log file holding the experiment's stderr.
+    pub stderr: PathBuf,
+    /// When and on which worker `run_all` ran the experiment, retries included.
+    pub slot: Option<Slot>,
+    /// Processes the experiment started were still running once it ended, and were killed.
+    pub leftovers_killed: bool,
+    /// Soft timeout the experiment succeeded after, if it ran longer than it.
+    pub slow: Option<Duration>,
+    /// Limits of its cgroup the experiment ran into, `memory` or `cpu`.
+    pub limits_hit: Vec<String>,
+    /// Read from its `result_file` once it succeeded.
+    pub metrics: Metrics,
+    /// Its experiment is `allow_failure`.
+    pub allow_failure: bool,
+}
+
+impl ExperimentResult {
+    pub fn success(&self) -> bool {
+        self.outcome == ExperimentOutcome::Succeeded
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.outcome == ExperimentOutcome::Cancelled
+    }
+
+    /// Whether the experiment did not run because a dependency failed, a knock-on effect rather
+    /// than a failure of its own.
+    pub fn blocked(&self) -> bool {
+        matches!(self.outcome, ExperimentOutcome::Blocked(_))
+    }
+
+    /// Whether the experiment was not run on purpose, which is no failure.
+    pub fn skipped(&self) -> bool {
+        matches!(self.outcome, ExperimentOutcome::Skipped(_))
+    }
+
+    /// Whether the experiment failed by itself, rather than being blocked, cancelled or skipped.
+    pub fn failed(&self) -> bool {
+        !self.success() && !self.cancelled() && !self.blocked() && !self.skipped()
+    }
+
+    /// Whether the experiment failed in a way that fails the run: by itself and without being
+    /// `allow_failure`.
+    pub fn counts_as_failure(&self) -> bool {
+        self.failed() && !self.allow_failure
+    }
+
+    /// Result of `exp` when it was not started at all.
+    fn not_started(exp: &Experiment, log_dir: &Path, outcome: ExperimentOutcome) -> Self {
+        ExperimentResult {
+            name: exp.name.to_owned(),
+            command: exp.command.to_owned(),
+            params: exp.params.clone(),
+            script: exp.script.clone(),
+            start: Local::now(),
+            duration: Duration::ZERO,
+            status: None,
+            outcome,
+            stdin: exp.stdin.is_some(),
+            capture: exp.capture,
+            attempts: 0,
+            nice: None,
+            cpus: Vec::new(),
+            no_output: false,
+            usage: None,
+            disk: None,
+            stderr: log_dir.join("stderr.log"),
+            slot: None,
+            leftovers_killed: false,
+            slow: None,
+            limits_hit: Vec::new(),
+            metrics: Metrics::new(),
+            allow_failure: exp.allow_failure,
+        }
+    }
+}
+
+/// Runs every experiment and returns their results. Failing experiments do not stop the run,
+/// use `check_results` to turn them into an error.
+pub fn execute(p: &Project, options: &ExecutionOptions) -> Result<Vec<ExperimentResult>, AppError> {
+    if options.profile && !PROFILING_SUPPORTED {
+        warning!(UnsupportedPlatform, "--profile is only supported on Unix, experiments are not profiled");
+    }
+    let previous = start_run(p)?;
+    catch_interrupt();
+    let results = with_setup_and_teardown(p, || run_all(p, previous.as_deref(), options))?;
+    if interrupted() {
+        return Err(AppError::ExternalError("Interrupted, the running experiments were stopped.".to_owned()));
+    }
+    Ok(results)
+}
+
+/// Records the run ID of `p` as the latest run, and returns the experiment logs folder of the
+/// run recorded before it, if any: its own when it is run again, as in append mode.
+fn start_run(p: &Project) -> Result<Option<PathBuf>, AppError> {
+    let run_id = match &p.run_id {
+        Some(run_id) => run_id,
+        None => return Ok(None),
+    };
+    let logs = p.logs_path();
+    let previous = latest_run(&logs);
+    record_run(&logs, run_id)?;
+    Ok(previous.map(|previous| logs.join(previous)))
+}
+
+/// Runs the project `setup` commands before `run` and its `teardown` commands after it, even if
+/// the setup or `run` failed. A failing teardown only produces a warning.
+fn with_setup_and_teardown<T>(p: &Project, run: impl FnOnce() -> Result<T, AppError>) -> Result<T, AppError> {
+    let project_path = Path::new(&p.path);
+    let logs = p.run_logs_path();
+
+    let result = if p.setup.is_empty() {
+        run()
+    } else {
+        info!("Running setup");
+        run_hook("setup", &p.setup, &[], project_path, &logs.join("setup.log")).and_then(|_| run())
+    };
+
+    if !p.teardown.is_empty() {
+        info!("Running teardown");
+        if let Err(err) = run_hook("teardown", &p.teardown, &[], project_path, &logs.join("teardown.log")) {
+            warning!(TeardownFailed, "teardown failed: {}", err);
+        }
+    }
+
+    result
+}
+
+/// Runs the experiments in waves: an experiment starts once every experiment it depends on
+/// finished, and is skipped when one of them did not succeed. `previous` holds the experiment
+/// logs of the previous run, used to estimate durations under `--deadline`.
+fn run_all(p: &Project, previous: Option<&Path>, options: &ExecutionOptions) -> Result<Vec<ExperimentResult>, AppError> {
+    let logs = p.run_logs_path();
+    let scheduler = Scheduler::new(options.jobs)
+        .with_resources(p.resources.clone())
+        .with_limit(options.limit.clone())
+        .with_strict_order(options.replay.is_some());
+    let failures = AtomicUsize::new(0);
+    let groups = GroupHooks::new(p);
+    // Flushed as each experiment ends, so a run killed midway keeps the results of the finished ones.
+    let summary_file = logs.join("summary.tsv");
+    let summary = Mutex::new(open_table(&summary_file, &SUMMARY_HEADER, options)?);
+    let experiments = p.experiments.iter().map(|exp| p.wrapped(exp)).collect::<Vec<_>>();
+    if let Some(replay) = &options.replay {
+        let unrecorded = p.experiments.iter()
+            .filter(|exp| !replay.contains(&exp.name))
+            .map(|exp| exp.name.as_str())
+            .collect::<Vec<_>>();
+        if !unrecorded.is_empty() {
+            warning!(ReplayMismatch, "{} did not run in the replayed run, they start after the others", unrecorded.join(", "));
+        }
+    }
+
+    // Indexed by config order, results are reported in that order whatever order they ran in.
+    let mut results: Vec<Option<ExperimentResult>> = p.experiments.iter().map(|_| None).collect();
+    for wave in waves(p).map_err(AppError::Parsing)? {
+        let (skipped, wave): (Vec<_>, Vec<_>) = wave.into_iter().partition(|index| options.skipped.contains_key(&p.experiments[*index].name));
+        for index in skipped {
+            let exp = &p.experiments[index];
+            groups.leave(exp);
+            let outcome = ExperimentOutcome::Skipped(options.skipped[&exp.name].to_owned());
+            let result = ExperimentResult::not_started(exp, &logs.join(exp.log_folder()), outcome);
+            record_result(&summary, &logs.join(exp.log_folder()), &result);
+            results[index] = Some(result);
+        }
+        let succeeded = |name: &str| p.experiments.iter()
+            .position(|exp| exp.name == name)
+            .map(|index| matches!(&results[index], Some(result) if result.success()))
+            .unwrap_or(true);
+
+        let (wave, blocked): (Vec<_>, Vec<_>) = wave.into_iter()
+            .map(|index| (index, p.experiments[index].depends_on.iter().find(|dependency| !succeeded(dependency)).cloned()))
+            .partition(|(_, dependency)| dependency.is_none());
+        for (index, dependency) in blocked {
+            let exp = &p.experiments[index];
+            let dependency = dependency.expect("partitioned on the blocking dependency");
+            info!("Skipping {}, its dependency {} did not succeed", exp.name, dependency);
+            groups.leave(exp);
+            let outcome = ExperimentOutcome::Blocked(dependency);
+            let result = ExperimentResult::not_started(exp, &logs.join(exp.log_folder()), outcome);
+            record_result(&summary, &logs.join(exp.log_folder()), &result);
+            results[index] = Some(result);
+        }
+        let mut wave = wave.into_iter().map(|(index, _)| index).collect::<Vec<_>>();
+        // Waves run one after the other, so the recorded order only reorders each of them.
+        if let Some(replay) = &options.replay {
+            wave.sort_by_key(|index| replay.iter().position(|name| *name == p.experiments[*index].name).unwrap_or(usize::MAX));
+        }
+
+        let tasks = wave.iter()
+            .map(|index| &experiments[*index])
+            .map(|exp| {
+                let working_dir = p.working_dir(exp);
+                let log_dir = logs.join(exp.log_folder());
+                let history = previous.map(|previous| previous.join(exp.log_folder()));
+                let (failures, groups, summary) = (&failures, &groups, &summary);
+                let task = Box::new(move |token: &CancellationToken| {
+                    let unstarted = if interrupted() {
+                        Some(ExperimentOutcome::Cancelled)
+                    } else if !fits_before_deadline(exp, &log_dir, history.as_deref(), options) {
+                        Some(ExperimentOutcome::SkippedForTime)
+                    } else {
+                        None
+                    };
+                    if let Some(outcome) = unstarted {
+                        groups.leave(exp);
+                        let result = ExperimentResult::not_started(exp, &log_dir, outcome);
+                        record_result(summary, &log_dir, &result);
+                        return result;
+                    }
+                    let result = match groups.enter(exp) {
+                        Ok(()) => match verify_inputs(exp, &working_dir) {
+                            Ok(()) => match experiment_env(p, exp, 0, &log_dir, &options.env) {
+                                Ok(env) => run_with_retries(exp, &env, &working_dir, &log_dir, options, token)
+                                    .unwrap_or_else(|err| rejected(exp, &log_dir, ExperimentOutcome::SpawnFailed(reason(&err)))),
+                                Err(err) => rejected(exp, &log_dir, ExperimentOutcome::EnvFailed(reason(&err))),
+                            },
+                            Err(err) => rejected(exp, &log_dir, ExperimentOutcome::InputRejected(reason(&err))),
+                        },
+                        Err(group) => ExperimentResult::not_started(exp, &log_dir, ExperimentOutcome::SetupFailed(group)),
+                    };
+                    groups.leave(exp);
+                    record_result(summary, &log_dir, &result);
+                    if result.counts_as_failure() {
+                        let count = failures.fetch_add(1, Ordering::SeqCst) + 1;
+                        if options.max_failures.map(|max| count >= max).unwrap_or(false) {
+                            token.cancel();
+                        }
+                    }
+                    result
+                });
+                (task as Task<ExperimentResult>, exp.resources.clone())
+            })
+            .collect();
+
+        for (index, result) in wave.into_iter().zip(scheduler.run_requesting(tasks)) {
+            results[index] = result.map(|(result, slot)| ExperimentResult { slot: Some(slot), ..result });
+        }
+    }
+    // The members a stopped run never started cannot tear their group down.
+    groups.finish();
+
+    let aborted = options.max_failures.map(|max| failures.load(Ordering::SeqCst) >= max).unwrap_or(false);
+    if let (true, Some(max)) = (aborted, options.max_failures) {
+        report_aborted(&results, max);
+    }
+    let unfinished = results.iter()
+        .flatten()
+        .filter(|result| matches!(result.outcome, ExperimentOutcome::SkippedForTime | ExperimentOutcome::DeadlineReached))
+        .map(|result| result.name.as_str())
+        .collect::<Vec<_>>();
+    if !unfinished.is_empty() {
+        warning!(DeadlineReached, "the deadline left {} unfinished", unfinished.join(", "));
+    }
+    let slow = results.iter_mut()
+        .flatten()
+        .filter_map(|result| {
+            let exp = p.experiments.iter().find(|exp| exp.name == result.name)?;
+            let soft_timeout = exp.soft_timeout.map(Duration::from_secs).or(options.soft_timeout)?;
+            if !result.success() || result.duration <= soft_timeout {
+                return None;
+            }
+            result.slow = Some(soft_timeout);
+            Some(format!("{} ({:.1}s > {}s)", result.name, result.duration.as_secs_f64(), soft_timeout.as_secs()))
+        })
+        .collect::<Vec<_>>();
+    if !slow.is_empty() {
+        warning!(SlowExperiment, "{} slow experiment(s), longer than their soft timeout: {}", slow.len(), slow.join(", "));
+    }
+    let silent = results.iter()
+        .flatten()
+        .filter(|result| result.success() && result.no_output)
+        .map(|result| result.name.as_str())
+        .collect::<Vec<_>>();
+    if !silent.is_empty() {
+        warning!(
+            NoOutput,
+            "{} succeeded without printing anything and declare no outputs, check that their command does something",
+            silent.join(", ")
+        );
+    }
+    let results = results.into_iter().flatten().collect::<Vec<_>>();
+    if !aborted && results.iter().any(|result| !result.success()) {
+        info!("Summary: {}", summarize(&results));
+    }
+    Ok(results)
+}
+
+/// Counts of `results` telling the experiments that failed from those blocked by a failed
+/// dependency, like `3 succeeded, 1 failed, 2 blocked`.
+pub fn summarize(results: &[ExperimentResult]) -> String {
+    let count = |keep: &dyn Fn(&ExperimentResult) -> bool| results.iter().filter(|result| keep(result)).count();
+    let mut summary = format!(
+        "{} succeeded, {} failed",
+        count(&|result| result.success()),
+        count(&ExperimentResult::counts_as_failure)
+    );
+    let labels = [
+        ("failed (allowed)", count(&|result| result.failed() && result.allow_failure)),
+        ("blocked", count(&|result| result.blocked())),
+        ("cancelled", count(&|result| result.cancelled())),
+        ("skipped", count(&|result| result.skipped())),
+    ];
+    for (label, count) in labels {
+        if count > 0 {
+            summary.push_str(&format!(", {} {}", count, label));
+        }
+    }
+    summary
+}
+
+/// Indices of the experiments of `p` grouped by dependency level, in the order they are started:
+/// each group only starts once the previous one finished, higher priorities first within a group.
+pub fn waves(p: &Project) -> Result<Vec<Vec<usize>>, ParsingError> {
+    let levels = dependency_levels(&p.experiments)?;
+    Ok((0..=levels.iter().copied().max().unwrap_or(0))
+        .map(|level| {
+            let mut wave = (0..p.experiments.len()).filter(|index| levels[*index] == level).collect::<Vec<_>>();
+            // Stable sort, equal priorities keep the config order.
+            wave.sort_by_key(|index| Reverse(p.experiments[*index].priority));
+            wave
+        })
+        .filter(|wave| !wave.is_empty())
+        .collect())
+}
+
+/// Summarizes a run stopped after `max` failures by `--fail-fast` or `--max-failures`.
+fn report_aborted(results: &[Option<ExperimentResult>], max: usize) {
+    let finished = results.iter().flatten().collect::<Vec<_>>();
+    let not_started = results.iter().filter(|result| result.is_none()).count();
+    let cancelled = finished.iter().filter(|result| result.cancelled()).count();
+    let blocked = finished.iter().filter(|result| result.blocked()).count();
+    let skipped = finished.iter().filter(|result| result.skipped()).count();
+    let failed = finished.iter().filter(|result| result.failed()).count();
+
+    info!(
+        "Stopped after {} failure(s): {} succeeded, {} failed, {} blocked, {} cancelled, {} skipped, {} not started",
+        max, finished.len() - failed - cancelled - blocked - skipped, failed, blocked, cancelled, skipped, not_started
+    );
+}
+
+/// Fails with the experiments that failed by themselves, leaving out those cancelled or blocked
+/// by another failure and those allowed to fail.
+pub fn check_results(results: &[ExperimentResult]) -> Result<(), AppError> {
+    let failures = results.iter()
+        .filter(|result| result.counts_as_failure())
+        .map(|result| result.name.to_owned())
+        .collect::<Vec<_>>();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::ExperimentsFailed(failures))
+    }
+}
+
+/// Runs the project `aggregate` commands from the project folder once the experiments ran,
+/// logging into `aggregate.log` of the run logs. `REPLIKATE_LOGS_DIR` gives them the absolute
+/// path of the experiment logs of the run and `REPLIKATE_REPORT` that of its JSON `report`. They
+/// are skipped when an experiment failed, unless `always`.
+pub fn aggregate(p: &Project, results: &[ExperimentResult], report: &Path, always: bool) -> Result<(), AppError> {
+    if let (Err(AppError::ExperimentsFailed(failed)), false) = (check_results(results), always) {
+        info!("Skipping aggregate, {} failed, use --aggregate-always to run it anyway", failed.join(", "));
+        return Ok(());
+    }
+    let absolute = |path: &Path| std::path::absolute(path)
+        .map_err(|e| AppError::IOError(path.to_str().unwrap().to_owned(), Arc::new(e)));
+    let env = vec![
+        ("REPLIKATE_LOGS_DIR".to_owned(), absolute(&p.run_logs_path())?.to_string_lossy().into_owned()),
+        ("REPLIKATE_REPORT".to_owned(), absolute(report)?.to_string_lossy().into_owned()),
+    ];
+    info!("Running aggregate");
+    run_hook("aggregate", &p.aggregate, &env, Path::new(&p.path), &p.run_logs_path().join("aggregate.log"))
+}
+
+/// Runs `exp` repeatedly, each iteration logging into `<experiment logs>/stress/<iteration>/`,
+/// until an iteration fails or `max_iterations` iterations succeeded.
+pub fn stress(p: &Project, exp: &Experiment, max_iterations: Option<usize>, options: &ExecutionOptions) -> Result<(), AppError> {
+    start_run(p)?;
+    catch_interrupt();
+    with_setup_and_teardown(p, || {
+        let groups = GroupHooks::of(p, std::slice::from_ref(exp));
+        let stressed = groups.enter(exp)
+            .map_err(|group| AppError::ExternalError(format!("The setup of the group {} of {} failed.", group, exp.name)))
+            .and_then(|_| stress_loop(p, exp, max_iterations, options));
+        groups.leave(exp);
+        stressed
+    })
+}
+
+fn stress_loop(p: &Project, exp: &Experiment, max_iterations: Option<usize>, options: &ExecutionOptions) -> Result<(), AppError> {
+    let exp = &p.wrapped(exp);
+    let stress_dir = p.run_logs_path().join(exp.log_folder()).join("stress");
+    verify_inputs(exp, &p.working_dir(exp))?;
+    let mut iteration = 1;
+
+    while max_iterations.map(|max| iteration <= max).unwrap_or(true) {
+        let log_dir = stress_dir.join(iteration.to_string());
+        create_dir_all(&log_dir)
+            .map_err(|e| AppError::IOError(log_dir.to_str().unwrap().to_owned(), Arc::new(e)))?;
+
+        // Each iteration is a repetition with its own seed.
+        let env = experiment_env(p, exp, iteration as u64 - 1, &log_dir, &options.env)?;
+        if !run_experiment(exp, &env, &p.working_dir(exp), &log_dir, options, &CancellationToken::default())?.success() {
+            return Err(AppError::ExternalError(format!(
+                "{} failed at iteration {} after {} successful iteration(s), see {}.",
+                exp.name, iteration, iteration - 1, log_dir.display()
+            )));
+        }
+        iteration += 1;
+    }
+
+    info!("{} succeeded {} time(s) in a row", exp.name, iteration - 1);
+    Ok(())
+}
+
+/// Columns of `summary.tsv`, one row per experiment of the run.
+const SUMMARY_HEADER: [&str; 5] = ["name", "start", "duration_ms", "status", "outcome"];
+
+/// Writes `result.json` into `log_dir` and appends a row to the `summary` of the run, as soon as
+/// an experiment finished or was skipped. Failing to do so only earns a warning, the result is
+/// still reported at the end of the run.
+fn record_result(summary: &Mutex<TSVTable>, log_dir: &Path, result: &ExperimentResult) {
+    let row: Vec<Box<dyn TSVSerializable>> = vec![
+        Box::new(result.name.to_owned()),
+        Box::new(result.start.to_rfc3339()),
+        Box::new(result.duration.as_millis()),
+        Box::new(result.status.map(|code| code.to_string()).unwrap_or_default()),
+        Box::new(result.outcome.kind().to_owned()),
+    ];
+    let recorded = create_dir_all(log_dir)
+        .map_err(|e| AppError::IOError(log_dir.to_str().unwrap().to_owned(), Arc::new(e)))
+        .and_then(|_| write_result(&log_dir.join("result.json"), result))
+        .and_then(|_| summary.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).append(&row));
+    if let Err(err) = recorded {
+        warning!(UnflushedResult, "the result of {} could not be recorded as it ended: {}", result.name, err);
+    }
+}
+
+/// Appends `row` to the table at `path`, opened with `open_table`. A table that cannot be written
+/// only earns a warning, the experiment keeps its outcome.
+fn record_row<H: TSVHeader>(exp: &Experiment, path: &Path, header: &H, row: &[Box<dyn TSVSerializable>], options: &ExecutionOptions) {
+    if let Err(err) = open_table(path, header, options).and_then(|mut table| table.append(row)) {
+        warning!(UnflushedResult, "the result of {} could not be recorded into {}: {}", exp.name, path.display(), err);
+    }
+}
+
+/// Result of `exp` when it could not be started for `outcome`, which is logged.
+fn rejected(exp: &Experiment, log_dir: &Path, outcome: ExperimentOutcome) -> ExperimentResult {
+    info!("Experiment {} {}", exp.name, outcome);
+    ExperimentResult::not_started(exp, log_dir, outcome)
+}
+
+/// `err` as the reason of an outcome, without its final period.
+fn reason(err: &AppError) -> String {
+    err.to_string().trim_end_matches('.').to_owned()
+}
+
+/// Runs `exp` again after a failure, up to its `retries`, as long as the failure is one of its
+/// `retry_on` exit codes. The result is the one of the last run.
+fn run_with_retries(
+    exp: &Experiment,
+    env: &[(String, String)],
+    working_dir: &Path,
+    log_dir: &Path,
+    options: &ExecutionOptions,
+    token: &CancellationToken,
+) -> Result<ExperimentResult, AppError> {
+    let mut attempts = 1;
+    loop {
+        let mut result = run_experiment(exp, env, working_dir, log_dir, options, token)?;
+        result.attempts = attempts;
+        let retry = match &result.outcome {
+            ExperimentOutcome::Succeeded | ExperimentOutcome::Cancelled | ExperimentOutcome::DeadlineReached => false,
+            ExperimentOutcome::Failed(code) => exp.retry_on.is_empty() || exp.retry_on.contains(code),
+            _ => exp.retry_on.is_empty(),
+        };
+        if !retry || attempts > exp.retries || token.is_cancelled() {
+            return Ok(result);
+        }
+        info!("Retrying {} ({}/{}), last run: {}", exp.name, attempts, exp.retries, result.outcome);
+        attempts += 1;
+    }
+}
+
+fn run_experiment(
+    exp: &Experiment,
+    env: &[(String, String)],
+    working_dir: &Path,
+    log_dir: &Path,
+    options: &ExecutionOptions,
+    token: &CancellationToken,
+) -> Result<ExperimentResult, AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    let (mut argv, nice, cpus) = scheduling_prefix(exp);
+    // Commands are checked when parsed, but for the executor wrapping them.
+    match split_words(&exp.command) {
+        Some(words) => argv.extend(words),
+        None => {
+            let outcome = ExperimentOutcome::SpawnFailed(format!("a quote is left open in {}", exp.command));
+            return Ok(rejected(exp, log_dir, outcome));
+        }
+    }
+
+    let start = Local::now();
+    // In append mode both streams go to the same run log, but for a stdout compared to a golden
+    // file: it goes to `stdout.log`, replaced by each run, so that only this run is compared. The
+    // streams left out by `capture` are discarded.
+    let (stdout, stderr, stdout_log, stderr_log) = match options.log_mode {
+        LogMode::Overwrite => {
+            let stdout = log_dir.join("stdout.log");
+            let stderr = log_dir.join("stderr.log");
+            (
+                capture_into(&stdout, exp.capture.stdout())?,
+                capture_into(&stderr, exp.capture.stderr())?,
+                stdout,
+                stderr,
+            )
+        }
+        LogMode::Append { .. } => {
+            let log = log_dir.join(format!("run-{}.log", start.format("%Y%m%d-%H%M%S%.3f")));
+            let file = if exp.capture == Capture::None { None } else { Some(File::create(&log).map_err(into_err(&log))?) };
+            let stream = |captured: bool| match (&file, captured) {
+                (Some(file), true) => file.try_clone().map(Stdio::from).map_err(into_err(&log)),
+                _ => Ok(Stdio::null()),
+            };
+            let (stdout, stdout_log) = if matches!(&exp.golden, Some(Golden { output: None, .. })) {
+                let stdout = log_dir.join("stdout.log");
+                (capture_into(&stdout, true)?, stdout)
+            } else {
+                (stream(exp.capture.stdout())?, log.to_owned())
+            };
+            (stdout, stream(exp.capture.stderr())?, stdout_log, log)
+        }
+    };
+
+    // The commands may write their outputs into `REPLIKATE_OUT` right away.
+    let artifacts = log_dir.join(ARTIFACTS_FOLDER);
+    create_dir_all(&artifacts).map_err(into_err(&artifacts))?;
+
+    let stdin = match &exp.stdin {
+        None => Stdio::null(),
+        Some(StdinSource::Text(_)) => Stdio::piped(),
+        Some(StdinSource::File(file)) => {
+            let file = working_dir.join(file);
+            Stdio::from(File::open(&file).map_err(into_err(&file))?)
+        }
+    };
+
+    let script = match &exp.script {
+        Some(script) => Some(write_script(exp, script)?),
+        None => None,
+    };
+    if let Some(script) = &script {
+        for arg in argv.iter_mut().filter(|arg| *arg == SCRIPT_PLACEHOLDER) {
+            *arg = script.0.to_string_lossy().into_owned();
+        }
+    }
+
+    info!("Running {}", exp.name);
+    let timer = Instant::now();
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..])
+        .current_dir(working_dir)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(stderr);
+    isolate(&mut command);
+    let limits = Limits { memory: exp.memory_limit, cpus: exp.cpu_limit };
+    let cgroup = if limits.is_empty() {
+        None
+    } else {
+        match Cgroup::confine(&mut command, exp.log_folder(), limits) {
+            Ok(cgroup) => Some(cgroup),
+            Err(reason) => {
+                warning!(UnenforcedLimits, "the limits of {} are not enforced, {}", exp.name, reason);
+                None
+            }
+        }
+    };
+    let disk = options.profile.then(|| DiskSampler::start(&[working_dir, &artifacts], DISK_SAMPLE_INTERVAL));
+    trace_start(&command);
+    let spawned = command.spawn();
+
+    let (status, mut outcome, usage, leftovers_killed) = match spawned {
+        Ok(mut child) => {
+            // Written from another thread so a child that does not read it all cannot block the
+            // timeout checks. Write errors only mean the child exited or closed its stdin early.
+            if let (Some(StdinSource::Text(text)), Some(mut pipe)) = (&exp.stdin, child.stdin.take()) {
+                let text = text.to_owned();
+                thread::spawn(move || {
+                    let _ = pipe.write_all(text.as_bytes());
+                });
+            }
+
+            let (exit, usage) = match wait_for(exp, &mut child, &timer, options, token) {
+                Ok(waited) => waited,
+                Err(err) => {
+                    let _ = child.kill().and_then(|_| child.wait());
+                    (Err(ExperimentOutcome::Errored(format!("it could not be waited for: {}", reason(&err)))), None)
+                }
+            };
+            // Whatever the experiment forked and detached must not outlive it.
+            let leftovers_killed = kill_group(child.id());
+            if leftovers_killed {
+                warning!(LeftoverProcesses, "{} left processes running, they were killed", exp.name);
+            }
+            match &exit {
+                Ok(exit) => trace_exit(&command, format!("exited with {}", exit)),
+                Err(stopped) => trace_exit(&command, format!("was killed: {}", stopped)),
+            }
+            let outcome = match &exit {
+                Err(stopped) => stopped.clone(),
+                Ok(exit) => match exit.code() {
+                    Some(0) => ExperimentOutcome::Succeeded,
+                    Some(code) => ExperimentOutcome::Failed(code),
+                    None => ExperimentOutcome::Signaled(signal(exit)),
+                },
+            };
+            (exit.ok().and_then(|exit| exit.code()), outcome, usage, leftovers_killed)
+        }
+        Err(err) => {
+            trace_exit(&command, format!("could not be started: {}", err));
+            (None, ExperimentOutcome::SpawnFailed(format!("{}: {}", argv[0], err)), None, false)
+        }
+    };
+    let duration = timer.elapsed();
+    let disk = disk.map(DiskSampler::stop);
+    let limits_hit = cgroup.map(|cgroup| cgroup.limits_hit()).unwrap_or_default();
+    if limits_hit.iter().any(|limit| limit == "memory") {
+        info!("Experiment {} ran out of its memory limit of {}", exp.name, format_size(exp.memory_limit.unwrap_or_default()));
+    }
+    drop(script);
+
+    if options.normalize_logs && exp.capture != Capture::None {
+        for log in [&stdout_log, &stderr_log] {
+            if let Err(err) = normalize_log(log) {
+                warning!(UnflushedResult, "the log {} of {} could not be normalized: {}", log.display(), exp.name, err);
+            }
+        }
+    }
+
+    match &outcome {
+        ExperimentOutcome::TimedOut => info!("Experiment {} timed out after {}s", exp.name, duration.as_secs()),
+        ExperimentOutcome::Failed(code) => info!("Experiment {} failed with exit code {}", exp.name, code),
+        ExperimentOutcome::Cancelled => info!("Experiment {} was cancelled", exp.name),
+        ExperimentOutcome::DeadlineReached => info!("Experiment {} was {} after {}s", exp.name, outcome, duration.as_secs()),
+        ExperimentOutcome::Signaled(_) => info!("Experiment {} was {}", exp.name, outcome),
+        outcome => info!("Experiment {} {}", exp.name, outcome),
+    }
+    if let Some(usage) = &usage {
+        info!(
+            "Experiment {} peaked at {} of memory and used {:.2}s of CPU",
+            exp.name, format_size(usage.max_rss_kb * 1024), usage.cpu_time().as_secs_f64()
+        );
+    }
+    if let Some(disk) = &disk {
+        info!("Experiment {} peaked at {} on disk and left {}", exp.name, format_size(disk.peak), format_size(disk.last));
+    }
+
+    let header: Vec<&str> = vec!["start", "duration_ms", "status", "timed_out", "nice", "cpus", "outcome"];
+    let row: Vec<Box<dyn TSVSerializable>> = vec![
+        Box::new(start.to_rfc3339()),
+        Box::new(duration.as_millis()),
+        Box::new(status.map(|code| code.to_string()).unwrap_or_default()),
+        Box::new(outcome == ExperimentOutcome::TimedOut),
+        Box::new(nice.map(|n| n.to_string()).unwrap_or_default()),
+        Box::new(cpus.iter().map(|core| core.to_string()).collect::<Vec<_>>().join(",")),
+        Box::new(outcome.kind().to_owned()),
+    ];
+
+    // In append mode `result.tsv` gathers one row per run, like the run logs.
+    record_row(exp, &log_dir.join("result.tsv"), &header, &row, options);
+
+    if let Some(usage) = &usage {
+        let header = ["start", "max_rss_kb", "user_ms", "system_ms"];
+        let row: Vec<Box<dyn TSVSerializable>> = vec![
+            Box::new(start.to_rfc3339()),
+            Box::new(usage.max_rss_kb),
+            Box::new(usage.user_time.as_millis()),
+            Box::new(usage.system_time.as_millis()),
+        ];
+        record_row(exp, &log_dir.join("profile.tsv"), &header, &row, options);
+    }
+
+    if let Some(disk) = &disk {
+        let header = ["start", "peak_bytes", "final_bytes"];
+        let row: Vec<Box<dyn TSVSerializable>> = vec![
+            Box::new(start.to_rfc3339()),
+            Box::new(disk.peak),
+            Box::new(disk.last),
+        ];
+        record_row(exp, &log_dir.join("disk.tsv"), &header, &row, options);
+    }
+
+    if let LogMode::Append { keep_runs, keep_size } = options.log_mode {
+        if let Err(err) = prune_runs(log_dir, keep_runs, keep_size) {
+            warning!(UnflushedResult, "the old run logs of {} could not be pruned: {}", exp.name, err);
+        }
+    }
+
+    if let (Some(golden), ExperimentOutcome::Succeeded) = (&exp.golden, &outcome) {
+        let diff = log_dir.join("golden.diff");
+        match check_golden(golden, working_dir, &stdout_log, &diff, options.update_golden) {
+            Ok(true) => {}
+            Ok(false) => {
+                info!("Experiment {} output differs from {}, see {}", exp.name, golden.path, diff.display());
+                outcome = ExperimentOutcome::GoldenMismatch(format!("output differs from the golden file {}", golden.path));
+            }
+            Err(err) => {
+                let reason = format!("output could not be compared to the golden file {}: {}", golden.path, reason(&err));
+                info!("Experiment {} {}", exp.name, reason);
+                outcome = ExperimentOutcome::GoldenMismatch(reason);
+            }
+        }
+    }
+
+    if let (Some(check), ExperimentOutcome::Succeeded) = (&exp.check, &outcome) {
+        match run_check(exp, check, env, working_dir, log_dir, options, token).unwrap_or_else(|err| Err(ExperimentOutcome::CheckFailed(reason(&err)))) {
+            Ok(()) => {}
+            Err(ExperimentOutcome::CheckFailed(reason)) => {
+                info!("Experiment {} was rejected by its check: {}, see {}", exp.name, reason, log_dir.join("check.log").display());
+                outcome = ExperimentOutcome::CheckFailed(reason);
+            }
+            Err(stopped) => {
+                info!("Experiment {} was {} during its check", exp.name, stopped);
+                outcome = stopped;
+            }
+        }
+    }
+
+    let metrics = match (&exp.result_file, &outcome) {
+        (Some(result_file), ExperimentOutcome::Succeeded) => read_result_file(exp, result_file, working_dir, log_dir, options),
+        _ => Metrics::new(),
+    };
+
+    if let (false, ExperimentOutcome::Succeeded) = (exp.outputs.is_empty(), &outcome) {
+        if let Err(err) = collect_outputs(exp, working_dir, &artifacts) {
+            outcome = ExperimentOutcome::Errored(format!("its outputs could not be collected: {}", reason(&err)));
+            info!("Experiment {} {}", exp.name, outcome);
+        }
+    }
+
+    let result = ExperimentResult {
+        name: exp.name.to_owned(),
+        command: exp.command.to_owned(),
+        params: exp.params.clone(),
+        script: exp.script.clone(),
+        start,
+        duration,
+        status,
+        outcome,
+        stdin: exp.stdin.is_some(),
+        capture: exp.capture,
+        attempts: 1,
+        nice,
+        cpus,
+        no_output: exp.outputs.is_empty() && exp.check.is_none() && exp.result_file.is_none() && exp.capture != Capture::None && is_empty(&stdout_log) && is_empty(&stderr_log)
+            && read_dir(&artifacts).map(|mut entries| entries.next().is_none()).unwrap_or(true),
+        usage,
+        disk,
+        stderr: stderr_log,
+        slot: None,
+        leftovers_killed,
+        slow: None,
+        limits_hit,
+        metrics,
+        allow_failure: exp.allow_failure,
+    };
+
+    Ok(result)
+}
+
+/// Reads the metrics `exp` wrote into `result_file` and records them into `metrics.tsv`. A
+/// missing or malformed file only earns a warning, as does a header differing from the previous
+/// runs in append mode, or a table that cannot be written.
+fn read_result_file(
+    exp: &Experiment,
+    result_file: &str,
+    working_dir: &Path,
+    log_dir: &Path,
+    options: &ExecutionOptions,
+) -> Metrics {
+    let path = working_dir.join(result_file);
+    let metrics = match read_metrics(&path) {
+        Ok(metrics) => metrics,
+        Err(reason) => {
+            warning!(UnreadResults, "cannot read the results of {} from {}: {}", exp.name, path.display(), reason);
+            return Metrics::new();
+        }
+    };
+    if metrics.is_empty() {
+        return metrics;
+    }
+    let reported = metrics.iter().map(|(name, value)| format!("{}={}", name, metric_text(value))).collect::<Vec<_>>();
+    info!("Experiment {} reported {}", exp.name, reported.join(", "));
+
+    let metrics_file = log_dir.join("metrics.tsv");
+    let table = match options.log_mode {
+        LogMode::Overwrite => TSVTable::create(&metrics_file, &metrics),
+        LogMode::Append { .. } => TSVTable::open(&metrics_file, &metrics),
+    };
+    let row = metrics.values().map(metric_cell).collect::<Vec<_>>();
+    match table.and_then(|mut table| table.append(&row)) {
+        Err(AppError::TableMismatch(_, reason)) => warning!(UnreadResults, "the metrics of {} are not recorded into {}, {}", exp.name, metrics_file.display(), reason),
+        Err(err) => warning!(UnreadResults, "the metrics of {} are not recorded into {}: {}", exp.name, metrics_file.display(), err),
+        Ok(()) => {}
+    }
+    metrics
+}
+
+/// Executable file of the temporary folder holding an inline script, removed when dropped however
+/// the run ends.
+struct ScriptFile(PathBuf);
+
+impl Drop for ScriptFile {
+    fn drop(&mut self) {
+        if let Err(err) = remove_file(&self.0) {
+            info!("Cannot remove the script {}: {}", self.0.display(), err);
+        }
+    }
+}
+
+/// Writes the inline `script` of `exp` to an executable file of the temporary folder, unique to
+/// this run of the experiment.
+fn write_script(exp: &Experiment, script: &str) -> Result<ScriptFile, AppError> {
+    static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+    let file_name = format!(
+        "replikate-{}-{}-{}.script",
+        std::process::id(), WRITTEN.fetch_add(1, Ordering::SeqCst), exp.log_folder().replace('/', "_")
+    );
+    let path = env::temp_dir().join(file_name);
+    let into_err = |e: std::io::Error| AppError::IOError(path.to_str().unwrap().to_owned(), Arc::new(e));
+
+    write(&path, script).map_err(into_err)?;
+    let file = ScriptFile(path.to_owned());
+    #[cfg(unix)]
+    {
+        use std::{fs::{Permissions, set_permissions}, os::unix::fs::PermissionsExt};
+        set_permissions(&path, Permissions::from_mode(0o700)).map_err(into_err)?;
+    }
+    Ok(file)
+}
+
+/// Runs the `check` of `exp` the way its command is run, both streams going to `check.log`, and
+/// records its exit status in `check.tsv`. The check gets the timeout of `exp` and is killed with
+/// the run. Returns why the check failed as a `CheckFailed`, or the outcome it was stopped with.
+/// Fails when the check could not be logged or waited for.
+fn run_check(
+    exp: &Experiment,
+    check: &str,
+    env: &[(String, String)],
+    working_dir: &Path,
+    log_dir: &Path,
+    options: &ExecutionOptions,
+    token: &CancellationToken,
+) -> Result<Result<(), ExperimentOutcome>, AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    let log = log_dir.join("check.log");
+    let file = File::create(&log).map_err(into_err(&log))?;
+    let stderr = file.try_clone().map_err(into_err(&log))?;
+
+    // Checked when parsed: not empty and without an open quote.
+    let argv = split_words(check).unwrap_or_default();
+    let start = Local::now();
+    let timer = Instant::now();
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..])
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(file)
+        .stderr(stderr);
+    isolate(&mut command);
+    trace_start(&command);
+    let (status, outcome) = match command.spawn() {
+        Ok(mut child) => {
+            let (exit, _) = wait_for(exp, &mut child, &timer, options, token).inspect_err(|_| {
+                let _ = child.kill().and_then(|_| child.wait());
+            })?;
+            kill_group(child.id());
+            match exit {
+                Ok(exit) => {
+                    trace_exit(&command, format!("exited with {}", exit));
+                    let outcome = match exit.code() {
+                        Some(0) => Ok(()),
+                        Some(code) => Err(ExperimentOutcome::CheckFailed(format!("exit code {}", code))),
+                        None => Err(ExperimentOutcome::CheckFailed(ExperimentOutcome::Signaled(signal(&exit)).to_string())),
+                    };
+                    (exit.code(), outcome)
+                }
+                Err(stopped) => {
+                    trace_exit(&command, format!("was killed: {}", stopped));
+                    let outcome = match stopped {
+                        ExperimentOutcome::TimedOut => ExperimentOutcome::CheckFailed(stopped.to_string()),
+                        stopped => stopped,
+                    };
+                    (None, Err(outcome))
+                }
+            }
+        }
+        Err(err) => {
+            trace_exit(&command, format!("could not be started: {}", err));
+            (None, Err(ExperimentOutcome::CheckFailed(format!("{} could not be started: {}", argv[0], err))))
+        }
+    };
+    let duration = timer.elapsed();
+
+    let header = ["start", "duration_ms", "status", "succeeded"];
+    let row: Vec<Box<dyn TSVSerializable>> = vec![
+        Box::new(start.to_rfc3339()),
+        Box::new(duration.as_millis()),
+        Box::new(status.map(|code| code.to_string()).unwrap_or_default()),
+        Box::new(outcome.is_ok()),
+    ];
+    record_row(exp, &log_dir.join("check.tsv"), &header, &row, options);
+
+    Ok(outcome)
+}
+
+/// Table at `path` with `header`, appended to in append mode and created afresh otherwise. In
+/// append mode, a table written with another header, as by an older replikate, is moved aside to
+/// `<path>.old` and started over, with a warning.
+fn open_table<H: TSVHeader>(path: &Path, header: &H, options: &ExecutionOptions) -> Result<TSVTable, AppError> {
+    match options.log_mode {
+        LogMode::Overwrite => TSVTable::create(path, header),
+        LogMode::Append { .. } => match TSVTable::open(path, header) {
+            Err(AppError::TableMismatch(_, reason)) => {
+                let old = path.with_extension("tsv.old");
+                rename(path, &old).map_err(|e| AppError::IOError(path.to_str().unwrap().to_owned(), Arc::new(e)))?;
+                warning!(RotatedTable, "{} was moved to {} to start over, {}", path.display(), old.display(), reason);
+                TSVTable::create(path, header)
+            }
+            table => table,
+        },
+    }
+}
+
+/// Whether `log` is empty or missing, as for a stream that is not captured.
+fn is_empty(log: &Path) -> bool {
+    log.metadata().map(|metadata| metadata.len() == 0).unwrap_or(true)
+}
+
+/// Log file receiving a captured stream byte for byte, otherwise removes the one left by a
+/// previous run so it is not mistaken for this run's output.
+fn capture_into(log: &Path, captured: bool) -> Result<Stdio, AppError> {
+    let into_err = |e: std::io::Error| AppError::IOError(log.to_str().unwrap().to_owned(), Arc::new(e));
+    if captured {
+        return File::create(log).map(Stdio::from).map_err(into_err);
+    }
+    match remove_file(log) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(into_err(err)),
+        _ => Ok(Stdio::null()),
+    }
+}
+
+/// Waits for `child` to exit by itself, or kills it and returns why: its timeout, the deadline
+/// or the cancellation of the run. Under `--profile`, also returns the resources it used.
+fn wait_for(
+    exp: &Experiment,
+    child: &mut Child,
+    timer: &Instant,
+    options: &ExecutionOptions,
+    token: &CancellationToken,
+) -> Result<(Result<ExitStatus, ExperimentOutcome>, Option<ResourceUsage>), AppError> {
+    // Profiling reaps the child itself to read its resource usage.
+    let profile = options.profile && PROFILING_SUPPORTED;
+    let timeout = exp.timeout.map(Duration::from_secs);
+    let into_err = |e: std::io::Error| AppError::IOError(exp.name.to_owned(), Arc::new(e));
+    loop {
+        if profile {
+            if let Some((exit, usage)) = try_wait_with_usage(child).map_err(into_err)? {
+                return Ok((Ok(exit), Some(usage)));
+            }
+            thread::sleep(POLL_INTERVAL);
+        } else if let Some(exit) = child.wait_timeout(POLL_INTERVAL).map_err(into_err)? {
+            return Ok((Ok(exit), None));
+        }
+
+        let stopped = if timeout.map(|timeout| timer.elapsed() >= timeout).unwrap_or(false) {
+            ExperimentOutcome::TimedOut
+        } else if options.deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(false) {
+            ExperimentOutcome::DeadlineReached
+        } else if token.is_cancelled() || interrupted() {
+            ExperimentOutcome::Cancelled
+        } else {
+            continue;
+        };
+        child.kill().map_err(into_err)?;
+        if profile {
+            let (_, usage) = wait_with_usage(child).map_err(into_err)?;
+            return Ok((Err(stopped), Some(usage)));
+        }
+        child.wait().map_err(into_err)?;
+        return Ok((Err(stopped), None));
+    }
+}
+
+/// Whether `exp` can start without passing the deadline, judging from the duration of its last
+/// successful run recorded in `result.tsv`, in this run's logs or else in `history`, those of the
+/// previous run. Experiments never run before are started as long as the deadline has not passed.
+fn fits_before_deadline(exp: &Experiment, log_dir: &Path, history: Option<&Path>, options: &ExecutionOptions) -> bool {
+    let deadline = match options.deadline {
+        Some(deadline) => deadline,
+        None => return true,
+    };
+    let now = Instant::now();
+    if now >= deadline {
+        info!("Skipping {}, the deadline has passed", exp.name);
+        return false;
+    }
+    match previous_duration(log_dir).or_else(|| history.and_then(previous_duration)) {
+        Some(estimate) if now + estimate > deadline => {
+            info!(
+                "Skipping {}, its last run took {}s and only {}s are left before the deadline",
+                exp.name, estimate.as_secs(), (deadline - now).as_secs()
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Duration of the last successful run recorded in `log_dir/result.tsv`.
+fn previous_duration(log_dir: &Path) -> Option<Duration> {
+    let content = read_to_string(log_dir.join("result.tsv")).ok()?;
+    let mut lines = content.lines();
+    let header = lines.next()?.split('\t').collect::<Vec<_>>();
+    let column = |name: &str| header.iter().position(|column| column.trim_matches('"') == name);
+    let (duration, outcome) = (column("duration_ms")?, column("outcome")?);
+
+    lines.rev()
+        .map(|line| line.split('\t').collect::<Vec<_>>())
+        .find(|row| row.get(outcome).map(|value| value.trim_matches('"')) == Some("succeeded"))
+        .and_then(|row| row.get(duration)?.parse().ok())
+        .map(Duration::from_millis)
+}
+
+#[cfg(unix)]
+fn signal(exit: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    exit.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_exit: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Hard links (or copies, across file systems) the declared outputs of `exp` into `artifacts`,
+/// keeping their path relative to the working directory.
+fn collect_outputs(exp: &Experiment, working_dir: &Path, artifacts: &Path) -> Result<(), AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    for pattern in &exp.outputs {
+        let files = glob(working_dir, pattern).map_err(into_err(working_dir))?;
+        if files.is_empty() {
+            warning!(MissingOutput, "output '{}' of {} was not produced", pattern, exp.name);
+        }
+        for file in files.iter().filter(|file| file.is_file()) {
+            let relative = file.strip_prefix(working_dir)
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|_| PathBuf::from(file.file_name().unwrap()));
+            let destination = artifacts.join(relative);
+            create_dir_all(destination.parent().unwrap()).map_err(into_err(&destination))?;
+            if destination.exists() {
+                remove_file(&destination).map_err(into_err(&destination))?;
+            }
+            if hard_link(file, &destination).is_err() {
+                copy(file, &destination).map_err(into_err(file))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the oldest `run-*.log` files of `log_dir` once more than `keep_runs` files are kept or
+/// their total size exceeds `keep_size`. The most recent run is always kept.
+fn prune_runs(log_dir: &Path, keep_runs: Option<usize>, keep_size: Option<u64>) -> Result<(), AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    let mut runs: Vec<(PathBuf, u64)> = Vec::new();
+    for entry in read_dir(log_dir).map_err(into_err(log_dir))? {
+        let entry = entry.map_err(into_err(log_dir))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with("run-") && name.ends_with(".log") {
+            let size = entry.metadata().map_err(into_err(&entry.path()))?.len();
+            runs.push((entry.path(), size));
+        }
+    }
+    runs.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut total_size = 0;
+    for (index, (run, size)) in runs.iter().enumerate() {
+        total_size += size;
+        let over_count = keep_runs.map(|keep| index >= keep).unwrap_or(false);
+        let over_size = keep_size.map(|keep| total_size > keep).unwrap_or(false);
+        if index > 0 && (over_count || over_size) {
+            remove_file(run).map_err(into_err(run))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the `nice`/`taskset` prefix to prepend to the experiment command along with the
+/// settings that are actually applied. Both are ignored on platforms other than Linux.
+fn scheduling_prefix(exp: &Experiment) -> (Vec<String>, Option<i32>, Vec<usize>) {
+    if !cfg!(target_os = "linux") {
+        if exp.nice.is_some() || !exp.cpus.is_empty() {
+            warning!(UnsupportedPlatform, "nice and cpus are only supported on Linux, ignored for {}", exp.name);
+        }
+        return (Vec::new(), None, Vec::new());
+    }
+
+    let mut prefix = Vec::new();
+    if let Some(nice) = exp.nice {
+        prefix.extend(vec!["nice".to_owned(), "-n".to_owned(), nice.to_string()]);
+    }
+    if !exp.cpus.is_empty() {
+        let cpus = exp.cpus.iter().map(|core| core.to_string()).collect::<Vec<_>>().join(",");
+        prefix.extend(vec!["taskset".to_owned(), "-c".to_owned(), cpus]);
+    }
+
+    (prefix, exp.nice, exp.cpus.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use yaml_rust::YamlLoader;
+    use crate::{json::Json, model::Project};
+    use super::{ExecutionOptions, ExperimentOutcome, ExperimentResult, LogMode, check_results, run_all, summarize};
+
+    /// Project read from `config` into a fresh folder named after `name`, its tree created.
+    fn project(name: &str, config: &str) -> (Project, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("replikate-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let yaml = &YamlLoader::load_from_str(config).unwrap()[0];
+        let project = Project::from_config(yaml, &dir).unwrap().set_path(dir.to_str().unwrap());
+        crate::create_tree(&project).unwrap();
+        (project, dir)
+    }
+
+    fn options() -> ExecutionOptions {
+        ExecutionOptions { jobs: 1, ..ExecutionOptions::default() }
+    }
+
+    fn outcome<'a>(results: &'a [ExperimentResult], name: &str) -> &'a ExperimentOutcome {
+        &results.iter().find(|result| result.name == name).unwrap().outcome
+    }
+
+    #[test]
+    fn rejects_only_the_experiment_with_a_bad_input() {
+        let config = "name: inputs\nexperiments:\n  - name: bad\n    command: \"true\"\n    inputs:\n      - path: missing.csv\n        sha256: \
+            e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\n  - name: sibling\n    command: \"true\"\n";
+        let (p, dir) = project("inputs", config);
+        let results = run_all(&p, None, &options()).unwrap();
+        assert!(matches!(outcome(&results, "bad"), ExperimentOutcome::InputRejected(_)));
+        assert_eq!(outcome(&results, "sibling"), &ExperimentOutcome::Succeeded);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fails_only_the_experiment_whose_env_file_is_malformed() {
+        let config = "name: env\nexperiments:\n  - name: bad\n    command: \"true\"\n    env_file: bad.env\n  - name: sibling\n    command: \"true\"\n";
+        let (p, dir) = project("env", config);
+        std::fs::write(dir.join("bad.env"), "not an assignment\n").unwrap();
+        let results = run_all(&p, None, &options()).unwrap();
+        assert!(matches!(outcome(&results, "bad"), ExperimentOutcome::EnvFailed(_)));
+        assert_eq!(outcome(&results, "sibling"), &ExperimentOutcome::Succeeded);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fails_only_the_experiment_whose_logs_cannot_be_written() {
+        let config = "name: logs\nexperiments:\n  - name: unlogged\n    command: \"true\"\n  - name: untabled\n    command: \"true\"\n\
+            \x20 - name: sibling\n    command: \"true\"\n";
+        let (p, dir) = project("logs", config);
+        std::fs::create_dir_all(p.run_logs_path().join("unlogged").join("stdout.log")).unwrap();
+        std::fs::create_dir_all(p.run_logs_path().join("untabled").join("result.tsv")).unwrap();
+        let results = run_all(&p, None, &options()).unwrap();
+        assert!(matches!(outcome(&results, "unlogged"), ExperimentOutcome::SpawnFailed(_)));
+        assert_eq!(outcome(&results, "untabled"), &ExperimentOutcome::Succeeded);
+        assert_eq!(outcome(&results, "sibling"), &ExperimentOutcome::Succeeded);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn records_the_applied_nice_and_cpus_in_the_result() {
+        let (p, dir) = project("scheduling", "name: scheduling\nexperiments:\n  - name: a\n    command: \"true\"\n    nice: 5\n    cpus: [0]\n");
+        let results = run_all(&p, None, &options()).unwrap();
+        assert_eq!(outcome(&results, "a"), &ExperimentOutcome::Succeeded);
+        assert_eq!((results[0].nice, results[0].cpus.as_slice()), (Some(5), &[0][..]));
+        let recorded = std::fs::read_to_string(p.run_logs_path().join("a").join("result.json")).unwrap();
+        let recorded = Json::parse(&recorded).unwrap();
+        assert_eq!(recorded.get("nice").and_then(Json::as_f64), Some(5.0));
+        assert_eq!(recorded.get("cpus"), Some(&Json::Array(vec![Json::Number(0.0)])));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn records_the_experiments_skipped_at_the_deadline() {
+        let (p, dir) = project("deadline", "name: deadline\nexperiments:\n  - name: a\n    command: \"true\"\n");
+        let options = ExecutionOptions { deadline: Some(std::time::Instant::now()), ..options() };
+        let results = run_all(&p, None, &options).unwrap();
+        assert_eq!(outcome(&results, "a"), &ExperimentOutcome::SkippedForTime);
+        assert!(p.run_logs_path().join("a").join("result.json").exists());
+        let summary = std::fs::read_to_string(p.run_logs_path().join("summary.tsv")).unwrap();
+        assert!(summary.lines().nth(1).unwrap().ends_with("\t\"skipped_for_time\""), "{}", summary);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn times_out_a_check_with_the_timeout_of_its_experiment() {
+        let config = "name: check\nexperiments:\n  - name: slow\n    command: \"true\"\n    check: sleep 30\n    timeout: 1\n";
+        let (p, dir) = project("check", config);
+        let started = std::time::Instant::now();
+        let results = run_all(&p, None, &options()).unwrap();
+        assert_eq!(outcome(&results, "slow"), &ExperimentOutcome::CheckFailed("timed out".to_owned()));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn moves_aside_a_result_table_with_an_older_header_in_append_mode() {
+        let (p, dir) = project("rotate", "name: rotate\nexperiments:\n  - name: a\n    command: \"true\"\n");
+        let result = p.run_logs_path().join("a").join("result.tsv");
+        std::fs::write(&result, "\"start\"\t\"duration_ms\"\t\"status\"\t\"timed_out\"\t\"nice\"\t\"cpus\"\n").unwrap();
+        let options = ExecutionOptions { log_mode: LogMode::Append { keep_runs: None, keep_size: None }, ..options() };
+        let results = run_all(&p, None, &options).unwrap();
+        assert_eq!(outcome(&results, "a"), &ExperimentOutcome::Succeeded);
+        let old = std::fs::read_to_string(result.with_extension("tsv.old")).unwrap();
+        assert!(!old.contains("outcome"));
+        let rows = std::fs::read_to_string(&result).unwrap();
+        assert!(rows.lines().next().unwrap().ends_with("\t\"outcome\""));
+        assert_eq!(rows.lines().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_skipped_experiments_without_failing() {
+        let (p, dir) = project("skipped", "name: skipped\nexperiments:\n  - name: a\n    command: \"false\"\n  - name: b\n    command: \"true\"\n");
+        let reason = "its optional requirement tool is not satisfied (missing)".to_owned();
+        let options = ExecutionOptions { skipped: [("a".to_owned(), reason.to_owned())].into(), ..options() };
+        let results = run_all(&p, None, &options).unwrap();
+        assert_eq!(outcome(&results, "a"), &ExperimentOutcome::Skipped(reason));
+        assert_eq!(summarize(&results), "1 succeeded, 0 failed, 1 skipped");
+        assert!(check_results(&results).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn does_not_count_allowed_failures() {
+        let config = "name: allowed\nexperiments:\n  - name: flaky\n    command: \"false\"\n    allow_failure: true\n  \
+            - name: after\n    command: \"true\"\n";
+        let (p, dir) = project("allowed", config);
+        let options = ExecutionOptions { max_failures: Some(1), ..options() };
+        let results = run_all(&p, None, &options).unwrap();
+        assert_eq!(outcome(&results, "flaky"), &ExperimentOutcome::Failed(1));
+        assert_eq!(outcome(&results, "after"), &ExperimentOutcome::Succeeded);
+        assert_eq!(summarize(&results), "1 succeeded, 0 failed, 1 failed (allowed)");
+        assert!(check_results(&results).is_ok());
+
+        let junit = dir.join("junit.xml");
+        crate::junit::write_junit(junit.to_str().unwrap(), "allowed", &results).unwrap();
+        let xml = std::fs::read_to_string(&junit).unwrap();
+        assert!(xml.contains("failures=\"0\" skipped=\"1\""));
+        assert!(xml.contains("<skipped message=\"failed (allowed): exit code 1\"/>"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
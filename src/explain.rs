@@ -0,0 +1,162 @@
+use crate::{
+    AppError,
+    execute::waves,
+    model::{Project, build_levels},
+    units::format_size,
+};
+
+/// Prints in plain words what each phase would do for `p`, without running anything: the
+/// requirements checked, the repositories cloned and built, the experiments run in order and
+/// where their logs go. `jobs` is the `--jobs` value.
+pub fn explain(p: &Project, jobs: usize) -> Result<(), AppError> {
+    let src = p.src_path();
+    let logs = p.logs_path();
+    let at_a_time = if jobs > 1 { format!(", up to {} at a time", jobs) } else { String::new() };
+
+    println!("Project {} in {}, logging into {}.", p.name, p.path, logs.display());
+
+    println!();
+    println!("--requirements checks, and --install installs when missing:");
+    if p.requirements.is_empty() {
+        println!("  nothing, no requirement is declared");
+    }
+    for requirement in &p.requirements {
+        let version = if requirement.version.is_empty() { String::new() } else { format!(" version {}", requirement.version) };
+        let optional = if requirement.required { "" } else { ", optional" };
+        let install = if requirement.install.is_some() { "" } else { ", it has no install command" };
+        println!("  {}{}{}{}", requirement.name, version, optional, install);
+    }
+
+    if !p.preflight.is_empty() {
+        println!();
+        println!("Before --build and --run, the preflight checks run from {}:", p.path);
+        for command in &p.preflight {
+            println!("  {}", command);
+        }
+    }
+
+    println!();
+    println!("--git clones:");
+    if p.git_binary.is_some() || !p.git_args.is_empty() {
+        let git = std::iter::once(p.git_binary.as_deref().unwrap_or("git")).chain(p.git_args.iter().map(String::as_str));
+        println!("  running git as '{}'", git.collect::<Vec<_>>().join(" "));
+    }
+    if p.repositories.is_empty() {
+        println!("  nothing, no repository is declared");
+    }
+    for repository in &p.repositories {
+        let destination = src.join(&repository.name);
+        if repository.local {
+            println!("  nothing for {}, linked in place as {}", repository.url, destination.display());
+            continue;
+        }
+        let clone = if destination.exists() { "already cloned in" } else { "into" };
+        let commit = repository.commit.as_ref().map(|commit| format!(", then checks out {}", commit)).unwrap_or_default();
+        println!("  {} {} {}{}", repository.url, clone, destination.display(), commit);
+    }
+
+    println!();
+    println!("--build builds{}:", at_a_time);
+    let levels = build_levels(&p.repositories).map_err(AppError::Parsing)?;
+    let mut builds = p.repositories.iter().zip(levels).filter(|(repository, _)| repository.build.is_some()).collect::<Vec<_>>();
+    // Stable sort, builds of a level keep the config order.
+    builds.sort_by_key(|(_, level)| *level);
+    if builds.is_empty() {
+        println!("  nothing, no repository declares a build command");
+    }
+    for (repository, level) in builds {
+        let after = if repository.build_depends_on.is_empty() {
+            String::new()
+        } else {
+            format!(" after {}", repository.build_depends_on.join(", "))
+        };
+        println!(
+            "  step {}: {}{}, running '{}' in {}, logging into {}",
+            level + 1, repository.name, after, repository.build.as_deref().unwrap_or_default(),
+            src.join(&repository.name).display(), logs.join("build").join(format!("{}.log", repository.name)).display()
+        );
+    }
+
+    println!();
+    println!("--run runs{}:", at_a_time);
+    if !p.setup.is_empty() {
+        println!("  first the setup commands: {}", p.setup.join("; "));
+    }
+    if p.experiments.is_empty() {
+        println!("  no experiment");
+    } else {
+        let seed = if p.seed.is_some() { " and its seed as REPLIKATE_SEED" } else { "" };
+        println!("  each experiment given the artifacts folder of its logs as REPLIKATE_OUT{}", seed);
+    }
+    for (name, group) in p.groups.iter().filter(|(name, _)| p.experiments.iter().any(|exp| exp.group.as_deref() == Some(name))) {
+        if !group.setup.is_empty() {
+            println!("  before the first experiment of the group {}, its setup commands: {}", name, group.setup.join("; "));
+        }
+        if !group.teardown.is_empty() {
+            println!("  after the last experiment of the group {}, its teardown commands: {}", name, group.teardown.join("; "));
+        }
+    }
+    for (step, wave) in waves(p).map_err(AppError::Parsing)?.iter().enumerate() {
+        println!("  step {}:", step + 1);
+        for exp in wave.iter().map(|index| &p.experiments[*index]) {
+            let mut details = Vec::new();
+            if let Some(timeout) = exp.timeout {
+                details.push(format!("stopped after {}s", timeout));
+            }
+            if let Some(soft_timeout) = exp.soft_timeout {
+                details.push(format!("reported as slow after {}s", soft_timeout));
+            }
+            if let Some(memory) = exp.memory_limit {
+                details.push(format!("limited to {} of memory", format_size(memory)));
+            }
+            if let Some(cores) = exp.cpu_limit {
+                details.push(format!("limited to {} core(s)", cores));
+            }
+            if let Some(group) = &exp.group {
+                details.push(format!("in the group {}", group));
+            }
+            if exp.allow_failure {
+                details.push("allowed to fail".to_owned());
+            }
+            if !exp.depends_on.is_empty() {
+                details.push(format!("only if {} succeeded", exp.depends_on.join(", ")));
+            }
+            if !exp.resources.is_empty() {
+                let resources = exp.resources.iter().map(|(name, amount)| format!("{} {}", amount, name)).collect::<Vec<_>>();
+                details.push(format!("holds {} while running", resources.join(", ")));
+            }
+            if !exp.requires.is_empty() {
+                details.push(format!("skipped if the optional ones of {} are not satisfied", exp.requires.join(", ")));
+            }
+            if !exp.inputs.is_empty() {
+                details.push(format!("checks {} input(s) first", exp.inputs.len()));
+            }
+            if let Some(golden) = &exp.golden {
+                let within = if golden.tolerance.is_some() { " within tolerance" } else { "" };
+                details.push(format!("compares its output to {}{}", golden.path, within));
+            }
+            if let Some(check) = &exp.check {
+                details.push(format!("is then checked by '{}'", check));
+            }
+            if let Some(result_file) = &exp.result_file {
+                details.push(format!("reports the results of {}", result_file));
+            }
+            let details = if details.is_empty() { String::new() } else { format!(" ({})", details.join(", ")) };
+            println!(
+                "    {}: '{}' in {}, logging into {}{}",
+                exp.name, p.wrapped(exp).command, p.working_dir(exp).display(), p.run_logs_path().join(exp.log_folder()).display(), details
+            );
+        }
+    }
+    if !p.teardown.is_empty() {
+        println!("  and last the teardown commands: {}", p.teardown.join("; "));
+    }
+    if !p.aggregate.is_empty() {
+        println!(
+            "  then, unless an experiment failed, the aggregate commands: {}, logging into {}",
+            p.aggregate.join("; "), p.run_logs_path().join("aggregate.log").display()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::AppError;
+#[cfg(feature = "remote-config")]
+use {
+    std::process::{Command, Stdio},
+    crate::trace::traced_output,
+};
+
+/// Whether the configuration argument is an http(s) URL rather than a path.
+pub fn is_url(config: &str) -> bool {
+    config.starts_with("http://") || config.starts_with("https://")
+}
+
+/// Project folder of a configuration served at `url`: its file name without extension, in the
+/// current directory. `None` when the URL path ends with no file name.
+pub fn url_project_path(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let stem = name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name);
+    if stem.is_empty() || path.trim_end_matches('/').matches('/').count() < 3 {
+        return None;
+    }
+    Some(stem.to_owned())
+}
+
+/// Downloads the configuration at `url` with `curl`, following redirects. Any answer but a 2xx
+/// one is an error.
+#[cfg(feature = "remote-config")]
+pub fn fetch_config(url: &str) -> Result<String, AppError> {
+    let failed = |reason: String| AppError::ConfigFetchFailed(url.to_owned(), reason);
+    let output = traced_output(Command::new("curl")
+        .args(["--silent", "--show-error", "--location", "--write-out", "\n%{http_code}", "--", url])
+        .stdin(Stdio::null()))
+        .map_err(|e| failed(format!("curl is not available ({})", e)))?;
+    if !output.status.success() {
+        return Err(failed(String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+    }
+
+    let answer = String::from_utf8(output.stdout).map_err(|_| failed("the answer is not valid UTF-8".to_owned()))?;
+    parse_answer(&answer).map(str::to_owned).map_err(failed)
+}
+
+/// Body of an answer printed by curl, followed by its HTTP status on a line of its own. Any
+/// status but a 2xx one is an error.
+#[cfg(any(feature = "remote-config", test))]
+fn parse_answer(answer: &str) -> Result<&str, String> {
+    let (body, code) = answer.rsplit_once('\n').unwrap_or(("", answer));
+    if !code.starts_with('2') {
+        return Err(format!("the server answered with HTTP status {}", code));
+    }
+    Ok(body)
+}
+
+#[cfg(not(feature = "remote-config"))]
+pub fn fetch_config(url: &str) -> Result<String, AppError> {
+    Err(AppError::ConfigFetchFailed(url.to_owned(), "this replikate was built without the remote-config feature".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_answer, url_project_path};
+
+    #[test]
+    fn names_the_project_after_the_url_file_name() {
+        assert_eq!(url_project_path("https://store.example/configs/bench.yml"), Some("bench".to_owned()));
+        assert_eq!(url_project_path("http://store.example/bench.yaml?version=3#top"), Some("bench".to_owned()));
+        assert_eq!(url_project_path("https://store.example/configs/"), None);
+        assert_eq!(url_project_path("https://store.example"), None);
+    }
+
+    #[test]
+    fn reads_the_body_of_2xx_answers_only() {
+        assert_eq!(parse_answer("name: bench\nexperiments: []\n\n200"), Ok("name: bench\nexperiments: []\n"));
+        assert_eq!(parse_answer("\n204"), Ok(""));
+        assert_eq!(parse_answer("Not Found\n404"), Err("the server answered with HTTP status 404".to_owned()));
+        assert_eq!(parse_answer("000"), Err("the server answered with HTTP status 000".to_owned()));
+    }
+}
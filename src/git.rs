@@ -0,0 +1,173 @@
+use std::{
+    fs::{read_link, remove_dir_all, remove_file},
+    path::Path,
+    process::{Command, Stdio},
+    thread::sleep,
+    time::Duration,
+};
+use crate::{
+    info,
+    warning,
+    AppError,
+    auth::git_auth_env,
+    disk::dir_size,
+    output::is_quiet,
+    trace::{trace_exit, trace_start, traced_output, traced_status},
+    model::{Project, Repository},
+    scheduler::ProcessLimit,
+    units::format_size,
+};
+use std::sync::Arc;
+
+/// How often the size of a clone is measured under `--max-clone-size`.
+const CLONE_SIZE_POLL: Duration = Duration::from_millis(200);
+
+/// Clones the repositories of `p` that are not cloned yet and checks out their commit, holding a
+/// permit of `limit` for each. A clone growing beyond `max_clone_size` bytes is killed and
+/// removed. Local repositories are linked instead and left untouched.
+pub fn git(p: &Project, max_clone_size: Option<u64>, limit: Option<&ProcessLimit>) -> Result<(), AppError> {
+    let src = p.src_path();
+
+    for repository in &p.repositories {
+        let destination = src.join(&repository.name);
+        if repository.local {
+            link_local(repository, &destination)?;
+            continue;
+        }
+        let _permit = limit.map(ProcessLimit::acquire);
+
+        if !destination.exists() {
+            info!("Cloning {} into {}", repository.url, destination.display());
+            let mut clone = git_command(p);
+            clone.envs(git_auth_env(repository)?)
+                .arg("clone")
+                .args(if is_quiet() { vec!["--quiet"] } else { vec![] })
+                .arg(&repository.url)
+                .arg(&destination);
+            match max_clone_size {
+                Some(max_size) => clone_within(&mut clone, &destination, max_size)?,
+                None => run_git(&mut clone)?,
+            }
+        }
+
+        if let Some(commit) = &repository.commit {
+            info!("Checking out {} in {}", commit, repository.name);
+            run_git(git_command(p)
+                .arg("checkout")
+                .args(if is_quiet() { vec!["--quiet"] } else { vec![] })
+                .arg(commit)
+                .current_dir(&destination))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Points `destination` at the folder of the local `repository`, its path being already resolved
+/// from the folder of the config. A folder already at `destination` is kept, with a notice.
+fn link_local(repository: &Repository, destination: &Path) -> Result<(), AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    let target = std::path::absolute(&repository.url).map_err(into_err(Path::new(&repository.url)))?;
+    if !target.is_dir() {
+        return Err(AppError::ExternalError(format!("The local repository {} is not a folder.", repository.url)));
+    }
+    if let Some(commit) = &repository.commit {
+        warning!(IgnoredSetting, "{} is local, its commit {} is not checked out", repository.name, commit);
+    }
+    match read_link(destination) {
+        Ok(linked) if linked == target => return Ok(()),
+        Ok(_) => remove_file(destination).map_err(into_err(destination))?,
+        Err(_) if destination.exists() => {
+            info!("Skipping {}, {} already exists and is used as is", repository.url, destination.display());
+            return Ok(());
+        }
+        Err(_) => {}
+    }
+    info!("Linking {} in place as {}", repository.url, destination.display());
+    symlink(&target, destination).map_err(into_err(destination))
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(not(unix))]
+fn symlink(_target: &Path, _link: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "local repositories are only linked on Unix"))
+}
+
+/// `git_binary` of `p`, or else `git`, with the `git_args` of `p` given first.
+fn git_command(p: &Project) -> Command {
+    let mut git = Command::new(p.git_binary.as_deref().unwrap_or("git"));
+    git.args(&p.git_args);
+    git
+}
+
+/// Commit checked out in `repository` of `p`, `None` when it is not a git working copy.
+pub fn resolved_commit(p: &Project, repository: &Path) -> Option<String> {
+    if !repository.join(".git").exists() {
+        return None;
+    }
+    let output = traced_output(git_command(p)
+        .args(["rev-parse", "HEAD"])
+        .current_dir(repository)
+        .stderr(Stdio::null()))
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Runs the `clone` command into `destination`, measuring its size as it grows and once git
+/// exited. Beyond `max_size` bytes, git is killed and the partial clone removed.
+fn clone_within(clone: &mut Command, destination: &Path, max_size: u64) -> Result<(), AppError> {
+    trace_start(clone);
+    let program = clone.get_program().to_string_lossy().into_owned();
+    let mut child = clone.spawn()
+        .map_err(|e| AppError::IOError(program.to_owned(), Arc::new(e)))?;
+
+    let status = loop {
+        let status = child.try_wait().map_err(|e| AppError::IOError(program.to_owned(), Arc::new(e)))?;
+        let size = dir_size(destination);
+        if size > max_size {
+            if status.is_none() {
+                // Killing a git that exited meanwhile fails, which leaves nothing to do.
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            trace_exit(clone, format!("was killed past --max-clone-size with {} cloned", format_size(size)));
+            remove_dir_all(destination)
+                .map_err(|e| AppError::IOError(destination.to_str().unwrap().to_owned(), Arc::new(e)))?;
+            return Err(AppError::ExternalError(format!(
+                "The clone into {} exceeded --max-clone-size ({} > {}), it was aborted and removed.",
+                destination.display(), format_size(size), format_size(max_size)
+            )));
+        }
+        if let Some(status) = status {
+            trace_exit(clone, format!("exited with {}", status));
+            break status;
+        }
+        sleep(CLONE_SIZE_POLL);
+    };
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(AppError::ExternalError(format!("git exited with {}.", status)))
+    }
+}
+
+fn run_git(command: &mut Command) -> Result<(), AppError> {
+    let status = traced_status(command)
+        .map_err(|e| AppError::IOError(command.get_program().to_string_lossy().into_owned(), Arc::new(e)))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(AppError::ExternalError(format!("git exited with {}.", status)))
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    fs::read_dir,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Expands `pattern` relative to `base`. Each `/`-separated component may use `*` (any sequence
+/// of characters) and `?` (any single character). Non-wildcard components are kept as is, so a
+/// pattern without wildcards yields its path only if it exists. Results are sorted.
+pub fn glob(base: &Path, pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let mut candidates = vec![if pattern.starts_with('/') { PathBuf::from("/") } else { base.to_path_buf() }];
+
+    for component in pattern.split('/').filter(|c| !c.is_empty() && *c != ".") {
+        let mut next = Vec::new();
+        for candidate in candidates {
+            if !has_wildcard(component) {
+                let path = candidate.join(component);
+                if path.exists() {
+                    next.push(path);
+                }
+            } else if candidate.is_dir() {
+                for entry in read_dir(&candidate)? {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    if (!name.starts_with('.') || component.starts_with('.')) && matches(component, &name) {
+                        next.push(entry.path());
+                    }
+                }
+            }
+        }
+        candidates = next;
+    }
+
+    candidates.sort();
+    Ok(candidates)
+}
+
+pub fn has_wildcard(pattern: &str) -> bool {
+    pattern.contains('*') || pattern.contains('?')
+}
+
+/// Matches a single path component against a pattern using `*` and `?`.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
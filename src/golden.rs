@@ -0,0 +1,217 @@
+use std::{
+    fs::{File, copy, create_dir_all, read_to_string, remove_file},
+    io::Write,
+    path::Path,
+};
+use crate::{
+    info,
+    AppError,
+    diff::unified_diff,
+    model::{Golden, Tolerance},
+};
+use std::sync::Arc;
+
+/// Compares `actual`, the captured stdout or the declared output file, to the golden file, its
+/// numbers within the golden `tolerance` if any, and writes their unified diff into `diff` on
+/// mismatch. With `update`, the golden file is replaced by `actual` instead. Returns whether the
+/// output matches.
+pub fn check_golden(golden: &Golden, working_dir: &Path, stdout: &Path, diff: &Path, update: bool) -> Result<bool, AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    let expected_path = working_dir.join(&golden.path);
+    let actual_path = match &golden.output {
+        Some(output) => working_dir.join(output),
+        None => stdout.to_owned(),
+    };
+
+    if update {
+        if let Some(parent) = expected_path.parent() {
+            create_dir_all(parent).map_err(into_err(parent))?;
+        }
+        copy(&actual_path, &expected_path).map_err(into_err(&actual_path))?;
+        info!("Updated golden file {}", expected_path.display());
+        return Ok(true);
+    }
+
+    let expected = read_to_string(&expected_path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => AppError::ExternalError(format!(
+            "The golden file {} does not exist, create it with --update-golden.", expected_path.display()
+        )),
+        _ => into_err(&expected_path)(err),
+    })?;
+    let actual = read_to_string(&actual_path).map_err(into_err(&actual_path))?;
+    let actual_name = golden.output.as_deref().unwrap_or("stdout");
+    let within_tolerance = match golden.tolerance.map(|tolerance| compare_numbers(&expected, &actual, tolerance)) {
+        Some(Ok(largest)) => {
+            if let Some(deviation) = largest {
+                info!("{} matches {} within tolerance, its largest deviation being {}", actual_name, golden.path, deviation);
+            }
+            true
+        }
+        Some(Err(reason)) => {
+            info!("{} differs from {} beyond tolerance, {}", actual_name, golden.path, reason);
+            false
+        }
+        None => expected == actual,
+    };
+    let changes = if within_tolerance { String::new() } else { unified_diff(&expected, &actual, &golden.path, actual_name) };
+    if changes.is_empty() {
+        if diff.exists() {
+            remove_file(diff).map_err(into_err(diff))?;
+        }
+        return Ok(true);
+    }
+
+    File::create(diff)
+        .and_then(|mut file| file.write_all(changes.as_bytes()))
+        .map_err(into_err(diff))?;
+    Ok(false)
+}
+
+/// A number of the output differing from the golden one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deviation {
+    /// From 1.
+    pub line: usize,
+    pub expected: String,
+    pub actual: String,
+    pub difference: f64,
+}
+
+impl std::fmt::Display for Deviation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:.3e} at line {} ({} instead of {})", self.difference, self.line, self.actual, self.expected)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'a> {
+    Number(&'a str, f64),
+    Text(&'a str),
+}
+
+/// Compares `actual` to `expected` line by line and word by word, the numbers within `tolerance`
+/// and anything else exactly. Returns the largest deviation within tolerance, if any number
+/// differs, or why they do not match: the first deviation beyond tolerance or other difference.
+fn compare_numbers(expected: &str, actual: &str, tolerance: Tolerance) -> Result<Option<Deviation>, String> {
+    let (expected_lines, actual_lines) = (expected.lines().collect::<Vec<_>>(), actual.lines().collect::<Vec<_>>());
+    if expected_lines.len() != actual_lines.len() {
+        return Err(format!("{} lines instead of {}", actual_lines.len(), expected_lines.len()));
+    }
+
+    let mut largest: Option<Deviation> = None;
+    for (index, (expected, actual)) in expected_lines.iter().zip(&actual_lines).enumerate() {
+        let (expected_tokens, actual_tokens) = (tokens(expected), tokens(actual));
+        if expected_tokens.len() != actual_tokens.len() {
+            return Err(format!("line {} differs besides its numbers", index + 1));
+        }
+        for (expected_token, actual_token) in expected_tokens.into_iter().zip(actual_tokens) {
+            let (expected_text, expected_number, actual_text, actual_number) = match (expected_token, actual_token) {
+                (Token::Number(expected_text, expected), Token::Number(actual_text, actual)) => (expected_text, expected, actual_text, actual),
+                (expected, actual) if expected == actual => continue,
+                _ => return Err(format!("line {} differs besides its numbers", index + 1)),
+            };
+            let difference = (actual_number - expected_number).abs();
+            if difference == 0.0 {
+                continue;
+            }
+            let deviation = Deviation { line: index + 1, expected: expected_text.to_owned(), actual: actual_text.to_owned(), difference };
+            let allowed = tolerance.absolute.max(tolerance.relative * expected_number.abs());
+            if difference > allowed {
+                return Err(format!("first off by {}", deviation));
+            }
+            if largest.as_ref().map(|largest| difference > largest.difference).unwrap_or(true) {
+                largest = Some(deviation);
+            }
+        }
+    }
+    Ok(largest)
+}
+
+/// Splits `line` into its words, and words into runs of numbers and of other characters. A sign
+/// belongs to the number it precedes unless it follows a letter or digit, as in `x-1`.
+fn tokens(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for word in line.split_whitespace() {
+        let bytes = word.as_bytes();
+        let digits_from = |mut index: usize| {
+            while index < bytes.len() && bytes[index].is_ascii_digit() {
+                index += 1;
+            }
+            index
+        };
+        let (mut index, mut text_start) = (0, 0);
+        while index < bytes.len() {
+            let signed = matches!(bytes[index], b'-' | b'+') && (index == 0 || !bytes[index - 1].is_ascii_alphanumeric());
+            let start = index;
+            let mut end = if signed { index + 1 } else { index };
+            let integer_end = digits_from(end);
+            let mut has_digits = integer_end > end;
+            end = integer_end;
+            if end < bytes.len() && bytes[end] == b'.' {
+                let fraction_end = digits_from(end + 1);
+                if fraction_end > end + 1 || has_digits {
+                    has_digits |= fraction_end > end + 1;
+                    end = fraction_end;
+                }
+            }
+            if has_digits && end < bytes.len() && matches!(bytes[end], b'e' | b'E') {
+                let exponent = if end + 1 < bytes.len() && matches!(bytes[end + 1], b'-' | b'+') { end + 2 } else { end + 1 };
+                if digits_from(exponent) > exponent {
+                    end = digits_from(exponent);
+                }
+            }
+            let number = Some(&word[start..end]).filter(|_| has_digits).and_then(|text| text.parse::<f64>().ok().map(|number| (text, number)));
+            match number {
+                Some((text, number)) => {
+                    if text_start < start {
+                        tokens.push(Token::Text(&word[text_start..start]));
+                    }
+                    tokens.push(Token::Number(text, number));
+                    index = end;
+                    text_start = end;
+                }
+                None => index += word[index..].chars().next().map(char::len_utf8).unwrap_or(1),
+            }
+        }
+        if text_start < bytes.len() {
+            tokens.push(Token::Text(&word[text_start..]));
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Token, compare_numbers, tokens};
+    use crate::model::Tolerance;
+
+    #[test]
+    fn splits_words_into_numbers_and_text() {
+        assert_eq!(tokens("loss=-0.25, step 3e2 x-1"), vec![
+            Token::Text("loss="), Token::Number("-0.25", -0.25), Token::Text(","),
+            Token::Text("step"), Token::Number("3e2", 300.0),
+            Token::Text("x-"), Token::Number("1", 1.0),
+        ]);
+        assert_eq!(tokens("v1.2. e5 ."), vec![
+            Token::Text("v"), Token::Number("1.2", 1.2), Token::Text("."), Token::Text("e"), Token::Number("5", 5.0), Token::Text("."),
+        ]);
+    }
+
+    #[test]
+    fn compares_numbers_within_tolerance_and_text_exactly() {
+        let tolerance = Tolerance { absolute: 1e-6, relative: 0.01 };
+        let largest = compare_numbers("mean 100.0\nerror 0.0000001\n", "mean 100.5\nerror 0.0000002\n", tolerance).unwrap().unwrap();
+        assert_eq!((largest.line, largest.actual.as_str()), (1, "100.5"));
+        assert_eq!(compare_numbers("a 1\n", "a 1\n", tolerance), Ok(None));
+
+        let beyond = compare_numbers("mean 100.0\nsd 1\n", "mean 100.5\nsd 2\n", tolerance).unwrap_err();
+        assert!(beyond.contains("line 2"), "{}", beyond);
+        assert!(compare_numbers("mean 1\n", "median 1\n", tolerance).is_err());
+        assert!(compare_numbers("1 2\n", "1\n", tolerance).is_err());
+        assert!(compare_numbers("1\n", "1\n2\n", tolerance).is_err());
+    }
+}
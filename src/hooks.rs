@@ -0,0 +1,160 @@
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+use crate::{
+    info,
+    warning,
+    AppError,
+    model::{Experiment, Project},
+    trace::traced_status,
+};
+use std::sync::Arc;
+
+/// Runs project level `commands` through `sh -c` from `working_dir` with the variables `env`
+/// set, appending their output to `log`. Stops at the first command that fails.
+pub fn run_hook(name: &str, commands: &[String], env: &[(String, String)], working_dir: &Path, log: &Path) -> Result<(), AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(log)
+        .map_err(into_err(log))?;
+
+    for command in commands {
+        writeln!(file, "$ {}", command).map_err(into_err(log))?;
+        let stderr = file.try_clone().map_err(into_err(log))?;
+        let stdout = file.try_clone().map_err(into_err(log))?;
+        let status = traced_status(Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(working_dir)
+            .envs(env.iter().map(|(name, value)| (name, value)))
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr))
+            .map_err(|e| AppError::IOError(command.to_owned(), Arc::new(e)))?;
+
+        if !status.success() {
+            return Err(AppError::ExternalError(format!(
+                "The {} command '{}' exited with {}, see {}.", name, command, status, log.display()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Setup and teardown of the `groups` of a run, shared by the workers running their members. The
+/// setup of a group runs when its first member is about to start, the other members waiting for
+/// it, and its teardown once every member finished or was skipped. Like the project ones, they
+/// run from the project folder and log into `setup-<group>.log` and `teardown-<group>.log` of the
+/// run logs.
+pub struct GroupHooks<'a> {
+    p: &'a Project,
+    states: BTreeMap<&'a str, Mutex<GroupState>>,
+}
+
+struct GroupState {
+    /// Whether the setup succeeded, `None` until it ran.
+    setup: Option<bool>,
+    /// Members that did not finish yet.
+    remaining: usize,
+    torn_down: bool,
+}
+
+impl<'a> GroupHooks<'a> {
+    /// Tracks the groups of the experiments of `p`, each waiting for all of its members.
+    pub fn new(p: &'a Project) -> GroupHooks<'a> {
+        Self::of(p, &p.experiments)
+    }
+
+    /// Tracks the groups of `experiments` only, as when a single one runs.
+    pub fn of(p: &'a Project, experiments: &'a [Experiment]) -> GroupHooks<'a> {
+        let mut states = BTreeMap::new();
+        for group in experiments.iter().filter_map(|exp| exp.group.as_deref()) {
+            states.entry(group)
+                .or_insert_with(|| Mutex::new(GroupState { setup: None, remaining: 0, torn_down: false }))
+                .get_mut()
+                .unwrap()
+                .remaining += 1;
+        }
+        GroupHooks { p, states }
+    }
+
+    /// Runs the setup of the group of `exp` unless it already ran. Fails with the group when
+    /// its setup failed, now or for a previous member.
+    pub fn enter(&self, exp: &Experiment) -> Result<(), String> {
+        let (name, state) = match self.state(exp) {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let mut state = state.lock().unwrap();
+        let succeeded = match state.setup {
+            Some(succeeded) => succeeded,
+            None => {
+                let setup = &self.p.groups[name].setup;
+                let succeeded = setup.is_empty() || {
+                    info!("Running the setup of {}", name);
+                    let log = self.p.run_logs_path().join(format!("setup-{}.log", name));
+                    match run_hook("setup", setup, &[], Path::new(&self.p.path), &log) {
+                        Ok(()) => true,
+                        Err(err) => {
+                            info!("{}", err);
+                            false
+                        }
+                    }
+                };
+                state.setup = Some(succeeded);
+                succeeded
+            }
+        };
+        if succeeded { Ok(()) } else { Err(name.to_owned()) }
+    }
+
+    /// Records that `exp` finished or will not run, tearing its group down after the last member.
+    pub fn leave(&self, exp: &Experiment) {
+        if let Some((name, state)) = self.state(exp) {
+            let mut state = state.lock().unwrap();
+            state.remaining = state.remaining.saturating_sub(1);
+            if state.remaining == 0 {
+                self.tear_down(name, &mut state);
+            }
+        }
+    }
+
+    /// Tears down the groups set up whose members did not all run, as when the run was stopped.
+    pub fn finish(&self) {
+        for (name, state) in &self.states {
+            self.tear_down(name, &mut state.lock().unwrap());
+        }
+    }
+
+    fn state(&self, exp: &Experiment) -> Option<(&'a str, &Mutex<GroupState>)> {
+        let group = exp.group.as_deref()?;
+        self.states.get_key_value(group).map(|(name, state)| (*name, state))
+    }
+
+    /// Runs the teardown of the group `name` once, if its setup ran, even when it failed.
+    fn tear_down(&self, name: &str, state: &mut GroupState) {
+        let teardown = &self.p.groups[name].teardown;
+        if state.setup.is_none() || state.torn_down || teardown.is_empty() {
+            return;
+        }
+        state.torn_down = true;
+        info!("Running the teardown of {}", name);
+        let log = self.p.run_logs_path().join(format!("teardown-{}.log", name));
+        if let Err(err) = run_hook("teardown", teardown, &[], Path::new(&self.p.path), &log) {
+            warning!(TeardownFailed, "teardown of {} failed: {}", name, err);
+        }
+    }
+}
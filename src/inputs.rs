@@ -0,0 +1,76 @@
+use std::{
+    fs::{create_dir_all, remove_file, rename},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use crate::{
+    info,
+    AppError,
+    model::{Experiment, Input},
+    sha256::sha256_file,
+    trace::traced_status,
+};
+use std::sync::Arc;
+
+/// Checks that every input of `exp` exists in `working_dir` with its expected checksum,
+/// downloading the missing ones that declare a `url`.
+pub fn verify_inputs(exp: &Experiment, working_dir: &Path) -> Result<(), AppError> {
+    for input in &exp.inputs {
+        let path = working_dir.join(&input.path);
+        if !path.exists() {
+            match &input.url {
+                Some(url) => download(input, url, &path)?,
+                None => return Err(AppError::MissingInput(exp.name.to_owned(), input.path.to_owned())),
+            }
+        }
+
+        let actual = sha256_file(&path)
+            .map_err(|e| AppError::IOError(path.to_str().unwrap().to_owned(), Arc::new(e)))?;
+        if actual != input.sha256 {
+            return Err(AppError::InputMismatch(
+                exp.name.to_owned(),
+                input.path.to_owned(),
+                input.sha256.to_owned(),
+                actual,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Downloads `url` next to `path` and only moves it in place once its checksum matches, so a
+/// partial or wrong download never passes for the real input.
+fn download(input: &Input, url: &str, path: &Path) -> Result<(), AppError> {
+    let into_err = |p: &Path| {
+        let p = p.to_str().unwrap().to_owned();
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
+    };
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent).map_err(into_err(parent))?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    info!("Downloading {}", input.path);
+    let status = traced_status(Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--output"])
+        .arg(&partial)
+        .arg(url)
+        .stdin(Stdio::null()))
+        .map_err(|e| AppError::IOError("curl".to_owned(), Arc::new(e)))?;
+    if !status.success() {
+        let _ = remove_file(&partial);
+        return Err(AppError::ExternalError(format!("Cannot download {} from {}.", input.path, url)));
+    }
+
+    let actual = sha256_file(&partial).map_err(into_err(&partial))?;
+    if actual != input.sha256 {
+        let _ = remove_file(&partial);
+        return Err(AppError::ExternalError(format!(
+            "The file downloaded from {} has checksum {}, expected {}.", url, actual, input.sha256
+        )));
+    }
+    rename(&partial, path).map_err(into_err(path))
+}
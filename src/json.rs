@@ -0,0 +1,261 @@
+use std::fmt;
+
+/// Minimal JSON document, objects keep their insertion order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+    }
+
+    pub fn string(text: &str) -> Json {
+        Json::String(text.to_owned())
+    }
+
+    /// `Null` when `value` is `None`.
+    pub fn optional<T>(value: Option<T>, to_json: impl FnOnce(T) -> Json) -> Json {
+        value.map(to_json).unwrap_or(Json::Null)
+    }
+
+    /// Parses a JSON document, the error telling what was expected at which byte.
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser { text, position: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(value),
+            Some(_) => Err(parser.error("the end of the document")),
+        }
+    }
+
+    /// Field `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    /// Byte offset of the next character.
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, expected: &str) -> String {
+        format!("expected {} at byte {}", expected, self.position)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\n' | b'\r' | b'\t')) {
+            self.position += 1;
+        }
+    }
+
+    fn literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        if !self.text[self.position..].starts_with(literal) {
+            return Err(self.error(literal));
+        }
+        self.position += literal.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[') => self.sequence(b']', |parser| parser.value()).map(Json::Array),
+            Some(b'{') => self.sequence(b'}', |parser| {
+                parser.skip_whitespace();
+                if parser.peek() != Some(b'"') {
+                    return Err(parser.error("a field name"));
+                }
+                let key = parser.string()?;
+                parser.skip_whitespace();
+                if parser.peek() != Some(b':') {
+                    return Err(parser.error("':'"));
+                }
+                parser.position += 1;
+                Ok((key, parser.value()?))
+            }).map(Json::Object),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.position;
+                while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+                    self.position += 1;
+                }
+                self.text[start..self.position].parse().map(Json::Number).map_err(|_| {
+                    self.position = start;
+                    self.error("a number")
+                })
+            }
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    /// Comma separated items of an array or object, from its opening bracket to `close`.
+    fn sequence<T>(&mut self, close: u8, mut item: impl FnMut(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        self.position += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.position += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(c) if c == close => {
+                    self.position += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.error(&format!("',' or '{}'", close as char))),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let mut string = String::new();
+        loop {
+            let rest = &self.text[self.position..];
+            let end = rest.find(['"', '\\']).ok_or_else(|| self.error("a closing '\"'"))?;
+            string.push_str(&rest[..end]);
+            self.position += end + 1;
+            if rest.as_bytes()[end] == b'"' {
+                return Ok(string);
+            }
+
+            let escaped = match self.peek() {
+                Some(b'"') => '"',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'b') => '\u{8}',
+                Some(b'f') => '\u{c}',
+                Some(b'u') => {
+                    let code = self.text.get(self.position + 1..self.position + 5)
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .ok_or_else(|| self.error("4 hexadecimal digits"))?;
+                    self.position += 4;
+                    // Surrogate pairs are not combined, replikate never writes them.
+                    char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                }
+                _ => return Err(self.error("an escape sequence")),
+            };
+            string.push(escaped);
+            self.position += 1;
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn parses_what_it_writes() {
+        let document = Json::object(vec![
+            ("name", Json::string("a \"quoted\"\tname\u{1}")),
+            ("values", Json::Array(vec![Json::Number(1.5), Json::Number(-2.0), Json::Null, Json::Bool(true)])),
+            ("empty", Json::Object(Vec::new())),
+        ]);
+        assert_eq!(Json::parse(&document.to_string()), Ok(document));
+    }
+
+    #[test]
+    fn parses_whitespace_and_escapes() {
+        let document = Json::parse(" { \"a\" : [ 1e3 , \"\\u00e9\\/\" ] } ").unwrap();
+        assert_eq!(document.get("a"), Some(&Json::Array(vec![Json::Number(1000.0), Json::string("é/")])));
+    }
+
+    #[test]
+    fn reports_where_the_document_is_invalid() {
+        assert_eq!(Json::parse("[1, 2"), Err("expected ',' or ']' at byte 5".to_owned()));
+        assert_eq!(Json::parse("{\"a\" 1}"), Err("expected ':' at byte 5".to_owned()));
+        assert_eq!(Json::parse("1 2"), Err("expected the end of the document at byte 2".to_owned()));
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    fs::{File, read_to_string},
+    io::Write,
+};
+use crate::{
+    AppError,
+    execute::ExperimentResult,
+};
+use std::sync::Arc;
+
+/// Number of trailing stderr lines embedded in a failure.
+const STDERR_SNIPPET_LINES: usize = 20;
+
+/// Writes a JUnit XML report with one `<testcase>` per experiment in a `<testsuite>` named
+/// after the project.
+pub fn write_junit(path: &str, suite: &str, results: &[ExperimentResult]) -> Result<(), AppError> {
+    let skipped = results.iter().filter(|result| reported_as_skipped(result)).count();
+    let failures = results.iter().filter(|result| !result.success()).count() - skipped;
+    let time: f64 = results.iter().map(|result| result.duration.as_secs_f64()).sum();
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let timestamp = results.first()
+        .map(|result| format!(" timestamp=\"{}\"", result.start.format("%Y-%m-%dT%H:%M:%S")))
+        .unwrap_or_default();
+    xml.push_str(&format!(
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\"{}>\n",
+        escape(suite), results.len(), failures, skipped, time, timestamp
+    ));
+
+    for result in results {
+        xml.push_str(&format!(
+            "  <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            escape(&result.name), escape(suite), result.duration.as_secs_f64()
+        ));
+        if result.success() {
+            xml.push_str("/>\n");
+            continue;
+        }
+
+        let message = match result.failed() && result.allow_failure {
+            true => format!("failed (allowed): {}", result.outcome),
+            false => result.outcome.to_string(),
+        };
+        if reported_as_skipped(result) {
+            xml.push_str(&format!(">\n    <skipped message=\"{}\"/>\n  </testcase>\n", escape(&message)));
+            continue;
+        }
+        let stderr = read_to_string(&result.stderr).unwrap_or_default();
+        let lines = stderr.lines().collect::<Vec<_>>();
+        let snippet = lines[lines.len().saturating_sub(STDERR_SNIPPET_LINES)..].join("\n");
+
+        xml.push_str(">\n");
+        xml.push_str(&format!("    <failure message=\"{}\">{}</failure>\n", escape(&message), escape(&snippet)));
+        xml.push_str("  </testcase>\n");
+    }
+    xml.push_str("</testsuite>\n");
+
+    File::create(path)
+        .and_then(|mut file| file.write_all(xml.as_bytes()))
+        .map_err(|e| AppError::IOError(path.to_owned(), Arc::new(e)))
+}
+
+/// Blocked and skipped experiments never ran, and those allowed to fail do not fail the run: they
+/// are reported as skipped rather than failed.
+fn reported_as_skipped(result: &ExperimentResult) -> bool {
+    result.blocked() || result.skipped() || (result.failed() && result.allow_failure)
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .fold(String::new(), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&apos;"),
+                c => escaped.push(c),
+            }
+            escaped
+        })
+}
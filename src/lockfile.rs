@@ -0,0 +1,105 @@
+use std::fs::read_to_string;
+use crate::model::{Requirement, ParsingError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockfileFormat {
+    /// One `name==version` entry per line, as produced by `pip freeze`.
+    Pinned,
+    Cargo,
+}
+
+impl LockfileFormat {
+    pub fn from_name(name: &str) -> Option<LockfileFormat> {
+        match name {
+            "pinned" | "pip" => Some(LockfileFormat::Pinned),
+            "cargo" => Some(LockfileFormat::Cargo),
+            _ => None,
+        }
+    }
+
+    pub fn guess(path: &str) -> LockfileFormat {
+        if path.ends_with("Cargo.lock") {
+            LockfileFormat::Cargo
+        } else {
+            LockfileFormat::Pinned
+        }
+    }
+}
+
+pub fn load_requirements(path: &str, format: LockfileFormat) -> Result<Vec<Requirement>, ParsingError> {
+    let content = read_to_string(path)
+        .map_err(|e| ParsingError::UnreadableFile(format!("{}: {}", path, e)))?;
+
+    match format {
+        LockfileFormat::Pinned => parse_pinned(path, &content),
+        LockfileFormat::Cargo => parse_cargo_lock(path, &content),
+    }
+}
+
+fn parse_pinned(path: &str, content: &str) -> Result<Vec<Requirement>, ParsingError> {
+    let mut requirements = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.split_once("==") {
+            Some((name, version)) if !name.trim().is_empty() && !version.trim().is_empty() => {
+                requirements.push(Requirement {
+                    name: name.trim().to_owned(),
+                    version: version.trim().to_owned(),
+                    install: None,
+                    path: None,
+                    any_of: Vec::new(),
+                    required: true,
+                })
+            }
+            _ => return Err(ParsingError::InvalidLockfile(
+                format!("{}:{}: expected `name==version`", path, number + 1)
+            )),
+        }
+    }
+
+    Ok(requirements)
+}
+
+fn parse_cargo_lock(path: &str, content: &str) -> Result<Vec<Requirement>, ParsingError> {
+    let mut requirements = Vec::new();
+    let mut name: Option<String> = None;
+    let mut version: Option<String> = None;
+    let mut in_package = false;
+
+    let mut flush = |name: &mut Option<String>, version: &mut Option<String>| {
+        match (name.take(), version.take()) {
+            (Some(name), Some(version)) => {
+                requirements.push(Requirement { name, version, install: None, path: None, any_of: Vec::new(), required: true });
+                Ok(())
+            }
+            (None, None) => Ok(()),
+            _ => Err(ParsingError::InvalidLockfile(
+                format!("{}: package without name or version", path)
+            )),
+        }
+    };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            flush(&mut name, &mut version)?;
+            in_package = line == "[[package]]";
+        } else if !in_package {
+            continue;
+        } else if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim().trim_matches('"').to_owned();
+            match key.trim() {
+                "name" => name = Some(value),
+                "version" => version = Some(value),
+                _ => {}
+            }
+        }
+    }
+    flush(&mut name, &mut version)?;
+
+    Ok(requirements)
+}
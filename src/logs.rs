@@ -0,0 +1,95 @@
+use std::{
+    fs::{create_dir_all, read, read_dir, read_to_string, write},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use crate::{
+    AppError,
+    model::Experiment,
+};
+use std::sync::Arc;
+
+/// File of the logs folder naming the run that last wrote experiment logs.
+pub const LATEST_RUN_FILE: &str = "latest-run";
+
+/// ID of the run that last wrote experiment logs into `logs`, `None` before the first run with
+/// an ID.
+pub fn latest_run(logs: &Path) -> Option<String> {
+    read_to_string(logs.join(LATEST_RUN_FILE)).ok()
+        .map(|run_id| run_id.trim().to_owned())
+        .filter(|run_id| !run_id.is_empty())
+}
+
+/// Records `run_id` as the latest run writing experiment logs into `logs`.
+pub fn record_run(logs: &Path, run_id: &str) -> Result<(), AppError> {
+    let path = logs.join(LATEST_RUN_FILE);
+    create_dir_all(logs)
+        .and_then(|_| write(&path, format!("{}\n", run_id)))
+        .map_err(|e| AppError::IOError(path.to_str().unwrap().to_owned(), Arc::new(e)))
+}
+
+/// Returns the log files of the latest run of `exp`: its combined `run-<timestamp>.log` in append
+/// mode, `stdout.log` and `stderr.log` otherwise.
+pub fn latest_logs(logs: &Path, exp: &Experiment) -> Result<Vec<PathBuf>, AppError> {
+    let log_dir = logs.join(exp.log_folder());
+    let modified = |path: &Path| path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let mut runs = Vec::new();
+    if log_dir.exists() {
+        for entry in read_dir(&log_dir).map_err(|e| AppError::IOError(log_dir.to_str().unwrap().to_owned(), Arc::new(e)))? {
+            let path = entry.map_err(|e| AppError::IOError(log_dir.to_str().unwrap().to_owned(), Arc::new(e)))?.path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            if name.starts_with("run-") && name.ends_with(".log") {
+                runs.push(path);
+            }
+        }
+    }
+    runs.sort();
+
+    let stdout = log_dir.join("stdout.log");
+    let stderr = log_dir.join("stderr.log");
+    match runs.pop() {
+        Some(run) if !stdout.exists() || modified(&run) >= modified(&stdout) => Ok(vec![run]),
+        _ => Ok(vec![stdout, stderr].into_iter().filter(|log| log.exists()).collect()),
+    }
+}
+
+/// Rewrites `log` as UTF-8 with LF line endings: invalid sequences become U+FFFD and CRLF pairs
+/// become LF. A missing log, as for a stream that is not captured, is left alone.
+pub fn normalize_log(log: &Path) -> io::Result<()> {
+    let bytes = match read(log) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let normalized = String::from_utf8_lossy(&bytes).replace("\r\n", "\n");
+    if normalized.as_bytes() != bytes.as_slice() {
+        write(log, normalized)?;
+    }
+    Ok(())
+}
+
+/// Prints the captured output of the latest run of `exp`, limited to its last `tail` lines.
+pub fn show_log(logs: &Path, exp: &Experiment, tail: Option<usize>) -> Result<(), AppError> {
+    let files = latest_logs(logs, exp)?;
+    if files.is_empty() {
+        return Err(AppError::ExternalError(format!("No logs found for {} in {}.", exp.name, logs.display())));
+    }
+
+    for file in files {
+        let content = read_to_string(&file)
+            .map_err(|e| AppError::IOError(file.to_str().unwrap().to_owned(), Arc::new(e)))?;
+        let lines = content.lines().collect::<Vec<_>>();
+        let from = tail.map(|tail| lines.len().saturating_sub(tail)).unwrap_or(0);
+
+        println!("==> {} <==", file.display());
+        for line in &lines[from..] {
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
@@ -1,20 +1,43 @@
 use std::{
+    collections::BTreeMap,
     env,
-    fs::{File, create_dir},
-    io::{BufReader, BufRead},
-    path::Path
+    fs::{create_dir, create_dir_all, read_to_string},
+    io::ErrorKind,
+    path::Path,
+    time::Instant,
 };
+use chrono::Local;
 use seahorse::{App, Command, Context, Flag, FlagType};
-use yaml_rust::YamlLoader;
+use yaml_rust::{YamlLoader, YamlEmitter};
 use crate::{
     git::git,
     build::build,
-    execute::execute,
-    clean::clean,
-    model::{Project, FromYamlDocument, ParsingError},
-    AppError::IOError
+    execute::{aggregate, execute, check_results, stress, summarize, ExecutionOptions, ExperimentResult, LogMode},
+    junit::write_junit,
+    report::{merge_reports, recorded_order, write_report},
+    json::Json,
+    logs::{LATEST_RUN_FILE, latest_run, show_log},
+    artifacts::list_outputs,
+    query::print_query,
+    selection::{Selection, include_dependencies, shard},
+    units::{parse_duration, parse_size, format_size},
+    disk::free_space,
+    glob::glob,
+    requirements::{check_requirements, install, print_requirements, requirements_json, skip_missing_optional},
+    clean::{clean, prune},
+    hooks::run_hook,
+    model::{Project, Experiment, ToYamlDocument, ParsingError, CONFIG_VERSION, MIN_CONFIG_VERSION},
+    output::{WarningKind, is_quiet, raised_warnings, set_quiet},
+    trace::set_tracing,
+    watch::watch,
+    explain::explain,
+    fetch::{fetch_config, is_url, url_project_path},
+    verify::verify,
+    timeline::write_concurrency_report,
+    compare::{DEFAULT_THRESHOLD, compare},
+    scheduler::ProcessLimit,
 };
-use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
 
 mod model;
 mod git;
@@ -22,6 +45,41 @@ mod build;
 mod execute;
 mod tsv;
 mod clean;
+mod lockfile;
+mod output;
+mod glob;
+mod hooks;
+mod junit;
+mod requirements;
+mod scheduler;
+mod version;
+mod json;
+mod logs;
+mod selection;
+mod units;
+mod disk;
+mod sha256;
+mod fetch;
+mod inputs;
+mod watch;
+mod vars;
+mod provenance;
+mod report;
+mod diff;
+mod golden;
+mod profile;
+mod dotenv;
+mod explain;
+mod verify;
+mod timeline;
+mod auth;
+mod compare;
+mod artifacts;
+mod trace;
+mod process;
+mod cgroup;
+mod query;
+mod metrics;
 
 fn main() {
     let args = std::env::args().collect::<Vec<_>>();
@@ -31,10 +89,80 @@ fn main() {
         .version(env!("CARGO_PKG_VERSION"))
         .action(safe_wrapper)
         .flag(Flag::new("requirements", "replikate [config] --requirements", FlagType::Bool))
+        .flag(Flag::new("report-format", "replikate [config] --requirements --report-format [text|json]", FlagType::String))
+        .flag(Flag::new("install", "replikate [config] --install", FlagType::Bool))
+        .flag(Flag::new("install-all", "replikate [config] --install-all", FlagType::Bool))
+        .flag(Flag::new("strict-requirements", "replikate [config] --run --strict-requirements", FlagType::Bool))
+        .flag(Flag::new("require-for-build", "replikate [config] --build --require-for-build", FlagType::Bool))
+        .flag(Flag::new("only", "replikate [config] --only [experiment,...]", FlagType::String))
+        .flag(Flag::new("skip", "replikate [config] --skip [experiment,...]", FlagType::String))
+        .flag(Flag::new("tag", "replikate [config] --tag [tag,...]", FlagType::String))
+        .flag(Flag::new("exclude-tag", "replikate [config] --exclude-tag [tag,...]", FlagType::String))
+        .flag(Flag::new("auto-deps", "replikate [config] --only [experiment] --auto-deps", FlagType::Bool))
+        .flag(Flag::new("strict-deps", "replikate [config] --only [experiment] --strict-deps", FlagType::Bool))
+        .flag(Flag::new("list", "replikate [config] --list", FlagType::Bool))
+        .flag(Flag::new("list-outputs", "replikate [config] --list-outputs", FlagType::Bool))
         .flag(Flag::new("git", "replikate [config] --git(-g)", FlagType::Bool).alias("g"))
+        .flag(Flag::new("max-clone-size", "replikate [config] --git --max-clone-size [bytes]", FlagType::String))
         .flag(Flag::new("build", "replikate [config] --build(-b)", FlagType::Bool).alias("b"))
         .flag(Flag::new("run", "replikate [config] --run(-r)", FlagType::Bool).alias("r"))
         .flag(Flag::new("clean", "replikate [config] --clean", FlagType::Bool))
+        .flag(Flag::new("prune", "replikate [config] --prune --keep-last [count] --older-than [duration]", FlagType::Bool))
+        .flag(Flag::new("keep-last", "replikate [config] --prune --keep-last [count]", FlagType::String))
+        .flag(Flag::new("older-than", "replikate [config] --prune --older-than [duration]", FlagType::String))
+        .flag(Flag::new("keep-run", "replikate [config] --prune --keep-run [id,...]", FlagType::String))
+        .flag(Flag::new("dry-run", "replikate [config] --prune --dry-run", FlagType::Bool))
+        .flag(Flag::new("force", "replikate [config] --prune --force", FlagType::Bool))
+        .flag(Flag::new("no-tree", "replikate [config] --run --no-tree", FlagType::Bool))
+        .flag(Flag::new("quiet", "replikate [config] --quiet(-q)", FlagType::Bool).alias("q"))
+        .flag(Flag::new("trace", "replikate [config] --trace", FlagType::Bool))
+        .flag(Flag::new("error-format", "replikate [config] --error-format [human|json]", FlagType::String))
+        .flag(Flag::new("fail-on-warning", "replikate [config] --fail-on-warning [kind,...]", FlagType::String))
+        .flag(Flag::new("check-programs", "replikate [config] --check-programs", FlagType::Bool))
+        .flag(Flag::new("out-each", "replikate [config] --out-each [root,...]", FlagType::String))
+        .flag(Flag::new("logs-dir", "replikate [config] --logs-dir [path]", FlagType::String))
+        .flag(Flag::new("min-free-disk", "replikate [config] --min-free-disk [size]", FlagType::String))
+        .flag(Flag::new("watch", "replikate [config] --run --watch", FlagType::Bool))
+        .flag(Flag::new("print-config", "replikate [config] --print-config", FlagType::Bool))
+        .flag(Flag::new("query", "replikate [config] --query [path]", FlagType::String))
+        .flag(Flag::new("explain", "replikate [config] --explain", FlagType::Bool))
+        .flag(Flag::new("verify", "replikate [config] --verify", FlagType::Bool))
+        .flag(Flag::new("shard", "replikate [config] --run --shard [index]/[count]", FlagType::String))
+        .flag(Flag::new("jobs", "replikate [config] --run --jobs(-j) [count]", FlagType::String).alias("j"))
+        .flag(Flag::new("max-parallel", "replikate [config] --max-parallel [count]", FlagType::String))
+        .flag(Flag::new("replay", "replikate [config] --run --replay [report.json]", FlagType::String))
+        .flag(Flag::new("fail-fast", "replikate [config] --run --fail-fast", FlagType::Bool))
+        .flag(Flag::new("aggregate-always", "replikate [config] --run --aggregate-always", FlagType::Bool))
+        .flag(Flag::new("report", "replikate [config] --run --report [report.json]", FlagType::String))
+        .flag(Flag::new("max-failures", "replikate [config] --run --max-failures [count]", FlagType::String))
+        .flag(Flag::new("deadline", "replikate [config] --run --deadline [duration]", FlagType::String))
+        .flag(Flag::new("max-runtime-per-experiment", "replikate [config] --run --max-runtime-per-experiment [duration]", FlagType::String))
+        .flag(Flag::new("profile", "replikate [config] --run --profile", FlagType::Bool))
+        .flag(Flag::new("update-golden", "replikate [config] --run --update-golden", FlagType::Bool))
+        .flag(Flag::new("junit", "replikate [config] --run --junit [report.xml]", FlagType::String))
+        .flag(Flag::new("concurrency-report", "replikate [config] --run --jobs [n] --concurrency-report [timeline.csv]", FlagType::String))
+        .flag(Flag::new("stress", "replikate [config] --stress [experiment]", FlagType::String))
+        .flag(Flag::new("stress-max", "replikate [config] --stress [experiment] --stress-max [iterations]", FlagType::String))
+        .flag(Flag::new("show-log", "replikate [config] --show-log [experiment]", FlagType::String))
+        .flag(Flag::new("tail", "replikate [config] --show-log [experiment] --tail [lines]", FlagType::String))
+        .flag(Flag::new("run-id", "replikate [config] --run --run-id [id]", FlagType::String))
+        .flag(Flag::new("log-mode", "replikate [config] --log-mode [overwrite|append]", FlagType::String))
+        .flag(Flag::new("env", "replikate [config] --run --env [KEY=VALUE]", FlagType::String))
+        .flag(Flag::new("normalize-logs", "replikate [config] --run --normalize-logs", FlagType::Bool))
+        .flag(Flag::new("keep-runs", "replikate [config] --log-mode append --keep-runs [count]", FlagType::String))
+        .flag(Flag::new("keep-size", "replikate [config] --log-mode append --keep-size [bytes]", FlagType::String))
+        .command(Command::new()
+            .name("compare")
+            .usage("replikate compare [old.json] [new.json] --threshold [percent]")
+            .action(compare_wrapper)
+            .flag(Flag::new("threshold", "replikate compare [old.json] [new.json] --threshold [percent]", FlagType::String))
+            .flag(Flag::new("error-format", "replikate compare [old.json] [new.json] --error-format [text|json]", FlagType::String)))
+        .command(Command::new()
+            .name("merge")
+            .usage("replikate merge [reports...] --output(-o) [merged.json]")
+            .action(merge_wrapper)
+            .flag(Flag::new("output", "replikate merge [reports...] --output(-o) [merged.json]", FlagType::String).alias("o"))
+            .flag(Flag::new("error-format", "replikate merge [reports...] --output [merged.json] --error-format [text|json]", FlagType::String)))
         .command(Command::new().name("help").usage("help"));
     app.run(args);
 }
@@ -42,83 +170,945 @@ fn main() {
 #[derive(Clone, Debug)]
 pub enum AppError {
     MissingArgument(&'static str),
-    IOError(String, Rc<std::io::Error>),
+    IOError(String, Arc<std::io::Error>),
     ExternalError(String),
     Parsing(ParsingError),
+    ExperimentsFailed(Vec<String>),
+    InvalidArgument(&'static str, String),
+    UnknownExperiment(String),
+    /// Path, available bytes and required bytes.
+    InsufficientDiskSpace(String, u64, u64),
+    /// Missing config path and the YAML files found next to it.
+    ConfigNotFound(String, Vec<String>),
+    ConfigPermissionDenied(String),
+    /// Config URL and why it could not be downloaded.
+    ConfigFetchFailed(String, String),
+    /// Experiment and input path.
+    MissingInput(String, String),
+    /// Experiment, input path, expected and actual checksum.
+    InputMismatch(String, String, String, String),
+    /// TSV file and how a row or its header does not match the table.
+    TableMismatch(String, String),
+    /// Selected experiments and the unselected experiments they depend on.
+    MissingDependencies(Vec<(String, String)>),
+    /// Path of an `env_file` that is not marked optional.
+    MissingEnvFile(String),
+    /// Pairs of flags given together that contradict each other, with why.
+    /// Each flag, the flag it conflicts with, `None` for one given without the flag it needs, and
+    /// why.
+    ConflictingFlags(Vec<(String, Option<String>, String)>),
+    /// Kinds of the warnings given to `--fail-on-warning` that were raised, with how many times.
+    FatalWarnings(Vec<(String, usize)>),
+    /// A configuration was given without any flag telling what to do with it.
+    NoAction,
+}
+
+/// Exit code when nothing was asked for, told apart from the 1 of failures.
+const NO_ACTION_EXIT_CODE: i32 = 2;
+
+impl AppError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::NoAction => NO_ACTION_EXIT_CODE,
+            _ => 1,
+        }
+    }
 }
 
 fn safe_wrapper(c: &Context) {
-    let execution = run_app(c);
+    set_quiet(c.bool_flag("quiet"));
+    set_tracing(c.bool_flag("trace"));
+    let execution = fail_on_warnings(c).and_then(|fatal| {
+        run_app(c)?;
+        fatal_warnings(&raised_warnings(), &fatal)
+    });
+    exit_on_error(c, execution);
+}
+
+/// Kinds of warnings `--fail-on-warning` makes fatal.
+fn fail_on_warnings(c: &Context) -> Result<Vec<WarningKind>, AppError> {
+    repeated_flag(c, "fail-on-warning").iter()
+        .map(|name| WarningKind::from_name(name).ok_or_else(|| AppError::ExternalError(format!(
+            "Unknown warning kind '{}' for '--fail-on-warning', expected one of: {}.",
+            name, WarningKind::ALL.iter().map(|kind| kind.name()).collect::<Vec<_>>().join(", ")
+        ))))
+        .collect()
+}
+
+/// Evaluated once everything ran: fails when a warning of a `fatal` kind was raised.
+fn fatal_warnings(raised: &BTreeMap<WarningKind, usize>, fatal: &[WarningKind]) -> Result<(), AppError> {
+    let fatal = raised.iter()
+        .filter(|(kind, _)| fatal.contains(kind))
+        .map(|(kind, count)| (kind.name().to_owned(), *count))
+        .collect::<Vec<_>>();
+    if fatal.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::FatalWarnings(fatal))
+    }
+}
+
+fn compare_wrapper(c: &Context) {
+    let compared = match c.args.as_slice() {
+        [old, new] => parse_flag(c, "threshold")
+            .and_then(|threshold| compare(old, new, threshold.unwrap_or(DEFAULT_THRESHOLD))),
+        [_] | [] => Err(AppError::MissingArgument("reports")),
+        [_, _, extra, ..] => Err(AppError::InvalidArgument("reports", extra.to_owned())),
+    };
+    exit_on_error(c, compared);
+}
+
+fn merge_wrapper(c: &Context) {
+    let merged = match (c.string_flag("output"), c.args.as_slice()) {
+        (None, _) => Err(AppError::MissingArgument("output")),
+        (Some(_), []) => Err(AppError::MissingArgument("reports")),
+        (Some(output), reports) => merge_reports(&output, reports),
+    };
+    exit_on_error(c, merged);
+}
+
+fn exit_on_error(c: &Context, execution: Result<(), AppError>) {
     if let Some(err) = execution.err() {
-        match err {
-            AppError::MissingArgument(name) => println!("Missing argument '{}', use --help to show usage.", name),
-            AppError::IOError(path, sub_error) => println!("{} for '{}'.", sub_error, path),
-            AppError::ExternalError(message) => println!("{}", message),
-            AppError::Parsing(err) => { println!("Cannot parse the configuration file: {:?}", err) }
+        match c.string_flag("error-format").as_deref() {
+            Some("json") => eprintln!("{}", error_to_json(&err)),
+            _ if is_quiet() => eprintln!("{}", err),
+            _ => println!("{}", err),
         }
+        std::process::exit(err.exit_code());
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let message = match self {
+            AppError::MissingArgument(name) => format!("Missing argument '{}', use --help to show usage.", name),
+            AppError::IOError(path, sub_error) => format!("{} for '{}'.", sub_error, path),
+            AppError::ExternalError(message) => message.to_owned(),
+            AppError::Parsing(ParsingError::UnsupportedVersion(version)) => format!(
+                "The configuration file declares version {} but this replikate supports versions {} to {}, try upgrading replikate.",
+                version, MIN_CONFIG_VERSION, CONFIG_VERSION
+            ),
+            AppError::Parsing(ParsingError::RemovedField(field, hint)) => format!(
+                "The configuration field '{}' is no longer supported: {}", field, hint
+            ),
+            AppError::Parsing(ParsingError::UndefinedVariable(name)) => format!(
+                "The variable '{}' is neither declared in 'vars' nor set in the environment.", name
+            ),
+            AppError::Parsing(ParsingError::CyclicVariables(cycle)) => format!(
+                "The variables {} reference each other.", cycle.join(" -> ")
+            ),
+            AppError::Parsing(ParsingError::UnknownDependency(experiment, dependency)) => format!(
+                "{} depends on '{}' which is not an experiment of the configuration file.", experiment, dependency
+            ),
+            AppError::Parsing(ParsingError::UnknownRequirement(experiment, requirement)) => format!(
+                "{} requires '{}' which is not a requirement of the configuration file.", experiment, requirement
+            ),
+            AppError::Parsing(ParsingError::UnknownGroup(experiment, group)) => format!(
+                "{} belongs to the group '{}' which is not declared in 'groups'.", experiment, group
+            ),
+            AppError::Parsing(ParsingError::UnsatisfiableResource(experiment, resource, requested, capacity)) => format!(
+                "{} requests {} {} but the project 'resources' only provide {}.", experiment, requested, resource, capacity
+            ),
+            AppError::Parsing(ParsingError::CyclicDependencies(cycle)) => format!(
+                "The experiments {} depend on each other.", cycle.join(" -> ")
+            ),
+            AppError::Parsing(ParsingError::UnknownBuildDependency(repository, dependency)) => format!(
+                "The build of {} depends on '{}' which is not a repository of the configuration file.", repository, dependency
+            ),
+            AppError::Parsing(ParsingError::CyclicBuildDependencies(cycle)) => format!(
+                "The builds of {} depend on each other.", cycle.join(" -> ")
+            ),
+            AppError::Parsing(ParsingError::UnknownTool(name)) => format!(
+                "The tool '{}' is referenced but not declared in 'tools'.", name
+            ),
+            AppError::Parsing(ParsingError::UnterminatedVariable(text)) => format!(
+                "Missing '}}' after '${{' in '{}'.", text
+            ),
+            AppError::Parsing(ParsingError::UndefinedParam(experiment, param)) => format!(
+                "The command of {} uses '{{{}}}' which is not declared in its 'params'.", experiment, param
+            ),
+            AppError::Parsing(ParsingError::UnusedParam(experiment, param)) => format!(
+                "The param '{}' of {} is not used by its command.", param, experiment
+            ),
+            AppError::Parsing(ParsingError::UncapturedGolden(experiment)) => format!(
+                "{} compares its stdout to a golden file, its 'capture' must include stdout.", experiment
+            ),
+            AppError::Parsing(ParsingError::EmptyForEach(experiment, pattern)) => format!(
+                "The for_each pattern '{}' of {} matches no file.", pattern, experiment
+            ),
+            AppError::Parsing(ParsingError::ForEachCollision(experiment, stem)) => format!(
+                "Several files matching the for_each of {} are named '{}', their experiments would share a name.", experiment, stem
+            ),
+            AppError::Parsing(ParsingError::DuplicateExperiment(name)) => format!(
+                "The experiment '{}' is declared more than once.", name
+            ),
+            AppError::Parsing(err) => format!("Cannot parse the configuration file: {:?}", err),
+            AppError::InvalidArgument(flag, value) => format!("Invalid value '{}' for '--{}'.", value, flag),
+            AppError::UnknownExperiment(name) => format!("No experiment named '{}' in the configuration file.", name),
+            AppError::ExperimentsFailed(names) => format!("{} experiment(s) failed: {}", names.len(), names.join(", ")),
+            AppError::InsufficientDiskSpace(path, available, required) => format!(
+                "Only {} free on '{}' but {} are required.", format_size(*available), path, format_size(*required)
+            ),
+            AppError::ConfigNotFound(path, nearby) if nearby.is_empty() => format!(
+                "The configuration file '{}' does not exist, check the path.", path
+            ),
+            AppError::ConfigNotFound(path, nearby) => format!(
+                "The configuration file '{}' does not exist, did you mean one of: {}?", path, nearby.join(", ")
+            ),
+            AppError::MissingInput(experiment, path) => format!(
+                "The input '{}' of {} does not exist and has no url to download it from.", path, experiment
+            ),
+            AppError::InputMismatch(experiment, path, expected, actual) => format!(
+                "The input '{}' of {} has checksum {}, expected {}.", path, experiment, actual, expected
+            ),
+            AppError::MissingDependencies(missing) => format!(
+                "Selected experiments depend on unselected ones: {}. Select them too or use --auto-deps.",
+                missing.iter().map(|(dependent, dependency)| format!("{} needs {}", dependent, dependency)).collect::<Vec<_>>().join(", ")
+            ),
+            AppError::MissingEnvFile(path) => format!(
+                "The env file '{}' does not exist, mark it 'optional: true' to ignore it.", path
+            ),
+            AppError::TableMismatch(path, reason) => format!("Cannot append to '{}': {}.", path, reason),
+            AppError::ConflictingFlags(conflicts) => format!(
+                "Conflicting flags: {}.",
+                conflicts.iter()
+                    .map(|(flag, other, reason)| match other {
+                        Some(other) => format!("--{} with --{} ({})", flag, other, reason),
+                        None => format!("--{} ({})", flag, reason),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ),
+            AppError::FatalWarnings(warnings) => format!(
+                "Failing on the warnings given to '--fail-on-warning': {}.",
+                warnings.iter().map(|(kind, count)| format!("{} ({})", kind, count)).collect::<Vec<_>>().join(", ")
+            ),
+            AppError::NoAction => "No action given, nothing was done: add a phase such as --run, --build or --git, or use --help to show usage.".to_owned(),
+            AppError::ConfigPermissionDenied(path) => format!(
+                "Permission denied while reading the configuration file '{}', check its permissions.", path
+            ),
+            AppError::ConfigFetchFailed(url, reason) => format!("Cannot download the configuration file '{}': {}.", url, reason),
+        };
+        write!(f, "{}", message)
     }
 }
 
+/// Structured form of `err` for `--error-format json`, `kind` naming the `AppError` variant.
+fn error_to_json(err: &AppError) -> Json {
+    let (kind, context) = match err {
+        AppError::MissingArgument(name) => ("missing_argument", vec![("argument", Json::string(name))]),
+        AppError::IOError(path, _) => ("io_error", vec![("path", Json::string(path))]),
+        AppError::ExternalError(_) => ("external_error", vec![]),
+        AppError::Parsing(_) => ("parsing", vec![]),
+        AppError::ExperimentsFailed(names) => (
+            "experiments_failed",
+            vec![("experiments", Json::Array(names.iter().map(|name| Json::string(name)).collect()))],
+        ),
+        AppError::InvalidArgument(flag, value) => (
+            "invalid_argument",
+            vec![("argument", Json::string(flag)), ("value", Json::string(value))],
+        ),
+        AppError::UnknownExperiment(name) => ("unknown_experiment", vec![("experiment", Json::string(name))]),
+        AppError::ConfigNotFound(path, nearby) => (
+            "config_not_found",
+            vec![
+                ("path", Json::string(path)),
+                ("nearby", Json::Array(nearby.iter().map(|config| Json::string(config)).collect())),
+            ],
+        ),
+        AppError::ConfigPermissionDenied(path) => ("config_permission_denied", vec![("path", Json::string(path))]),
+        AppError::ConfigFetchFailed(url, reason) => (
+            "config_fetch_failed",
+            vec![("url", Json::string(url)), ("reason", Json::string(reason))],
+        ),
+        AppError::MissingInput(experiment, path) => (
+            "missing_input",
+            vec![("experiment", Json::string(experiment)), ("path", Json::string(path))],
+        ),
+        AppError::InputMismatch(experiment, path, expected, actual) => (
+            "input_mismatch",
+            vec![
+                ("experiment", Json::string(experiment)),
+                ("path", Json::string(path)),
+                ("expected", Json::string(expected)),
+                ("actual", Json::string(actual)),
+            ],
+        ),
+        AppError::MissingDependencies(missing) => (
+            "missing_dependencies",
+            vec![("dependencies", Json::Array(missing.iter()
+                .map(|(dependent, dependency)| Json::object(vec![
+                    ("experiment", Json::string(dependent)),
+                    ("dependency", Json::string(dependency)),
+                ]))
+                .collect()))],
+        ),
+        AppError::MissingEnvFile(path) => ("missing_env_file", vec![("path", Json::string(path))]),
+        AppError::TableMismatch(path, _) => ("table_mismatch", vec![("path", Json::string(path))]),
+        AppError::ConflictingFlags(conflicts) => (
+            "conflicting_flags",
+            vec![("conflicts", Json::Array(conflicts.iter()
+                .map(|(flag, other, reason)| Json::object(vec![
+                    ("flags", Json::Array(std::iter::once(flag).chain(other).map(|flag| Json::string(flag)).collect())),
+                    ("reason", Json::string(reason)),
+                ]))
+                .collect()))],
+        ),
+        AppError::NoAction => ("no_action", vec![]),
+        AppError::FatalWarnings(warnings) => (
+            "fatal_warnings",
+            vec![("warnings", Json::Array(warnings.iter()
+                .map(|(kind, count)| Json::object(vec![("kind", Json::string(kind)), ("count", Json::Number(*count as f64))]))
+                .collect()))],
+        ),
+        AppError::InsufficientDiskSpace(path, available, required) => (
+            "insufficient_disk_space",
+            vec![
+                ("path", Json::string(path)),
+                ("available", Json::Number(*available as f64)),
+                ("required", Json::Number(*required as f64)),
+            ],
+        ),
+    };
+
+    Json::object(vec![
+        ("kind", Json::string(kind)),
+        ("message", Json::string(&err.to_string())),
+        ("exit_code", Json::Number(err.exit_code() as f64)),
+        ("context", Json::object(context)),
+    ])
+}
+
+/// When replikate started, from which `--deadline` counts.
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
 fn run_app(c: &Context) -> Result<(), AppError> {
-    let config = c.args.first()
+    STARTED.get_or_init(Instant::now);
+    let conflicts = flag_conflicts(c);
+    if !conflicts.is_empty() {
+        return Err(AppError::ConflictingFlags(conflicts));
+    }
+
+    let config = *positional_args(c).first()
         .ok_or(AppError::MissingArgument("config"))?;
 
-    let config_file = File::open(config)
-        .map_err(|err| AppError::IOError(config.to_owned(), Rc::new(err)))?;
+    let selection = selection(c);
+    let (project, included) = load_project(c, config, &selection)?;
+
+    if c.bool_flag("check-programs") {
+        project.check_programs().map_err(AppError::Parsing)?;
+    }
+
+    if c.bool_flag("print-config") {
+        let mut output = String::new();
+        YamlEmitter::new(&mut output).dump(&project.to_yaml())
+            .map_err(|e| AppError::ExternalError(format!("Cannot print the configuration: {:?}", e)))?;
+        println!("{}", output);
+        return Ok(());
+    }
+
+    if let Some(path) = c.string_flag("query") {
+        return print_query(&project, &path);
+    }
+
+    if c.bool_flag("verify") {
+        return verify(&project);
+    }
+
+    if c.bool_flag("explain") {
+        return explain(&project, jobs(c)?);
+    }
+
+    if c.bool_flag("list") {
+        list_experiments(&project, &selection);
+        return Ok(());
+    }
+
+    if c.bool_flag("list-outputs") {
+        return list_outputs(&project);
+    }
+
+    if let Some(name) = c.string_flag("show-log") {
+        return show_log(&project.run_logs_path(), find_experiment(&project, &name)?, parse_flag(c, "tail")?);
+    }
+
+    if !PHASE_FLAGS.iter().any(|flag| given(c, flag)) {
+        // `--check-programs` alone is a check worth running.
+        return if c.bool_flag("check-programs") { Ok(()) } else { Err(AppError::NoAction) };
+    }
+
+    if project.version.is_none() {
+        info!("Notice: {} does not declare a 'version', assuming version {}.", config, CONFIG_VERSION);
+    }
+    for (dependent, dependency) in &included {
+        info!("Notice: including {}, needed by {}", dependency, dependent);
+    }
+
+    if c.bool_flag("watch") {
+        if is_url(config) {
+            return Err(AppError::ExternalError("--watch needs a local configuration file to watch.".to_owned()));
+        }
+        return watch(
+            Path::new(config),
+            project,
+            || load_project(c, config, &selection).map(|(project, _)| project),
+            |project| {
+                if let Err(err) = run_pipeline(c, project).and_then(|results| check_results(&results)) {
+                    info!("{}", err);
+                }
+            },
+        );
+    }
+
+    let roots = repeated_flag(c, "out-each");
+    if roots.is_empty() {
+        let results = run_pipeline(c, &project)?;
+        return check_results(&results);
+    }
+
+    let mut outcomes = Vec::new();
+    for root in &roots {
+        info!("Running into {}", root);
+        let logs_dir = project.logs_dir.as_ref()
+            .map(|logs| Path::new(logs).join(Path::new(root).file_name().unwrap_or_default()).to_string_lossy().into_owned());
+        let rooted = project.clone()
+            .set_path(root)
+            .set_logs_dir(logs_dir);
+        outcomes.push((root, run_pipeline(c, &rooted)));
+    }
 
-    let buf = BufReader::new(config_file);
-    let mut file_content = String::new();
+    info!("Summary:");
+    let mut broken_roots = Vec::new();
+    let mut failures = Vec::new();
+    for (root, outcome) in &outcomes {
+        match outcome {
+            Ok(results) => {
+                let failed = results.iter()
+                    .filter(|result| result.counts_as_failure())
+                    .map(|result| result.name.as_str())
+                    .collect::<Vec<_>>();
+                info!("  {}: {}{}", root, summarize(results),
+                    if failed.is_empty() { String::new() } else { format!(" ({} failed)", failed.join(", ")) });
+                failures.extend(failed.iter().map(|name| format!("{}/{}", root, name)));
+            }
+            Err(err) => {
+                info!("  {}: {}", root, err);
+                broken_roots.push(root.as_str());
+            }
+        }
+    }
 
-    for line in buf.lines() {
-        let line = line.map_err(|e| IOError(config.to_owned(), Rc::new(e)))?;
-        file_content.push_str(&line);
-        file_content.push('\n');
+    if !broken_roots.is_empty() {
+        Err(AppError::ExternalError(format!("{} output root(s) failed: {}", broken_roots.len(), broken_roots.join(", "))))
+    } else if !failures.is_empty() {
+        Err(AppError::ExperimentsFailed(failures))
+    } else {
+        Ok(())
     }
+}
+
+/// Reads and parses `config`, then keeps the experiments picked by `selection`. Also returns the
+/// dependencies included back by `--auto-deps`, as (dependent, dependency) pairs.
+fn load_project(c: &Context, config: &str, selection: &Selection) -> Result<(Project, Vec<(String, String)>), AppError> {
+    let file_content = if is_url(config) {
+        fetch_config(config)?
+    } else {
+        read_to_string(config)
+            .map_err(|err| match err.kind() {
+                ErrorKind::NotFound => AppError::ConfigNotFound(config.to_owned(), nearby_configs(config)),
+                ErrorKind::PermissionDenied => AppError::ConfigPermissionDenied(config.to_owned()),
+                ErrorKind::InvalidData => AppError::ExternalError(format!("{} is not a valid UTF-8 file.", config)),
+                _ => AppError::IOError(config.to_owned(), Arc::new(err)),
+            })?
+    };
 
     let yaml_doc = YamlLoader::load_from_str(&file_content)
         .map_err(|_| AppError::ExternalError(format!("Cannot parse {} as yaml file.", config).to_owned()))?;
 
-    let path = if config.contains('.') {
-        let index_of_last_dot = config.rfind('.').unwrap();
-        config[0..index_of_last_dot].to_owned()
+    // A downloaded config has its project folder and relative paths in the current directory.
+    let path = if is_url(config) {
+        url_project_path(config).ok_or_else(|| AppError::ExternalError(format!(
+            "Cannot name the project folder after {}, its path has no file name.", config
+        )))?
     } else {
-        config.to_owned()
+        derive_project_path(config)
+    };
+    let config_dir = match Path::new(config).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !is_url(config) => dir,
+        _ => Path::new("."),
     };
-    let project = Project::from_yaml(&yaml_doc[0])
-        .map_err(|e| AppError::Parsing(e))?
+    let project = Project::from_config(&yaml_doc[0], config_dir)
+        .map_err(AppError::Parsing)?
         .set_path(&path);
+    let run_id = run_id(c, &project)?;
+    let project = project.set_logs_dir(path_flag(c, "logs-dir", run_id.as_deref())?);
+    // Without a run ID of their own, phases reading experiment logs read the latest run.
+    let run_id = run_id.or_else(|| latest_run(&project.logs_path()));
+    let project = project.set_run_id(run_id);
+
+    let selected = selection.apply(project.clone())?;
+    let (selected, included) = include_dependencies(&project, selected, c.bool_flag("auto-deps"))?;
+    match parse_shard(c)? {
+        Some((index, count)) => Ok((shard(selected, index, count), included)),
+        None => Ok((selected, included)),
+    }
+}
+
+/// Names reserved for the other content of the logs folder.
+const RESERVED_RUN_IDS: &[&str] = &["build", "install", LATEST_RUN_FILE];
+
+/// Run whose experiment logs are written or read: `--run-id`, otherwise a new one named after
+/// the current time for the phases running experiments. In append mode runs keep appending to
+/// the latest run instead, which `load_project` falls back to. A run ID must be a single safe
+/// folder name, distinct from the other folders of the logs folder.
+fn run_id(c: &Context, project: &Project) -> Result<Option<String>, AppError> {
+    let run_id = match c.string_flag("run-id") {
+        Some(run_id) => run_id,
+        None if c.string_flag("log-mode").as_deref() == Some("append") => return Ok(None),
+        None if c.bool_flag("run") || c.string_flag("stress").is_some() || c.bool_flag("explain") => {
+            return Ok(Some(Local::now().format("%Y%m%d-%H%M%S%.3f").to_string()));
+        }
+        None => return Ok(None),
+    };
 
-    create_tree(&project)?;
+    let safe = !run_id.starts_with('.')
+        && run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && !RESERVED_RUN_IDS.contains(&run_id.as_str())
+        && !project.experiments.iter().any(|exp| exp.log_folder() == run_id);
+    if run_id.is_empty() || !safe {
+        return Err(AppError::InvalidArgument("run-id", run_id));
+    }
+    Ok(Some(run_id))
+}
+
+/// Value of the path flag `name` with its placeholders replaced: `{config}` by the name of the
+/// config file without extension, `{date}` by the current date and `{run_id}` by `run_id`.
+fn path_flag(c: &Context, name: &'static str, run_id: Option<&str>) -> Result<Option<String>, AppError> {
+    let path = match c.string_flag(name) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let config = positional_args(c).first()
+        .and_then(|config| Path::new(config.as_str()).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned());
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    let values = [("config", config.as_deref()), ("date", Some(date.as_str())), ("run_id", run_id)];
+
+    match expand_placeholders(&path, &values) {
+        Ok(expanded) => Ok(Some(expanded)),
+        Err(placeholder) if values.iter().any(|(known, _)| *known == placeholder) => Err(AppError::ExternalError(format!(
+            "--{} uses {{{}}}, which has no value here: give it with --{}.", name, placeholder, placeholder.replace('_', "-")
+        ))),
+        Err(_) => Err(AppError::InvalidArgument(name, path)),
+    }
+}
+
+/// Replaces each `{name}` of `template` by its value in `values`, or returns the first name
+/// that is unknown or has no value.
+fn expand_placeholders(template: &str, values: &[(&str, Option<&str>)]) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').map(|end| start + end).ok_or_else(|| rest[start + 1..].to_owned())?;
+        let name = &rest[start + 1..end];
+        let value = values.iter()
+            .find(|(known, _)| *known == name)
+            .and_then(|(_, value)| *value)
+            .ok_or_else(|| name.to_owned())?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(value);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// `--shard i/n`, with `1 <= i <= n`.
+fn parse_shard(c: &Context) -> Result<Option<(usize, usize)>, AppError> {
+    let value = match c.string_flag("shard") {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    match value.split_once('/').map(|(index, count)| (index.trim().parse::<usize>(), count.trim().parse::<usize>())) {
+        Some((Ok(index), Ok(count))) if index >= 1 && index <= count => Ok(Some((index, count))),
+        _ => Err(AppError::InvalidArgument("shard", value)),
+    }
+}
+
+fn selection(c: &Context) -> Selection {
+    Selection {
+        only: repeated_flag(c, "only"),
+        skip: repeated_flag(c, "skip"),
+        tags: repeated_flag(c, "tag"),
+        exclude_tags: repeated_flag(c, "exclude-tag"),
+    }
+}
+
+/// Flags reading the configuration or the logs, which exit before any phase runs.
+const INSPECTION_FLAGS: &[&str] = &["print-config", "query", "verify", "explain", "list", "list-outputs", "show-log"];
+
+/// Flags requesting a phase. Whatever the order they are given in, the phases run in this order:
+/// requirements, install, strict-requirements, preflight, git, build, clean, prune, run followed
+/// by the `aggregate` commands, and stress.
+/// Hence `--clean --run` wipes the logs, then runs into fresh ones.
+const PHASE_FLAGS: &[&str] = &[
+    "requirements", "install", "install-all", "strict-requirements", "git", "build", "clean", "prune", "run", "stress", "watch",
+];
+
+/// Flags contradicting each other whenever they are given together, with why.
+const CONFLICTING_FLAGS: &[(&str, &str, &str)] = &[
+    ("auto-deps", "strict-deps", "the first selects the missing dependencies the second rejects"),
+    ("fail-fast", "max-failures", "--fail-fast already stops at the first failure"),
+    ("watch", "out-each", "--watch only reruns into the project folder"),
+    ("dry-run", "force", "--force removes the runs --dry-run only lists"),
+];
+
+/// Combinations of flags that cannot be honoured together: several inspections, an inspection
+/// with a phase it would silently skip, the `CONFLICTING_FLAGS` and the flags of `--prune` given
+/// without it.
+fn flag_conflicts(c: &Context) -> Vec<(String, Option<String>, String)> {
+    let given = |flag: &str| given(c, flag);
+    let inspections = INSPECTION_FLAGS.iter().copied().filter(|flag| given(flag)).collect::<Vec<_>>();
+    let mut conflicts = Vec::new();
+    for (index, inspection) in inspections.iter().enumerate() {
+        for other in &inspections[index + 1..] {
+            conflicts.push((inspection.to_string(), Some(other.to_string()), "each prints its own answer and exits".to_owned()));
+        }
+        for phase in PHASE_FLAGS.iter().filter(|phase| given(phase)) {
+            conflicts.push((inspection.to_string(), Some(phase.to_string()), format!("--{} exits before any phase runs", inspection)));
+        }
+    }
+    for (flag, other, reason) in CONFLICTING_FLAGS {
+        if given(flag) && given(other) {
+            conflicts.push((flag.to_string(), Some(other.to_string()), reason.to_string()));
+        }
+    }
+    for flag in ["dry-run", "force"].iter().filter(|flag| given(flag) && !given("prune")) {
+        conflicts.push((flag.to_string(), None, "it only applies to --prune".to_owned()));
+    }
+    if c.bool_flag("clean") && c.string_flag("log-mode").as_deref() == Some("append") {
+        conflicts.push(("clean".to_owned(), Some("log-mode".to_owned()), "--clean deletes the runs append mode keeps".to_owned()));
+    }
+    conflicts
+}
+
+/// Whether `flag` was given, whatever its type.
+fn given(c: &Context, flag: &str) -> bool {
+    c.bool_flag(flag) || c.string_flag(flag).is_some()
+}
+
+/// Runs the requested phases for `project`, in the order of `PHASE_FLAGS`, and returns the
+/// results of the experiments run.
+fn run_pipeline(c: &Context, project: &Project) -> Result<Vec<ExperimentResult>, AppError> {
+    // Resolved first, so invalid paths are reported before the experiments run.
+    let run_id = project.run_id.as_deref();
+    let (junit, report) = (path_flag(c, "junit", run_id)?, path_flag(c, "report", run_id)?);
+    let timeline = path_flag(c, "concurrency-report", run_id)?;
+    let tree = needs_tree(c);
+    if tree {
+        create_tree(project)?;
+    }
+    let limit = process_limit(c)?;
+
+    if let Some(min_free) = c.string_flag("min-free-disk") {
+        let min_free = parse_size(&min_free)
+            .ok_or(AppError::InvalidArgument("min-free-disk", min_free))?;
+        check_free_disk(project, min_free)?;
+    }
 
     if c.bool_flag("requirements") {
-        println!("Requirements: ");
-        for requirement in &project.requirements {
-            println!("  {}, version: {}", requirement.name, requirement.version)
+        match c.string_flag("report-format").as_deref() {
+            None | Some("text") => print_requirements(project),
+            Some("json") => println!("{}", requirements_json(project)),
+            Some(format) => return Err(AppError::InvalidArgument("report-format", format.to_owned())),
         }
     }
 
+    if c.bool_flag("install") || c.bool_flag("install-all") {
+        install(project, c.bool_flag("install-all"))?;
+    }
+
+    if c.bool_flag("strict-requirements") {
+        check_requirements(project, false)?;
+    }
+
+    if (c.bool_flag("build") || c.bool_flag("run")) && !project.preflight.is_empty() {
+        info!("Running preflight checks");
+        let project_path = Path::new(&project.path);
+        run_hook("preflight", &project.preflight, &[], project_path, &project.run_logs_path().join("preflight.log"))?;
+    }
+
     if c.bool_flag("git") {
-        git(&project)?;
+        let max_clone_size = match c.string_flag("max-clone-size") {
+            Some(size) => Some(parse_size(&size).ok_or(AppError::InvalidArgument("max-clone-size", size))?),
+            None => None,
+        };
+        git(project, max_clone_size, limit.as_deref())?;
     }
     if c.bool_flag("build") {
-        build(&project)?;
+        if c.bool_flag("require-for-build") {
+            check_requirements(project, true)?;
+        }
+        build(project, jobs(c)?, limit.clone())?;
     }
     if c.bool_flag("clean") {
-        clean(&project)?;
-        create_tree(&project)?;
+        clean(project)?;
+        if tree {
+            create_tree(project)?;
+        }
+    }
+    if c.bool_flag("prune") {
+        let older_than = match c.string_flag("older-than") {
+            Some(age) => Some(parse_duration(&age).ok_or(AppError::InvalidArgument("older-than", age))?),
+            None => None,
+        };
+        let keep_last = parse_flag(c, "keep-last")?;
+        if keep_last.is_none() && older_than.is_none() {
+            return Err(AppError::ExternalError("--prune needs --keep-last or --older-than to tell the runs to keep.".to_owned()));
+        }
+        prune(project, keep_last, older_than, &repeated_flag(c, "keep-run"), c.bool_flag("force"), c.bool_flag("dry-run"))?;
     }
 
+    let mut results = Vec::new();
     if c.bool_flag("run") {
-        execute(&project)?;
+        let options = ExecutionOptions { skipped: skip_missing_optional(project), ..execution_options(c, limit.clone())? };
+        results = execute(project, &options)?;
+        if let Some(junit) = junit {
+            write_junit(&junit, &project.name, &results)?;
+        }
+        // The aggregate commands read a report, written into the run logs without `--report`.
+        let report = match report {
+            None if !project.aggregate.is_empty() => Some(project.run_logs_path().join("report.json").to_string_lossy().into_owned()),
+            report => report,
+        };
+        if let Some(report) = &report {
+            write_report(report, project, &results, parse_shard(c)?)?;
+        }
+        if let Some(timeline) = timeline {
+            write_concurrency_report(&timeline, &results)?;
+        }
+        if let (Some(report), false) = (&report, project.aggregate.is_empty()) {
+            aggregate(project, &results, Path::new(report), c.bool_flag("aggregate-always"))?;
+        }
+    }
+
+    if let Some(name) = c.string_flag("stress") {
+        let exp = find_experiment(project, &name)?;
+        stress(project, exp, parse_flag(c, "stress-max")?, &execution_options(c, limit)?)?;
+    }
+
+    Ok(results)
+}
+
+/// Phases writing into the project folder, `src/` or the logs.
+const TREE_PHASES: &[&str] = &["install", "install-all", "git", "build", "clean", "run"];
+
+/// Whether the project folders must be created: only when a phase using them is requested and
+/// `--no-tree` is not given, so inspecting a project such as with `--requirements` leaves no trace.
+fn needs_tree(c: &Context) -> bool {
+    !c.bool_flag("no-tree")
+        && (TREE_PHASES.iter().any(|phase| c.bool_flag(phase)) || c.string_flag("stress").is_some())
+}
+
+fn list_experiments(p: &Project, selection: &Selection) {
+    for exp in &p.experiments {
+        if exp.tags.is_empty() {
+            println!("{}", exp.name);
+        } else {
+            println!("{} [{}]", exp.name, exp.tags.join(", "));
+        }
+    }
+    for tag in &selection.tags {
+        let selected = p.experiments.iter()
+            .filter(|exp| exp.tags.contains(tag))
+            .map(|exp| exp.name.as_str())
+            .collect::<Vec<_>>();
+        println!("tag {}: {}", tag, selected.join(", "));
+    }
+}
+
+/// Flags that may be repeated, as in `--tag fast --tag gpu`. Their values are also split at
+/// commas by `repeated_flag`, but for `--env`, whose values may hold commas and are only read
+/// with `flag_occurrences`. `positional_args` relies on this list to skip the values of their
+/// later occurrences, which is why `--env` belongs to it.
+const REPEATABLE_FLAGS: &[&str] = &["only", "skip", "tag", "exclude-tag", "out-each", "fail-on-warning", "keep-run", "env"];
+
+/// Values of every occurrence of the flag `name`. Seahorse only consumes the first occurrence of
+/// a flag, later occurrences stay in `c.args`.
+fn flag_occurrences(c: &Context, name: &str) -> Vec<String> {
+    let flag = format!("--{}", name);
+    let mut values = c.string_flag(name).into_iter().collect::<Vec<_>>();
+    let mut args = c.args.iter();
+    while let Some(arg) = args.next() {
+        if *arg == flag {
+            values.extend(args.next().cloned());
+        }
+    }
+    values
+}
+
+/// Values of the repeatable flag `name`, comma separated values split apart.
+fn repeated_flag(c: &Context, name: &str) -> Vec<String> {
+    flag_occurrences(c, name).iter()
+        .flat_map(|value| value.split(','))
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn positional_args(c: &Context) -> Vec<&String> {
+    let mut positional = Vec::new();
+    let mut args = c.args.iter();
+    while let Some(arg) = args.next() {
+        if REPEATABLE_FLAGS.iter().any(|flag| arg.strip_prefix("--") == Some(flag)) {
+            args.next();
+        } else {
+            positional.push(arg);
+        }
+    }
+    positional
+}
+
+/// Checks that the project and logs folders have `min_free` bytes available, plus the estimated
+/// sizes of the selected experiments.
+fn check_free_disk(p: &Project, min_free: u64) -> Result<(), AppError> {
+    let estimated: u64 = p.experiments.iter().filter_map(|exp| exp.estimated_size).sum();
+    let required = min_free + estimated;
+
+    for path in &[Path::new(&p.path).to_path_buf(), p.logs_path()] {
+        match free_space(path) {
+            Some(available) if available < required => {
+                return Err(AppError::InsufficientDiskSpace(path.to_str().unwrap().to_owned(), available, required));
+            }
+            Some(_) => {}
+            None => warning!(UnknownDiskSpace, "cannot determine the free disk space of {}", path.display()),
+        }
     }
 
     Ok(())
 }
 
+fn find_experiment<'a>(p: &'a Project, name: &str) -> Result<&'a Experiment, AppError> {
+    p.experiments.iter()
+        .find(|exp| exp.name == name)
+        .ok_or(AppError::UnknownExperiment(name.to_owned()))
+}
+
+/// YAML files in the folder of `config`, suggested when it does not exist.
+fn nearby_configs(config: &str) -> Vec<String> {
+    let folder = match Path::new(config).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut configs = Vec::new();
+    for pattern in &["*.yml", "*.yaml"] {
+        configs.extend(glob(folder, pattern).unwrap_or_default());
+    }
+    configs.sort();
+    configs.iter()
+        .map(|config| config.strip_prefix(".").unwrap_or(config).to_string_lossy().into_owned())
+        .collect()
+}
+
+/// The project lives next to its config file, in a folder named after the config without its
+/// extension. Only a real file extension is stripped, dots in folder names or a leading dot are
+/// kept. A config without extension gets a `.d` suffix instead, its folder cannot share its name.
+fn derive_project_path(config: &str) -> String {
+    let config = Path::new(config);
+    match config.extension() {
+        Some(_) => config.with_extension(""),
+        None => config.with_extension("d"),
+    }
+    .to_string_lossy()
+    .into_owned()
+}
+
+/// Processes run at once by a phase: `--jobs`, or `--max-parallel` when only it is given.
+fn jobs(c: &Context) -> Result<usize, AppError> {
+    match parse_flag(c, "jobs")? {
+        Some(jobs) => Ok(jobs),
+        None => Ok(parse_flag(c, "max-parallel")?.unwrap_or(1)),
+    }
+}
+
+/// Permits of `--max-parallel`, shared by the git, build and run phases so that no more than
+/// this many external processes run at once, whatever `--jobs` allows.
+fn process_limit(c: &Context) -> Result<Option<Arc<ProcessLimit>>, AppError> {
+    match parse_flag(c, "max-parallel")? {
+        Some(0) => Err(AppError::InvalidArgument("max-parallel", "0".to_owned())),
+        max => Ok(max.map(ProcessLimit::new)),
+    }
+}
+
+fn execution_options(c: &Context, limit: Option<Arc<ProcessLimit>>) -> Result<ExecutionOptions, AppError> {
+    let log_mode = match c.string_flag("log-mode").as_deref() {
+        None | Some("overwrite") => LogMode::Overwrite,
+        Some("append") => LogMode::Append {
+            keep_runs: parse_flag(c, "keep-runs")?,
+            keep_size: match c.string_flag("keep-size") {
+                Some(size) => Some(parse_size(&size).ok_or(AppError::InvalidArgument("keep-size", size))?),
+                None => None,
+            },
+        },
+        Some(other) => return Err(AppError::InvalidArgument("log-mode", other.to_owned())),
+    };
+
+    Ok(ExecutionOptions {
+        log_mode,
+        jobs: jobs(c)?,
+        max_failures: match (c.bool_flag("fail-fast"), parse_flag::<usize>(c, "max-failures")?) {
+            (_, Some(0)) => return Err(AppError::InvalidArgument("max-failures", "0".to_owned())),
+            (true, _) => Some(1),
+            (false, max) => max,
+        },
+        update_golden: c.bool_flag("update-golden"),
+        profile: c.bool_flag("profile"),
+        normalize_logs: c.bool_flag("normalize-logs"),
+        deadline: match c.string_flag("deadline") {
+            Some(deadline) => {
+                let duration = parse_duration(&deadline).ok_or(AppError::InvalidArgument("deadline", deadline))?;
+                Some(*STARTED.get_or_init(Instant::now) + duration)
+            }
+            None => None,
+        },
+        soft_timeout: match c.string_flag("max-runtime-per-experiment") {
+            Some(limit) => Some(parse_duration(&limit).ok_or(AppError::InvalidArgument("max-runtime-per-experiment", limit))?),
+            None => None,
+        },
+        limit,
+        replay: match c.string_flag("replay") {
+            Some(report) => Some(recorded_order(&report)?),
+            None => None,
+        },
+        env: flag_occurrences(c, "env").into_iter()
+            .map(|assignment| parse_assignment(&assignment).ok_or(AppError::InvalidArgument("env", assignment)))
+            .collect::<Result<_, _>>()?,
+        skipped: BTreeMap::new(),
+    })
+}
+
+/// `KEY=VALUE` as given to `--env`, the value being taken as is.
+fn parse_assignment(assignment: &str) -> Option<(String, String)> {
+    let (name, value) = assignment.split_once('=')?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((name.to_owned(), value.to_owned()))
+}
+
+fn parse_flag<T: std::str::FromStr>(c: &Context, name: &'static str) -> Result<Option<T>, AppError> {
+    match c.string_flag(name) {
+        Some(value) => value.parse()
+            .map(Some)
+            .map_err(|_| AppError::InvalidArgument(name, value)),
+        None => Ok(None),
+    }
+}
+
 fn create_tree(p: &Project) -> Result<(), AppError> {
     let into_err = |p: &Path| {
         let p = p.to_str().unwrap().to_owned();
-        |e: std::io::Error| AppError::IOError(p, Rc::new(e))
+        |e: std::io::Error| AppError::IOError(p, Arc::new(e))
     };
 
     let path = Path::new(&p.path);
@@ -127,22 +1117,22 @@ fn create_tree(p: &Project) -> Result<(), AppError> {
             .map_err(into_err(path))?;
     }
 
-    let src = path.join("src");
+    let src = p.src_path();
     let src = src.as_path();
     if !src.exists() {
         create_dir(src)
             .map_err(into_err(src))?;
     }
 
-    let results = path.join("logs");
+    let results = p.run_logs_path();
     let results = results.as_path();
     if !results.exists() {
-        create_dir(results)
+        create_dir_all(results)
             .map_err(into_err(results))?;
     }
 
     for exp in &p.experiments {
-        let exp_folder = results.join(&exp.name);
+        let exp_folder = results.join(exp.log_folder());
         let exp_folder = exp_folder.as_path();
 
         if !exp_folder.exists() {
@@ -154,3 +1144,70 @@ fn create_tree(p: &Project) -> Result<(), AppError> {
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::output::WarningKind;
+    use super::{AppError, derive_project_path, expand_placeholders, fatal_warnings, parse_assignment};
+
+    #[test]
+    fn fails_only_on_the_raised_fatal_warnings() {
+        let raised = BTreeMap::from([(WarningKind::NoOutput, 2), (WarningKind::SlowExperiment, 1)]);
+        assert!(fatal_warnings(&raised, &[]).is_ok());
+        assert!(fatal_warnings(&raised, &[WarningKind::MissingOutput]).is_ok());
+        assert!(matches!(
+            fatal_warnings(&raised, &[WarningKind::NoOutput, WarningKind::MissingOutput]),
+            Err(AppError::FatalWarnings(fatal)) if fatal == vec![("no-output".to_owned(), 2)]
+        ));
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        let values = [("config", Some("bench")), ("run_id", None)];
+        assert_eq!(expand_placeholders("reports/{config}.json", &values), Ok("reports/bench.json".to_owned()));
+        assert_eq!(expand_placeholders("plain.json", &values), Ok("plain.json".to_owned()));
+        assert_eq!(expand_placeholders("{run_id}.json", &values), Err("run_id".to_owned()));
+        assert_eq!(expand_placeholders("{host}.json", &values), Err("host".to_owned()));
+    }
+
+    #[test]
+    fn strips_the_extension() {
+        assert_eq!(derive_project_path("config.yml"), "config");
+        assert_eq!(derive_project_path("dir/config.yaml"), "dir/config");
+    }
+
+    #[test]
+    fn keeps_dots_in_directories() {
+        assert_eq!(derive_project_path("./my.config/replikate"), "./my.config/replikate.d");
+        assert_eq!(derive_project_path("my.config/replikate.yml"), "my.config/replikate");
+    }
+
+    #[test]
+    fn strips_only_the_last_extension() {
+        assert_eq!(derive_project_path("experiments.v2.yml"), "experiments.v2");
+        assert_eq!(derive_project_path("config.yaml.gz"), "config.yaml");
+    }
+
+    #[test]
+    fn gives_configs_without_extension_a_folder_of_their_own() {
+        assert_eq!(derive_project_path("config"), "config.d");
+        assert_eq!(derive_project_path("dir.d/config"), "dir.d/config.d");
+        assert_eq!(derive_project_path("dir.d/config.yaml"), "dir.d/config");
+    }
+
+    #[test]
+    fn keeps_dotfiles() {
+        assert_eq!(derive_project_path(".replikate"), ".replikate.d");
+        assert_eq!(derive_project_path("dir/.replikate.yml"), "dir/.replikate");
+    }
+
+    #[test]
+    fn keeps_env_values_as_given() {
+        assert_eq!(parse_assignment("CUDA_VISIBLE_DEVICES=0,1"), Some(("CUDA_VISIBLE_DEVICES".to_owned(), "0,1".to_owned())));
+        assert_eq!(parse_assignment("QUERY=a=b"), Some(("QUERY".to_owned(), "a=b".to_owned())));
+        assert_eq!(parse_assignment("EMPTY="), Some(("EMPTY".to_owned(), String::new())));
+        assert_eq!(parse_assignment("=value"), None);
+        assert_eq!(parse_assignment("NOT VALID=1"), None);
+        assert_eq!(parse_assignment("DEBUG"), None);
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::BTreeMap,
+    fs::read_to_string,
+    path::Path,
+};
+use crate::{
+    json::Json,
+    tsv::TSVSerializable,
+};
+
+/// Values an experiment reports in its `result_file`, by name: numbers, strings or booleans.
+pub type Metrics = BTreeMap<String, Json>;
+
+/// Reads a result file: a JSON object of scalars when its name ends with `.json`, otherwise a TSV
+/// table as written by `TSVTable`, whose header names the values of its last row. Quoted TSV
+/// cells are strings, the others numbers when they parse as such.
+pub fn read_metrics(path: &Path) -> Result<Metrics, String> {
+    let content = read_to_string(path).map_err(|e| e.to_string())?;
+    if path.extension().map(|extension| extension == "json").unwrap_or(false) {
+        parse_json(&content)
+    } else {
+        parse_tsv(&content)
+    }
+}
+
+/// `value` as shown to the user, strings without their quotes.
+pub fn metric_text(value: &Json) -> String {
+    match value {
+        Json::String(text) => text.to_owned(),
+        value => value.to_string(),
+    }
+}
+
+/// `value` as a TSV cell, quoted when it is a string.
+pub fn metric_cell(value: &Json) -> Box<dyn TSVSerializable> {
+    match value {
+        Json::Number(number) => Box::new(*number),
+        Json::Bool(boolean) => Box::new(*boolean),
+        value => Box::new(metric_text(value)),
+    }
+}
+
+fn parse_json(content: &str) -> Result<Metrics, String> {
+    let fields = match Json::parse(content)? {
+        Json::Object(fields) => fields,
+        _ => return Err("expected a JSON object".to_owned()),
+    };
+    fields.into_iter()
+        .map(|(name, value)| match value {
+            Json::Number(_) | Json::String(_) | Json::Bool(_) => Ok((name, value)),
+            _ => Err(format!("the value of {} is not a number, a string or a boolean", name)),
+        })
+        .collect()
+}
+
+fn parse_tsv(content: &str) -> Result<Metrics, String> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next().ok_or_else(|| "the file is empty".to_owned())?.split('\t').map(unquoted).collect::<Vec<_>>();
+    let row = lines.next_back().ok_or_else(|| "the table has no row".to_owned())?.split('\t').collect::<Vec<_>>();
+    if row.len() != header.len() {
+        return Err(format!("expected {} values but the last row has {}", header.len(), row.len()));
+    }
+    Ok(header.into_iter()
+        .zip(row)
+        .map(|(name, value)| {
+            let value = match value.parse::<f64>() {
+                Ok(number) if number.is_finite() => Json::Number(number),
+                _ => Json::String(unquoted(value)),
+            };
+            (name, value)
+        })
+        .collect())
+}
+
+fn unquoted(cell: &str) -> String {
+    match cell.strip_prefix('"').and_then(|cell| cell.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => cell.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_json, parse_tsv};
+    use crate::json::Json;
+
+    #[test]
+    fn reads_json_scalars_and_rejects_nested_values() {
+        let metrics = parse_json(r#"{"accuracy": 0.93, "model": "small", "converged": true}"#).unwrap();
+        assert_eq!(metrics.get("accuracy"), Some(&Json::Number(0.93)));
+        assert_eq!(metrics.get("model"), Some(&Json::string("small")));
+        assert_eq!(metrics.get("converged"), Some(&Json::Bool(true)));
+        assert!(parse_json(r#"{"losses": [1, 2]}"#).is_err());
+        assert!(parse_json("[1]").is_err());
+    }
+
+    #[test]
+    fn reads_the_last_row_of_a_tsv_table() {
+        let metrics = parse_tsv("epoch\t\"loss\"\tphase\n1\t0.5\twarmup\n2\t0.25\t\"say \"\"done\"\"\"\n").unwrap();
+        assert_eq!(metrics.get("epoch"), Some(&Json::Number(2.0)));
+        assert_eq!(metrics.get("loss"), Some(&Json::Number(0.25)));
+        assert_eq!(metrics.get("phase"), Some(&Json::string("say \"done\"")));
+        assert!(parse_tsv("a\tb\n1\n").is_err());
+        assert!(parse_tsv("a\tb\n").is_err());
+    }
+}
@@ -0,0 +1,1880 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::read_to_string,
+    path::{Component, Path, PathBuf},
+};
+use yaml_rust::{Yaml, YamlLoader, yaml::Hash};
+use crate::{
+    glob::glob,
+    lockfile::{LockfileFormat, load_requirements},
+    vars::{resolve_included, resolve_variables},
+    units::parse_size,
+};
+
+#[derive(Clone, Debug)]
+pub enum ParsingError {
+    MissingField(&'static str),
+    InvalidField(&'static str),
+    UnreadableFile(String),
+    InvalidLockfile(String),
+    EmptyCommand(String),
+    MissingProgram(String, String),
+    UnsupportedVersion(i64),
+    RemovedField(&'static str, &'static str),
+    DuplicateLogDir(String),
+    /// Two repositories would be cloned into the same folder of `src_folder`.
+    DuplicateRepositoryDest(String),
+    UndefinedVariable(String),
+    /// Variables referencing each other, the first one being repeated at the end.
+    CyclicVariables(Vec<String>),
+    /// Text holding a `${` without its closing brace.
+    UnterminatedVariable(String),
+    /// `${tool:name}` referencing a name missing from `tools`.
+    UnknownTool(String),
+    /// Experiment and the dependency it names.
+    UnknownDependency(String, String),
+    /// Experiments depending on each other, the first one being repeated at the end.
+    CyclicDependencies(Vec<String>),
+    /// Repository and the `build_depends_on` entry naming no repository.
+    UnknownBuildDependency(String, String),
+    /// Repositories whose builds depend on each other, the first one being repeated at the end.
+    CyclicBuildDependencies(Vec<String>),
+    /// Experiment and the `{placeholder}` of its command missing from its `params`.
+    UndefinedParam(String, String),
+    /// Experiment and the param its command never uses.
+    UnusedParam(String, String),
+    /// Experiment comparing its stdout to a golden file while not capturing it.
+    UncapturedGolden(String),
+    /// Experiment name declared twice, possibly across `experiments_from` files.
+    DuplicateExperiment(String),
+    /// Experiment and the `requires` entry naming no requirement.
+    UnknownRequirement(String, String),
+    /// Experiment, resource, amount requested and capacity of the project `resources`.
+    UnsatisfiableResource(String, String, u64, u64),
+    /// Experiment and the `group` it names missing from the project `groups`.
+    UnknownGroup(String, String),
+    /// Experiment and the `for_each` pattern matching no file.
+    EmptyForEach(String, String),
+    /// Experiment and the stem shared by several files matching its `for_each`.
+    ForEachCollision(String, String),
+}
+
+/// Version of the configuration schema understood by this build.
+pub const CONFIG_VERSION: i64 = 1;
+
+/// Project subfolders receiving the repositories and the logs unless renamed by `src_folder` and
+/// `logs_folder`.
+pub const DEFAULT_SRC_FOLDER: &str = "src";
+pub const DEFAULT_LOGS_FOLDER: &str = "logs";
+
+/// Oldest configuration schema version this build can still read.
+pub const MIN_CONFIG_VERSION: i64 = 1;
+
+/// Top-level fields removed from the schema, with the version they were removed in and a
+/// migration hint.
+const REMOVED_FIELDS: &[(&str, i64, &str)] = &[];
+
+pub trait FromYamlDocument where Self: Sized {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError>;
+}
+
+/// Inverse of `FromYamlDocument`: the produced document parses back to an equivalent value.
+pub trait ToYamlDocument {
+    fn to_yaml(&self) -> Yaml;
+}
+
+#[derive(Clone, Debug)]
+pub struct Project {
+    /// Schema version declared by the config, if any.
+    pub version: Option<i64>,
+    /// Defaults to the name of the project folder.
+    pub name: String,
+    pub path: String,
+    /// Name of the project subfolder receiving the repositories, `src` by default.
+    pub src_folder: String,
+    /// Name of the project subfolder receiving the logs, `logs` by default.
+    pub logs_folder: String,
+    /// Overrides `<path>/<logs_folder>` as the folder receiving all logs.
+    pub logs_dir: Option<String>,
+    /// Run whose experiment logs are read or written, in `<logs>/<run_id>/`. Without run ID,
+    /// experiment logs go directly into the logs folder.
+    pub run_id: Option<String>,
+    /// Sanity check commands run before building or running anything.
+    pub preflight: Vec<String>,
+    /// Commands run once before the first experiment and once after the last one.
+    pub setup: Vec<String>,
+    pub teardown: Vec<String>,
+    /// Commands collating the results once every experiment ran, given `REPLIKATE_LOGS_DIR` and
+    /// `REPLIKATE_REPORT`.
+    pub aggregate: Vec<String>,
+    /// Loaded into the environment of every experiment, relative to the project folder.
+    pub env_file: Option<EnvFile>,
+    /// Base of the `REPLIKATE_SEED` given to every experiment.
+    pub seed: Option<u64>,
+    /// Launcher wrapping every experiment command, which replaces its `{cmd}` placeholder.
+    pub executor: Option<String>,
+    /// Program run for every git command instead of the `git` found on the `PATH`.
+    pub git_binary: Option<String>,
+    /// Arguments given to every git command before its subcommand, such as `-c key=value`.
+    pub git_args: Vec<String>,
+    /// Capacities of the named resources, such as `gpus`, shared by concurrent experiments.
+    pub resources: BTreeMap<String, u64>,
+    /// Hooks shared by the experiments naming the group in their `group`, by group name.
+    pub groups: BTreeMap<String, Group>,
+    pub requirements: Vec<Requirement>,
+    pub repositories: Vec<Repository>,
+    pub experiments: Vec<Experiment>,
+}
+
+impl Project {
+    pub fn set_path(mut self, path: &str) -> Self {
+        self.path = path.to_owned();
+        if self.name.is_empty() {
+            self.name = Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        self
+    }
+
+    pub fn set_logs_dir(mut self, logs_dir: Option<String>) -> Self {
+        self.logs_dir = logs_dir;
+        self
+    }
+
+    pub fn set_run_id(mut self, run_id: Option<String>) -> Self {
+        self.run_id = run_id;
+        self
+    }
+
+    pub fn logs_path(&self) -> PathBuf {
+        match &self.logs_dir {
+            Some(logs_dir) => PathBuf::from(logs_dir),
+            None => Path::new(&self.path).join(&self.logs_folder),
+        }
+    }
+
+    /// Folder holding the log folder of each experiment: that of the run `run_id` within the
+    /// logs folder.
+    pub fn run_logs_path(&self) -> PathBuf {
+        match &self.run_id {
+            Some(run_id) => self.logs_path().join(run_id),
+            None => self.logs_path(),
+        }
+    }
+
+    /// Folder into which the repositories are cloned.
+    pub fn src_path(&self) -> PathBuf {
+        Path::new(&self.path).join(&self.src_folder)
+    }
+
+    pub fn working_dir(&self, exp: &Experiment) -> PathBuf {
+        match &exp.repository {
+            Some(repository) => self.src_path().join(repository),
+            None => PathBuf::from(&self.path),
+        }
+    }
+
+    /// Checks that the program of every experiment is either on the `PATH` or an existing file
+    /// relative to the experiment's working directory.
+    pub fn check_programs(&self) -> Result<(), ParsingError> {
+        for exp in &self.experiments {
+            let exp = self.wrapped(exp);
+            let program = exp.program();
+            if !program_exists(&program, &self.working_dir(&exp)) {
+                return Err(ParsingError::MissingProgram(exp.name.to_owned(), program));
+            }
+        }
+        Ok(())
+    }
+
+    /// `exp` with the command actually run: its own command put in place of `{cmd}` in its
+    /// `executor`, or else in the project one. An empty executor runs the command as is.
+    pub fn wrapped(&self, exp: &Experiment) -> Experiment {
+        let executor = exp.executor.as_ref().or(self.executor.as_ref()).filter(|executor| !executor.is_empty());
+        match executor {
+            Some(executor) => Experiment {
+                command: executor.replace(EXECUTOR_PLACEHOLDER, &exp.command),
+                // Already wrapped.
+                executor: Some(String::new()),
+                ..exp.clone()
+            },
+            None => exp.clone(),
+        }
+    }
+}
+
+/// Depth of each experiment in the dependency graph: 0 without dependencies, otherwise one more
+/// than its deepest dependency. Dependencies missing from `experiments` are ignored.
+pub fn dependency_levels(experiments: &[Experiment]) -> Result<Vec<usize>, ParsingError> {
+    let names = experiments.iter().map(|exp| exp.name.as_str()).collect::<Vec<_>>();
+    let dependencies = experiments.iter().map(|exp| exp.depends_on.as_slice()).collect::<Vec<_>>();
+    graph_levels(&names, &dependencies).map_err(ParsingError::CyclicDependencies)
+}
+
+/// Like `dependency_levels`, for the `build_depends_on` of repositories.
+pub fn build_levels(repositories: &[Repository]) -> Result<Vec<usize>, ParsingError> {
+    let names = repositories.iter().map(|repo| repo.name.as_str()).collect::<Vec<_>>();
+    let dependencies = repositories.iter().map(|repo| repo.build_depends_on.as_slice()).collect::<Vec<_>>();
+    graph_levels(&names, &dependencies).map_err(ParsingError::CyclicBuildDependencies)
+}
+
+/// Depth of each node named `names[i]` depending on `dependencies[i]`, or the names forming a
+/// cycle, the first one being repeated at the end.
+fn graph_levels(names: &[&str], dependencies: &[&[String]]) -> Result<Vec<usize>, Vec<String>> {
+    fn visit(
+        index: usize,
+        names: &[&str],
+        dependencies: &[&[String]],
+        levels: &mut Vec<Option<usize>>,
+        stack: &mut Vec<usize>,
+    ) -> Result<usize, Vec<String>> {
+        if let Some(level) = levels[index] {
+            return Ok(level);
+        }
+        if let Some(start) = stack.iter().position(|other| *other == index) {
+            let mut cycle = stack[start..].iter().map(|other| names[*other].to_owned()).collect::<Vec<_>>();
+            cycle.push(names[index].to_owned());
+            return Err(cycle);
+        }
+
+        stack.push(index);
+        let mut level = 0;
+        for dependency in dependencies[index] {
+            if let Some(dependency) = names.iter().position(|name| name == dependency) {
+                level = level.max(visit(dependency, names, dependencies, levels, stack)? + 1);
+            }
+        }
+        stack.pop();
+
+        levels[index] = Some(level);
+        Ok(level)
+    }
+
+    let mut levels = vec![None; names.len()];
+    for index in 0..names.len() {
+        visit(index, names, dependencies, &mut levels, &mut Vec::new())?;
+    }
+    Ok(levels.into_iter().flatten().collect())
+}
+
+pub fn program_exists(program: &str, working_dir: &Path) -> bool {
+    if program.contains('/') {
+        return working_dir.join(program).is_file();
+    }
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+impl FromYamlDocument for Project {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        Project::from_config(yaml, Path::new("."))
+    }
+}
+
+impl Project {
+    /// Parses the project of a config file read from `config_dir`. The files it names to be read
+    /// while parsing, those of `requirements_from` and `experiments_from`, are relative to that
+    /// folder rather than to the current directory, so the config works from anywhere. So are the
+    /// repositories given by path.
+    pub fn from_config(config: &Yaml, config_dir: &Path) -> Result<Self, ParsingError> {
+        let yaml = &resolve_variables(config)?;
+        let version = match &yaml["version"] {
+            Yaml::BadValue => None,
+            Yaml::Integer(version) => Some(*version),
+            _ => return Err(ParsingError::InvalidField("version")),
+        };
+        check_compatibility(yaml, version.unwrap_or(CONFIG_VERSION))?;
+
+        let mut requirements: Vec<Requirement> = list_of(yaml, "requirements")?;
+        requirements.extend(requirements_from(&yaml["requirements_from"], config_dir)?);
+
+        let resources = resource_map(yaml)?;
+        let groups = group_map(yaml)?;
+        let mut expanded = BTreeMap::new();
+        let mut experiments = for_each_expanded(items(&yaml["experiments"]), config_dir, &mut expanded)?;
+        experiments.extend(experiments_from(&yaml["experiments_from"], config, config_dir, &mut expanded)?);
+        // Depending on an experiment expanded from `for_each` is depending on all its expansions.
+        let names = experiments.iter().map(|exp| exp.name.to_owned()).collect::<Vec<_>>();
+        for exp in &mut experiments {
+            exp.depends_on = exp.depends_on.iter()
+                .flat_map(|dependency| match expanded.get(dependency) {
+                    Some(expansions) if !names.contains(dependency) => expansions.clone(),
+                    _ => vec![dependency.to_owned()],
+                })
+                .collect();
+        }
+        for (index, exp) in experiments.iter().enumerate() {
+            if experiments[..index].iter().any(|other| other.name == exp.name) {
+                return Err(ParsingError::DuplicateExperiment(exp.name.to_owned()));
+            }
+            if experiments[..index].iter().any(|other| other.log_folder() == exp.log_folder()) {
+                return Err(ParsingError::DuplicateLogDir(exp.log_folder().to_owned()));
+            }
+            for dependency in &exp.depends_on {
+                if !experiments.iter().any(|other| other.name == *dependency) {
+                    return Err(ParsingError::UnknownDependency(exp.name.to_owned(), dependency.to_owned()));
+                }
+            }
+            for name in &exp.requires {
+                if !requirements.iter().any(|requirement| requirement.name == *name) {
+                    return Err(ParsingError::UnknownRequirement(exp.name.to_owned(), name.to_owned()));
+                }
+            }
+            if let Some(group) = exp.group.as_ref().filter(|group| !groups.contains_key(*group)) {
+                return Err(ParsingError::UnknownGroup(exp.name.to_owned(), group.to_owned()));
+            }
+            for (name, amount) in &exp.resources {
+                let capacity = resources.get(name).copied().unwrap_or(0);
+                if *amount > capacity {
+                    return Err(ParsingError::UnsatisfiableResource(exp.name.to_owned(), name.to_owned(), *amount, capacity));
+                }
+            }
+        }
+        dependency_levels(&experiments)?;
+
+        let mut repositories = unique_destinations(list_of(yaml, "repositories")?)?;
+        for repository in repositories.iter_mut().filter(|repository| is_path(&repository.url)) {
+            if Path::new(&repository.url).is_relative() && config_dir != Path::new(".") {
+                repository.url = config_dir.join(&repository.url).to_string_lossy().into_owned();
+            }
+        }
+        for repository in &repositories {
+            for dependency in &repository.build_depends_on {
+                if !repositories.iter().any(|other| other.name == *dependency) {
+                    return Err(ParsingError::UnknownBuildDependency(repository.name.to_owned(), dependency.to_owned()));
+                }
+            }
+        }
+        build_levels(&repositories)?;
+
+        let src_folder = folder_name(yaml, "src_folder", DEFAULT_SRC_FOLDER)?;
+        let logs_folder = folder_name(yaml, "logs_folder", DEFAULT_LOGS_FOLDER)?;
+        if src_folder == logs_folder {
+            return Err(ParsingError::InvalidField("logs_folder"));
+        }
+
+        Ok(Project {
+            version,
+            name: optional_string(yaml, "name")?.unwrap_or_default(),
+            path: String::new(),
+            src_folder,
+            logs_folder,
+            logs_dir: None,
+            run_id: None,
+            preflight: string_list(yaml, "preflight")?,
+            setup: string_list(yaml, "setup")?,
+            teardown: string_list(yaml, "teardown")?,
+            aggregate: string_list(yaml, "aggregate")?,
+            env_file: optional_env_file(yaml)?,
+            seed: match &yaml["seed"] {
+                Yaml::BadValue => None,
+                Yaml::Integer(seed) if *seed >= 0 => Some(*seed as u64),
+                _ => return Err(ParsingError::InvalidField("seed")),
+            },
+            executor: executor(yaml)?.filter(|executor| !executor.is_empty()),
+            git_binary: optional_string(yaml, "git_binary")?,
+            git_args: string_list(yaml, "git_args")?,
+            resources,
+            groups,
+            requirements,
+            repositories,
+            experiments,
+        })
+    }
+}
+
+impl ToYamlDocument for Project {
+    fn to_yaml(&self) -> Yaml {
+        let mut yaml = Hash::new();
+        yaml.insert(key("version"), Yaml::Integer(self.version.unwrap_or(CONFIG_VERSION)));
+        yaml.insert(key("name"), Yaml::String(self.name.to_owned()));
+        if self.src_folder != DEFAULT_SRC_FOLDER {
+            yaml.insert(key("src_folder"), Yaml::String(self.src_folder.to_owned()));
+        }
+        if self.logs_folder != DEFAULT_LOGS_FOLDER {
+            yaml.insert(key("logs_folder"), Yaml::String(self.logs_folder.to_owned()));
+        }
+        if !self.preflight.is_empty() {
+            yaml.insert(key("preflight"), strings_to_yaml(&self.preflight));
+        }
+        if !self.setup.is_empty() {
+            yaml.insert(key("setup"), strings_to_yaml(&self.setup));
+        }
+        if !self.teardown.is_empty() {
+            yaml.insert(key("teardown"), strings_to_yaml(&self.teardown));
+        }
+        if !self.aggregate.is_empty() {
+            yaml.insert(key("aggregate"), strings_to_yaml(&self.aggregate));
+        }
+        if let Some(env_file) = &self.env_file {
+            yaml.insert(key("env_file"), env_file.to_yaml());
+        }
+        if let Some(seed) = self.seed {
+            yaml.insert(key("seed"), Yaml::Integer(seed as i64));
+        }
+        insert_optional(&mut yaml, "executor", &self.executor);
+        insert_optional(&mut yaml, "git_binary", &self.git_binary);
+        if !self.git_args.is_empty() {
+            yaml.insert(key("git_args"), strings_to_yaml(&self.git_args));
+        }
+        if !self.resources.is_empty() {
+            yaml.insert(key("resources"), resources_to_yaml(&self.resources));
+        }
+        if !self.groups.is_empty() {
+            yaml.insert(key("groups"), Yaml::Hash(self.groups.iter().map(|(name, group)| (key(name), group.to_yaml())).collect()));
+        }
+        yaml.insert(key("requirements"), list_to_yaml(&self.requirements));
+        yaml.insert(key("repositories"), list_to_yaml(&self.repositories));
+        yaml.insert(key("experiments"), list_to_yaml(&self.experiments));
+        Yaml::Hash(yaml)
+    }
+}
+
+/// Commands of a group of experiments: its `setup` runs before the first of them starts and its
+/// `teardown` once all of them finished.
+#[derive(Clone, Debug, Default)]
+pub struct Group {
+    pub setup: Vec<String>,
+    pub teardown: Vec<String>,
+}
+
+impl FromYamlDocument for Group {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        Ok(Group {
+            setup: string_list(yaml, "setup")?,
+            teardown: string_list(yaml, "teardown")?,
+        })
+    }
+}
+
+impl ToYamlDocument for Group {
+    fn to_yaml(&self) -> Yaml {
+        let mut yaml = Hash::new();
+        if !self.setup.is_empty() {
+            yaml.insert(key("setup"), strings_to_yaml(&self.setup));
+        }
+        if !self.teardown.is_empty() {
+            yaml.insert(key("teardown"), strings_to_yaml(&self.teardown));
+        }
+        Yaml::Hash(yaml)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Requirement {
+    pub name: String,
+    pub version: String,
+    /// Shell command installing the requirement, used by `--install`.
+    pub install: Option<String>,
+    /// Program to check instead of looking `name` up on the `PATH`.
+    pub path: Option<String>,
+    /// Programs any of which satisfies the requirement, in order of preference. When given,
+    /// `name` and `version` are optional and only describe the requirement.
+    pub any_of: Vec<Requirement>,
+    /// An optional requirement only enables the experiments that `requires` it, true by default.
+    pub required: bool,
+}
+
+impl FromYamlDocument for Requirement {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        let any_of: Vec<Requirement> = list_of(yaml, "any_of")?;
+        if any_of.iter().any(|alternative| !alternative.any_of.is_empty()) {
+            return Err(ParsingError::InvalidField("any_of"));
+        }
+        if any_of.is_empty() {
+            return Ok(Requirement {
+                name: required_string(yaml, "name")?,
+                version: required_string(yaml, "version")?,
+                install: optional_string(yaml, "install")?,
+                path: optional_string(yaml, "path")?,
+                any_of,
+                required: bool_field(yaml, "required", true)?,
+            });
+        }
+
+        Ok(Requirement {
+            name: optional_string(yaml, "name")?.unwrap_or_else(|| {
+                any_of.iter().map(|alternative| alternative.name.as_str()).collect::<Vec<_>>().join(" or ")
+            }),
+            version: optional_string(yaml, "version")?.unwrap_or_default(),
+            install: optional_string(yaml, "install")?,
+            path: None,
+            any_of,
+            required: bool_field(yaml, "required", true)?,
+        })
+    }
+}
+
+impl ToYamlDocument for Requirement {
+    fn to_yaml(&self) -> Yaml {
+        let mut yaml = Hash::new();
+        yaml.insert(key("name"), Yaml::String(self.name.to_owned()));
+        if self.any_of.is_empty() || !self.version.is_empty() {
+            yaml.insert(key("version"), Yaml::String(self.version.to_owned()));
+        }
+        insert_optional(&mut yaml, "install", &self.install);
+        insert_optional(&mut yaml, "path", &self.path);
+        if !self.any_of.is_empty() {
+            yaml.insert(key("any_of"), list_to_yaml(&self.any_of));
+        }
+        if !self.required {
+            yaml.insert(key("required"), Yaml::Boolean(false));
+        }
+        Yaml::Hash(yaml)
+    }
+}
+
+impl Requirement {
+    /// The program checked for this requirement: its `path`, or else its `name`.
+    pub fn program(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+}
+
+fn check_compatibility(yaml: &Yaml, version: i64) -> Result<(), ParsingError> {
+    if !(MIN_CONFIG_VERSION..=CONFIG_VERSION).contains(&version) {
+        return Err(ParsingError::UnsupportedVersion(version));
+    }
+    for (field, removed_in, hint) in REMOVED_FIELDS {
+        if version >= *removed_in && !yaml[*field].is_badvalue() {
+            return Err(ParsingError::RemovedField(field, hint));
+        }
+    }
+    Ok(())
+}
+
+/// Placeholder of an `executor` replaced by the experiment command.
+const EXECUTOR_PLACEHOLDER: &str = "{cmd}";
+
+/// Argument of the command of a `script` experiment replaced by the path of its script file.
+pub const SCRIPT_PLACEHOLDER: &str = "{script}";
+
+/// Interpreter running a `script` that does not name one.
+const DEFAULT_INTERPRETER: &str = "sh";
+
+/// An `executor` template, which must hold `{cmd}` unless it is empty.
+fn executor(yaml: &Yaml) -> Result<Option<String>, ParsingError> {
+    match optional_string(yaml, "executor")? {
+        Some(executor) if !executor.is_empty() && !executor.contains(EXECUTOR_PLACEHOLDER) => {
+            Err(ParsingError::InvalidField("executor"))
+        }
+        executor => Ok(executor),
+    }
+}
+
+fn bool_field(yaml: &Yaml, field: &'static str, default: bool) -> Result<bool, ParsingError> {
+    match &yaml[field] {
+        Yaml::BadValue => Ok(default),
+        Yaml::Boolean(value) => Ok(*value),
+        _ => Err(ParsingError::InvalidField(field)),
+    }
+}
+
+/// Single folder name, defaulting to `default`: subfolders of the project cannot escape it.
+fn folder_name(yaml: &Yaml, field: &'static str, default: &str) -> Result<String, ParsingError> {
+    match optional_string(yaml, field)? {
+        None => Ok(default.to_owned()),
+        Some(name) => match Path::new(&name).components().collect::<Vec<_>>().as_slice() {
+            [Component::Normal(_)] => Ok(name),
+            _ => Err(ParsingError::InvalidField(field)),
+        },
+    }
+}
+
+/// Accepts either a lockfile path, whose format is guessed from its name, or a map with a `path`
+/// and an explicit `format` (`pinned` or `cargo`), relative to `config_dir`.
+fn requirements_from(yaml: &Yaml, config_dir: &Path) -> Result<Vec<Requirement>, ParsingError> {
+    let load = |path: &str, format| load_requirements(&config_dir.join(path).to_string_lossy(), format);
+    match yaml {
+        Yaml::BadValue => Ok(Vec::new()),
+        Yaml::String(path) => load(path, LockfileFormat::guess(path)),
+        Yaml::Hash(_) => {
+            let path = required_string(yaml, "path")?;
+            let format = match optional_string(yaml, "format")? {
+                Some(name) => LockfileFormat::from_name(&name)
+                    .ok_or(ParsingError::InvalidField("format"))?,
+                None => LockfileFormat::guess(&path),
+            };
+            load(&path, format)
+        }
+        _ => Err(ParsingError::InvalidField("requirements_from")),
+    }
+}
+
+/// Loads the experiments of every file matching the glob patterns of `experiments_from`, a
+/// pattern or a list of them, relative to `config_dir`. Each file holds one experiment or a list
+/// of them. Files are read in path order, their variables resolved from the `vars` and `tools` of
+/// `config`. The experiments expanded from `for_each` are added to `expanded`.
+fn experiments_from(
+    yaml: &Yaml, config: &Yaml, config_dir: &Path, expanded: &mut BTreeMap<String, Vec<String>>,
+) -> Result<Vec<Experiment>, ParsingError> {
+    let patterns = items(yaml).iter()
+        .map(|pattern| as_string(pattern).ok_or(ParsingError::InvalidField("experiments_from")))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut experiments = Vec::new();
+    for pattern in patterns {
+        let files = glob(config_dir, &pattern)
+            .map_err(|e| ParsingError::UnreadableFile(format!("{}: {}", pattern, e)))?;
+        for file in files {
+            let content = read_to_string(&file)
+                .map_err(|e| ParsingError::UnreadableFile(format!("{}: {}", file.display(), e)))?;
+            let documents = YamlLoader::load_from_str(&content)
+                .map_err(|e| ParsingError::UnreadableFile(format!("{}: {}", file.display(), e)))?;
+            if let Some(document) = documents.first() {
+                let document = resolve_included(document, config)?;
+                experiments.extend(for_each_expanded(items(&document), config_dir, expanded)?);
+            }
+        }
+    }
+    Ok(experiments)
+}
+
+#[derive(Clone, Debug)]
+pub struct Repository {
+    /// Folder of the clone relative to `src/`, also used by experiments to reference it. It is
+    /// the `dest` when given, otherwise derived from the url.
+    pub name: String,
+    pub url: String,
+    pub commit: Option<String>,
+    pub build: Option<String>,
+    /// Repositories, by name, built before this one.
+    pub build_depends_on: Vec<String>,
+    pub dest: Option<String>,
+    /// Credentials used to clone the repository over http(s).
+    pub auth: Option<Auth>,
+    /// Used in place through a link from `src/` rather than cloned, its `url` being a path on this
+    /// machine. Defaults to whether the url is a path, as git tells them apart, and no commit is
+    /// declared: a path with a commit is cloned to check it out.
+    pub local: bool,
+}
+
+impl FromYamlDocument for Repository {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        let url = required_string(yaml, "url")?;
+        let dest = optional_string(yaml, "dest")?;
+        if let Some(dest) = &dest {
+            let dest = Path::new(dest);
+            if dest.as_os_str().is_empty() || dest.is_absolute() || dest.components().any(|c| c == Component::ParentDir) {
+                return Err(ParsingError::InvalidField("dest"));
+            }
+        }
+
+        let commit = optional_string(yaml, "commit")?;
+        Ok(Repository {
+            name: dest.clone().unwrap_or_else(|| url_segments(&url, 1)),
+            local: bool_field(yaml, "local", is_path(&url) && commit.is_none())?,
+            commit,
+            build: optional_string(yaml, "build")?,
+            build_depends_on: string_list(yaml, "build_depends_on")?,
+            dest,
+            auth: match &yaml["auth"] {
+                Yaml::BadValue => None,
+                auth => Some(Auth::from_yaml(auth)?),
+            },
+            url,
+        })
+    }
+}
+
+impl ToYamlDocument for Repository {
+    fn to_yaml(&self) -> Yaml {
+        let mut yaml = Hash::new();
+        yaml.insert(key("url"), Yaml::String(self.url.to_owned()));
+        insert_optional(&mut yaml, "commit", &self.commit);
+        insert_optional(&mut yaml, "build", &self.build);
+        if !self.build_depends_on.is_empty() {
+            yaml.insert(key("build_depends_on"), strings_to_yaml(&self.build_depends_on));
+        }
+        insert_optional(&mut yaml, "dest", &self.dest);
+        if let Some(auth) = &self.auth {
+            yaml.insert(key("auth"), auth.to_yaml());
+        }
+        if self.local != (is_path(&self.url) && self.commit.is_none()) {
+            yaml.insert(key("local"), Yaml::Boolean(self.local));
+        }
+        Yaml::Hash(yaml)
+    }
+}
+
+/// Where the token cloning a private repository comes from. The token itself never appears in
+/// the configuration.
+#[derive(Clone, Debug)]
+pub struct Auth {
+    /// User name sent with the token, `x-access-token` by default.
+    pub username: Option<String>,
+    pub source: SecretSource,
+}
+
+#[derive(Clone, Debug)]
+pub enum SecretSource {
+    /// Environment variable holding the token.
+    Env(String),
+    /// Entry of the OS keyring.
+    Keyring { service: String, account: String },
+}
+
+impl FromYamlDocument for Auth {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        let source = match (optional_string(yaml, "token_env")?, &yaml["keyring"]) {
+            (Some(variable), Yaml::BadValue) => SecretSource::Env(variable),
+            (None, keyring @ Yaml::Hash(_)) => SecretSource::Keyring {
+                service: required_string(keyring, "service")?,
+                account: required_string(keyring, "account")?,
+            },
+            (None, Yaml::BadValue) => return Err(ParsingError::MissingField("token_env")),
+            _ => return Err(ParsingError::InvalidField("auth")),
+        };
+        Ok(Auth { username: optional_string(yaml, "username")?, source })
+    }
+}
+
+impl ToYamlDocument for Auth {
+    fn to_yaml(&self) -> Yaml {
+        let mut yaml = Hash::new();
+        insert_optional(&mut yaml, "username", &self.username);
+        match &self.source {
+            SecretSource::Env(variable) => {
+                yaml.insert(key("token_env"), Yaml::String(variable.to_owned()));
+            }
+            SecretSource::Keyring { service, account } => {
+                let mut keyring = Hash::new();
+                keyring.insert(key("service"), Yaml::String(service.to_owned()));
+                keyring.insert(key("account"), Yaml::String(account.to_owned()));
+                yaml.insert(key("keyring"), Yaml::Hash(keyring));
+            }
+        }
+        Yaml::Hash(yaml)
+    }
+}
+
+/// Whether git reads `url` as a path rather than a remote: it has no scheme and no `host:` as
+/// in `git@host:owner/name`, unless a `/` comes before the colon.
+fn is_path(url: &str) -> bool {
+    !url.contains("://") && url.find(':').map(|colon| url[..colon].contains('/')).unwrap_or(true)
+}
+
+/// The last `count` segments of `url` joined with `-`, without the `.git` suffix.
+fn url_segments(url: &str, count: usize) -> String {
+    let mut segments = url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .rsplit(['/', ':'])
+        .take(count)
+        .collect::<Vec<_>>();
+    segments.reverse();
+    segments.join("-")
+}
+
+/// Parameter receiving the absolute path of the file of an experiment expanded from `for_each`.
+const FOR_EACH_PARAM: &str = "file";
+
+/// Experiments of `items`, each one declaring a `for_each` pattern, relative to `config_dir`,
+/// replaced by one experiment per matching file in path order. Each is named, and logs, after its
+/// own name and the stem of its file, as `<name>-<stem>`, and gets the path of its file as the
+/// `{file}` param, quoted in its command and check when the path has whitespace. The names of
+/// the expansions of each are recorded in `expanded` under its own.
+fn for_each_expanded(items: &[Yaml], config_dir: &Path, expanded: &mut BTreeMap<String, Vec<String>>) -> Result<Vec<Experiment>, ParsingError> {
+    let mut experiments = Vec::new();
+    for item in items {
+        let (pattern, hash) = match (as_string(&item["for_each"]), item) {
+            (Some(pattern), Yaml::Hash(hash)) => (pattern, hash),
+            (None, _) if item["for_each"].is_badvalue() => {
+                experiments.push(Experiment::from_yaml(item)?);
+                continue;
+            }
+            _ => return Err(ParsingError::InvalidField("for_each")),
+        };
+        let name = required_string(item, "name")?;
+        let files = glob(config_dir, &pattern)
+            .map_err(|e| ParsingError::UnreadableFile(format!("{}: {}", pattern, e)))?
+            .into_iter()
+            .filter(|file| file.is_file())
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return Err(ParsingError::EmptyForEach(name, pattern));
+        }
+        if !item["params"][FOR_EACH_PARAM].is_badvalue() {
+            return Err(ParsingError::InvalidField("params"));
+        }
+
+        let mut stems = Vec::new();
+        for file in files {
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            if stems.contains(&stem) {
+                return Err(ParsingError::ForEachCollision(name, stem));
+            }
+            let path = std::path::absolute(&file)
+                .map_err(|e| ParsingError::UnreadableFile(format!("{}: {}", file.display(), e)))?;
+            let path = path.to_string_lossy().into_owned();
+
+            let mut expansion = hash.clone();
+            expansion.remove(&key("for_each"));
+            expansion.insert(key("name"), Yaml::String(format!("{}-{}", name, stem)));
+            if let Some(log_dir) = as_string(&item["log_dir"]) {
+                expansion.insert(key("log_dir"), Yaml::String(format!("{}-{}", log_dir, stem)));
+            }
+            let mut params = match &item["params"] {
+                Yaml::Hash(params) => params.clone(),
+                _ => Hash::new(),
+            };
+            params.insert(key(FOR_EACH_PARAM), Yaml::String(path));
+            expansion.insert(key("params"), Yaml::Hash(params));
+            let exp = Experiment::parse(&Yaml::Hash(expansion), &[FOR_EACH_PARAM])?;
+            expanded.entry(name.to_owned()).or_default().push(exp.name.to_owned());
+            experiments.push(exp);
+            stems.push(stem);
+        }
+    }
+    Ok(experiments)
+}
+
+/// Renames the repositories without `dest` whose url-derived folder collides with another one to
+/// `<owner>-<name>`, then rejects any remaining duplicate.
+fn unique_destinations(mut repositories: Vec<Repository>) -> Result<Vec<Repository>, ParsingError> {
+    let names = repositories.iter().map(|repository| repository.name.to_owned()).collect::<Vec<_>>();
+    for repository in repositories.iter_mut().filter(|repository| repository.dest.is_none()) {
+        if names.iter().filter(|name| **name == repository.name).count() > 1 {
+            repository.name = url_segments(&repository.url, 2);
+        }
+    }
+
+    for (index, repository) in repositories.iter().enumerate() {
+        if repositories[..index].iter().any(|other| other.name == repository.name) {
+            return Err(ParsingError::DuplicateRepositoryDest(repository.name.to_owned()));
+        }
+    }
+    Ok(repositories)
+}
+
+#[derive(Clone, Debug)]
+pub struct Experiment {
+    pub name: String,
+    pub repository: Option<String>,
+    /// With `params`, the template with its placeholders already substituted. With a `script`,
+    /// its interpreter followed by `{script}`, the path the script is written to.
+    pub command: String,
+    /// Values of the `{name}` placeholders of the command template, its check and golden files.
+    /// Every run is also given them as `REPLIKATE_PARAM_<NAME>` environment variables.
+    pub params: BTreeMap<String, String>,
+    /// Inline script run by the interpreter of `command` instead of a program. It is not
+    /// templated, its braces belonging to the shell: it reads its params from the environment.
+    pub script: Option<String>,
+    pub timeout: Option<u64>,
+    /// Seconds after which a successful run is reported as slow, over `--max-runtime-per-experiment`.
+    pub soft_timeout: Option<u64>,
+    pub nice: Option<i32>,
+    pub cpus: Vec<usize>,
+    /// Files or globs, relative to the working directory, collected after a successful run.
+    pub outputs: Vec<String>,
+    pub tags: Vec<String>,
+    /// Expected size in bytes of what the experiment writes, used by `--min-free-disk`.
+    pub estimated_size: Option<u64>,
+    /// Bytes of memory the experiment may use, enforced by a cgroup on Linux.
+    pub memory_limit: Option<u64>,
+    /// Cores the experiment may keep busy, possibly fractional, enforced by a cgroup on Linux.
+    pub cpu_limit: Option<f64>,
+    /// Name of the folder under the logs directory, defaults to the experiment name.
+    pub log_dir: Option<String>,
+    /// Datasets checked against their checksum before the experiment runs.
+    pub inputs: Vec<Input>,
+    /// Experiments with a higher priority are started first, 0 by default. Equal priorities
+    /// keep the config order.
+    pub priority: i64,
+    /// Experiments that must succeed before this one starts.
+    pub depends_on: Vec<String>,
+    /// Requirements the experiment needs, it is skipped when one of the optional ones is missing.
+    pub requires: Vec<String>,
+    /// Expected output of a successful run.
+    pub golden: Option<Golden>,
+    /// Validator run from the working directory once the command succeeded, as the command
+    /// without executor. The experiment only succeeds when it exits with 0 too.
+    pub check: Option<String>,
+    /// JSON or TSV file, relative to the working directory, the experiment writes its results
+    /// into. Read once it succeeded, into the metrics of its report.
+    pub result_file: Option<String>,
+    /// Piped to the experiment, which otherwise gets a closed stdin.
+    pub stdin: Option<StdinSource>,
+    /// Output streams recorded into the logs, both by default.
+    pub capture: Capture,
+    /// Number of times a failed run is started again.
+    pub retries: u32,
+    /// Exit codes worth a retry, any failure but a cancellation when empty.
+    pub retry_on: Vec<i32>,
+    /// Loaded over the project `env_file`, relative to the working directory.
+    pub env_file: Option<EnvFile>,
+    /// Environment variables set for the experiment, over those of the env files.
+    pub env: BTreeMap<String, String>,
+    /// Overrides the project `executor`, an empty one running the command directly.
+    pub executor: Option<String>,
+    /// Amounts of the project `resources` held while the experiment runs.
+    pub resources: BTreeMap<String, u64>,
+    /// Project `groups` entry whose setup and teardown surround the experiment.
+    pub group: Option<String>,
+    /// A failure is reported but does not fail the run nor count towards `--max-failures`.
+    pub allow_failure: bool,
+}
+
+impl FromYamlDocument for Experiment {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        Experiment::parse(yaml, &[])
+    }
+}
+
+impl Experiment {
+    /// Experiment of `yaml`, the values of its `quoted` params being quoted where they fill the
+    /// command or the check, which are split into words.
+    fn parse(yaml: &Yaml, quoted: &[&str]) -> Result<Self, ParsingError> {
+        let timeout = match &yaml["timeout"] {
+            Yaml::BadValue => None,
+            Yaml::Integer(seconds) if *seconds >= 0 => Some(*seconds as u64),
+            _ => return Err(ParsingError::InvalidField("timeout")),
+        };
+
+        let soft_timeout = match &yaml["soft_timeout"] {
+            Yaml::BadValue => None,
+            Yaml::Integer(seconds) if *seconds >= 0 => Some(*seconds as u64),
+            _ => return Err(ParsingError::InvalidField("soft_timeout")),
+        };
+
+        let nice = match &yaml["nice"] {
+            Yaml::BadValue => None,
+            Yaml::Integer(n) if *n >= -20 && *n <= 19 => Some(*n as i32),
+            _ => return Err(ParsingError::InvalidField("nice")),
+        };
+
+        let cpus = items(&yaml["cpus"]).iter()
+            .map(|core| match core {
+                Yaml::Integer(core) if *core >= 0 => Ok(*core as usize),
+                _ => Err(ParsingError::InvalidField("cpus")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let name = required_string(yaml, "name")?;
+        let params = string_map(yaml, "params")?;
+        let script = optional_string(yaml, "script")?;
+        let interpreter = optional_string(yaml, "interpreter")?;
+        let mut command = match (&script, optional_string(yaml, "command")?, &interpreter) {
+            (Some(_), Some(_), _) => return Err(ParsingError::InvalidField("script")),
+            (Some(_), None, _) => format!("{} {}", interpreter.as_deref().unwrap_or(DEFAULT_INTERPRETER), SCRIPT_PLACEHOLDER),
+            (None, Some(_), Some(_)) => return Err(ParsingError::InvalidField("interpreter")),
+            (None, Some(command), None) => command,
+            (None, None, _) => return Err(ParsingError::MissingField("command")),
+        };
+        let mut check = match optional_string(yaml, "check")? {
+            Some(check) if check.trim().is_empty() => return Err(ParsingError::InvalidField("check")),
+            check => check,
+        };
+        let mut golden = match &yaml["golden"] {
+            Yaml::BadValue => None,
+            golden => Some(Golden::from_yaml(golden)?),
+        };
+        if !yaml["params"].is_badvalue() {
+            let words = params.iter()
+                .map(|(param, value)| (param.to_owned(), if quoted.contains(&param.as_str()) { quote_word(value) } else { value.to_owned() }))
+                .collect::<BTreeMap<_, _>>();
+            let mut templates = Vec::new();
+            if script.is_none() {
+                templates.push((&mut command, &words));
+            }
+            templates.extend(check.as_mut().map(|check| (check, &words)));
+            if let Some(golden) = &mut golden {
+                templates.push((&mut golden.path, &params));
+                templates.extend(golden.output.as_mut().map(|output| (output, &params)));
+            }
+            fill_templates(&name, &mut templates, script.is_none())?;
+        }
+        if command.trim().is_empty() || script.as_deref().map(|script| script.trim().is_empty()).unwrap_or(false) {
+            return Err(ParsingError::EmptyCommand(name));
+        }
+        if split_words(&command).is_none() {
+            return Err(ParsingError::InvalidField("command"));
+        }
+        if check.as_deref().map(|check| split_words(check).is_none()).unwrap_or(false) {
+            return Err(ParsingError::InvalidField("check"));
+        }
+
+        let capture = match optional_string(yaml, "capture")? {
+            Some(capture) => Capture::from_name(&capture).ok_or(ParsingError::InvalidField("capture"))?,
+            None => Capture::Both,
+        };
+        if matches!(&golden, Some(Golden { output: None, .. })) && !capture.stdout() {
+            return Err(ParsingError::UncapturedGolden(name));
+        }
+
+        Ok(Experiment {
+            name,
+            repository: optional_string(yaml, "repository")?,
+            command,
+            params,
+            script,
+            timeout,
+            soft_timeout,
+            nice,
+            cpus,
+            outputs: string_list(yaml, "outputs")?,
+            tags: string_list(yaml, "tags")?,
+            estimated_size: match optional_string(yaml, "estimated_size")? {
+                Some(size) => Some(parse_size(&size).ok_or(ParsingError::InvalidField("estimated_size"))?),
+                None => None,
+            },
+            memory_limit: match optional_string(yaml, "memory_limit")? {
+                Some(size) => Some(parse_size(&size).filter(|size| *size > 0).ok_or(ParsingError::InvalidField("memory_limit"))?),
+                None => None,
+            },
+            cpu_limit: match optional_string(yaml, "cpu_limit")? {
+                Some(cores) => Some(cores.parse::<f64>().ok()
+                    .filter(|cores| cores.is_finite() && *cores > 0.0)
+                    .ok_or(ParsingError::InvalidField("cpu_limit"))?),
+                None => None,
+            },
+            log_dir: optional_string(yaml, "log_dir")?,
+            inputs: list_of(yaml, "inputs")?,
+            priority: match &yaml["priority"] {
+                Yaml::BadValue => 0,
+                Yaml::Integer(priority) => *priority,
+                _ => return Err(ParsingError::InvalidField("priority")),
+            },
+            depends_on: string_list(yaml, "depends_on")?,
+            requires: string_list(yaml, "requires")?,
+            golden,
+            check,
+            result_file: match optional_string(yaml, "result_file")? {
+                Some(file) if file.trim().is_empty() => return Err(ParsingError::InvalidField("result_file")),
+                file => file,
+            },
+            stdin: match &yaml["stdin"] {
+                Yaml::BadValue => None,
+                stdin => Some(StdinSource::from_yaml(stdin)?),
+            },
+            capture,
+            retries: match &yaml["retries"] {
+                Yaml::BadValue => 0,
+                Yaml::Integer(retries) if *retries >= 0 && *retries <= u32::MAX as i64 => *retries as u32,
+                _ => return Err(ParsingError::InvalidField("retries")),
+            },
+            retry_on: items(&yaml["retry_on"]).iter()
+                .map(|code| match code {
+                    Yaml::Integer(code) if *code >= i32::MIN as i64 && *code <= i32::MAX as i64 => Ok(*code as i32),
+                    _ => Err(ParsingError::InvalidField("retry_on")),
+                })
+                .collect::<Result<_, _>>()?,
+            env_file: optional_env_file(yaml)?,
+            env: string_map(yaml, "env")?,
+            executor: executor(yaml)?,
+            resources: resource_map(yaml)?,
+            group: optional_string(yaml, "group")?,
+            allow_failure: bool_field(yaml, "allow_failure", false)?,
+        })
+    }
+}
+
+impl ToYamlDocument for Experiment {
+    fn to_yaml(&self) -> Yaml {
+        let mut yaml = Hash::new();
+        yaml.insert(key("name"), Yaml::String(self.name.to_owned()));
+        insert_optional(&mut yaml, "repository", &self.repository);
+        // The substituted command stands for its template, `params` would be left unused. Scripts
+        // are not templated and read their `params` from the environment, so they keep them, the
+        // braces of their substituted templates escaped to read back the same.
+        let templated = self.script.is_some() && !self.params.is_empty();
+        match &self.script {
+            Some(script) => {
+                yaml.insert(key("script"), Yaml::String(script.to_owned()));
+                let interpreter = self.command.strip_suffix(SCRIPT_PLACEHOLDER).unwrap_or(&self.command).trim_end();
+                if interpreter != DEFAULT_INTERPRETER {
+                    yaml.insert(key("interpreter"), Yaml::String(interpreter.to_owned()));
+                }
+                if templated {
+                    yaml.insert(key("params"), Yaml::Hash(self.params.iter()
+                        .map(|(name, value)| (key(name), Yaml::String(value.to_owned())))
+                        .collect()));
+                }
+            }
+            None => {
+                yaml.insert(key("command"), Yaml::String(self.command.to_owned()));
+            }
+        }
+        if let Some(timeout) = self.timeout {
+            yaml.insert(key("timeout"), Yaml::Integer(timeout as i64));
+        }
+        if let Some(soft_timeout) = self.soft_timeout {
+            yaml.insert(key("soft_timeout"), Yaml::Integer(soft_timeout as i64));
+        }
+        if let Some(nice) = self.nice {
+            yaml.insert(key("nice"), Yaml::Integer(nice as i64));
+        }
+        if !self.cpus.is_empty() {
+            yaml.insert(key("cpus"), Yaml::Array(self.cpus.iter().map(|core| Yaml::Integer(*core as i64)).collect()));
+        }
+        if !self.outputs.is_empty() {
+            yaml.insert(key("outputs"), strings_to_yaml(&self.outputs));
+        }
+        if !self.tags.is_empty() {
+            yaml.insert(key("tags"), strings_to_yaml(&self.tags));
+        }
+        if let Some(size) = self.estimated_size {
+            yaml.insert(key("estimated_size"), Yaml::Integer(size as i64));
+        }
+        if let Some(size) = self.memory_limit {
+            yaml.insert(key("memory_limit"), Yaml::Integer(size as i64));
+        }
+        if let Some(cores) = self.cpu_limit {
+            yaml.insert(key("cpu_limit"), Yaml::Real(cores.to_string()));
+        }
+        insert_optional(&mut yaml, "log_dir", &self.log_dir);
+        if !self.inputs.is_empty() {
+            yaml.insert(key("inputs"), list_to_yaml(&self.inputs));
+        }
+        if self.priority != 0 {
+            yaml.insert(key("priority"), Yaml::Integer(self.priority));
+        }
+        if !self.depends_on.is_empty() {
+            yaml.insert(key("depends_on"), strings_to_yaml(&self.depends_on));
+        }
+        if !self.requires.is_empty() {
+            yaml.insert(key("requires"), strings_to_yaml(&self.requires));
+        }
+        if let Some(golden) = &self.golden {
+            let golden = if templated {
+                Golden { path: escape_braces(&golden.path), output: golden.output.as_deref().map(escape_braces), ..golden.clone() }
+            } else {
+                golden.clone()
+            };
+            yaml.insert(key("golden"), golden.to_yaml());
+        }
+        let check = self.check.as_deref().map(|check| if templated { escape_braces(check) } else { check.to_owned() });
+        insert_optional(&mut yaml, "check", &check);
+        insert_optional(&mut yaml, "result_file", &self.result_file);
+        if let Some(stdin) = &self.stdin {
+            yaml.insert(key("stdin"), stdin.to_yaml());
+        }
+        if self.capture != Capture::Both {
+            yaml.insert(key("capture"), Yaml::String(self.capture.name().to_owned()));
+        }
+        if self.retries != 0 {
+            yaml.insert(key("retries"), Yaml::Integer(self.retries as i64));
+        }
+        if !self.retry_on.is_empty() {
+            yaml.insert(key("retry_on"), Yaml::Array(self.retry_on.iter().map(|code| Yaml::Integer(*code as i64)).collect()));
+        }
+        if let Some(env_file) = &self.env_file {
+            yaml.insert(key("env_file"), env_file.to_yaml());
+        }
+        insert_optional(&mut yaml, "executor", &self.executor);
+        if !self.resources.is_empty() {
+            yaml.insert(key("resources"), resources_to_yaml(&self.resources));
+        }
+        insert_optional(&mut yaml, "group", &self.group);
+        if self.allow_failure {
+            yaml.insert(key("allow_failure"), Yaml::Boolean(true));
+        }
+        if !self.env.is_empty() {
+            yaml.insert(key("env"), Yaml::Hash(self.env.iter()
+                .map(|(name, value)| (key(name), Yaml::String(value.to_owned())))
+                .collect()));
+        }
+        Yaml::Hash(yaml)
+    }
+}
+
+impl Experiment {
+    pub fn log_folder(&self) -> &str {
+        self.log_dir.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn program(&self) -> String {
+        split_words(&self.command).and_then(|words| words.into_iter().next()).unwrap_or_default()
+    }
+}
+
+/// Words of `command` split as a shell would, without expanding anything. Whitespace separates
+/// words but within quotes: `'...'` is taken as is, `"..."` only unescapes `\"`, `\\`, `\$`
+/// and `` \` ``, and a backslash elsewhere escapes the next character. `None` when a quote is left
+/// open.
+pub fn split_words(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            c => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).push(chars.next().unwrap_or('\\')),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// `word` as `split_words` reads it back as a single word: single-quoted, unless it needs no
+/// quoting.
+fn quote_word(word: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || matches!(c, '\'' | '"' | '\\')) {
+        return word.to_owned();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// Replaces each `{name}` of the `templates` of `experiment`, its command, check and golden files,
+/// by its value in the params given with the template. `{{` and `}}` stand for literal braces,
+/// other braces are kept as is. When `exhaustive`, every param must be used by one of them, a
+/// script reading them instead.
+fn fill_templates(
+    experiment: &str, templates: &mut [(&mut String, &BTreeMap<String, String>)], exhaustive: bool,
+) -> Result<(), ParsingError> {
+    let mut used = Vec::new();
+    for (template, params) in templates.iter_mut() {
+        **template = fill_template(experiment, template, params, &mut used)?;
+    }
+    match templates.iter().flat_map(|(_, params)| params.keys()).find(|param| exhaustive && !used.contains(param)) {
+        Some(unused) => Err(ParsingError::UnusedParam(experiment.to_owned(), unused.to_owned())),
+        None => Ok(()),
+    }
+}
+
+/// `text` read back as itself once templated: its braces doubled.
+fn escape_braces(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+/// `template` with its placeholders filled, the params it uses being added to `used`.
+fn fill_template(experiment: &str, template: &str, params: &BTreeMap<String, String>, used: &mut Vec<String>) -> Result<String, ParsingError> {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let mut command = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        command.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        if candidate.starts_with("{{") || candidate.starts_with("}}") {
+            command.push_str(&candidate[..1]);
+            rest = &candidate[2..];
+            continue;
+        }
+        let placeholder = candidate[1..].find(|c| !is_name(c))
+            .filter(|end| *end > 0 && candidate[1 + end..].starts_with('}'))
+            .map(|end| &candidate[1..1 + end]);
+        match placeholder {
+            Some(param) => {
+                let value = params.get(param)
+                    .ok_or_else(|| ParsingError::UndefinedParam(experiment.to_owned(), param.to_owned()))?;
+                command.push_str(value);
+                used.push(param.to_owned());
+                rest = &candidate[param.len() + 2..];
+            }
+            None => {
+                command.push_str(&candidate[..1]);
+                rest = &candidate[1..];
+            }
+        }
+    }
+    command.push_str(rest);
+    Ok(command)
+}
+
+/// Output streams of an experiment written to its logs, the others are discarded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Capture {
+    Both,
+    Stdout,
+    Stderr,
+    None,
+}
+
+impl Capture {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "both" => Some(Capture::Both),
+            "stdout" => Some(Capture::Stdout),
+            "stderr" => Some(Capture::Stderr),
+            "none" => Some(Capture::None),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capture::Both => "both",
+            Capture::Stdout => "stdout",
+            Capture::Stderr => "stderr",
+            Capture::None => "none",
+        }
+    }
+
+    pub fn stdout(&self) -> bool {
+        matches!(self, Capture::Both | Capture::Stdout)
+    }
+
+    pub fn stderr(&self) -> bool {
+        matches!(self, Capture::Both | Capture::Stderr)
+    }
+}
+
+/// Either a path, or a map with the `path` and whether the file is `optional`.
+#[derive(Clone, Debug)]
+pub struct EnvFile {
+    pub path: String,
+    /// A missing optional file is ignored instead of failing the run.
+    pub optional: bool,
+}
+
+impl FromYamlDocument for EnvFile {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        match yaml {
+            Yaml::Hash(_) => Ok(EnvFile {
+                path: required_string(yaml, "path")?,
+                optional: match &yaml["optional"] {
+                    Yaml::BadValue => false,
+                    Yaml::Boolean(optional) => *optional,
+                    _ => return Err(ParsingError::InvalidField("optional")),
+                },
+            }),
+            path => Ok(EnvFile {
+                path: as_string(path).ok_or(ParsingError::InvalidField("env_file"))?,
+                optional: false,
+            }),
+        }
+    }
+}
+
+impl ToYamlDocument for EnvFile {
+    fn to_yaml(&self) -> Yaml {
+        if !self.optional {
+            return Yaml::String(self.path.to_owned());
+        }
+        let mut yaml = Hash::new();
+        yaml.insert(key("path"), Yaml::String(self.path.to_owned()));
+        yaml.insert(key("optional"), Yaml::Boolean(true));
+        Yaml::Hash(yaml)
+    }
+}
+
+fn optional_env_file(yaml: &Yaml) -> Result<Option<EnvFile>, ParsingError> {
+    match &yaml["env_file"] {
+        Yaml::BadValue => Ok(None),
+        env_file => EnvFile::from_yaml(env_file).map(Some),
+    }
+}
+
+/// Either an inline string or a map with the `file`, relative to the working directory, whose
+/// content is piped.
+#[derive(Clone, Debug)]
+pub enum StdinSource {
+    Text(String),
+    File(String),
+}
+
+impl FromYamlDocument for StdinSource {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        match yaml {
+            Yaml::Hash(_) => Ok(StdinSource::File(required_string(yaml, "file")?)),
+            text => as_string(text).map(StdinSource::Text).ok_or(ParsingError::InvalidField("stdin")),
+        }
+    }
+}
+
+impl ToYamlDocument for StdinSource {
+    fn to_yaml(&self) -> Yaml {
+        match self {
+            StdinSource::Text(text) => Yaml::String(text.to_owned()),
+            StdinSource::File(file) => {
+                let mut yaml = Hash::new();
+                yaml.insert(key("file"), Yaml::String(file.to_owned()));
+                Yaml::Hash(yaml)
+            }
+        }
+    }
+}
+
+/// Either a path, compared to the captured stdout, or a map with a `path`, the `output` file
+/// it is compared to and a numeric `tolerance`. Both paths are relative to the experiment's
+/// working directory.
+#[derive(Clone, Debug)]
+pub struct Golden {
+    pub path: String,
+    /// Output file compared instead of stdout.
+    pub output: Option<String>,
+    /// Compares the numbers of the output within this tolerance instead of byte for byte.
+    pub tolerance: Option<Tolerance>,
+}
+
+impl FromYamlDocument for Golden {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        match yaml {
+            Yaml::Hash(_) => Ok(Golden {
+                path: required_string(yaml, "path")?,
+                output: optional_string(yaml, "output")?,
+                tolerance: match &yaml["tolerance"] {
+                    Yaml::BadValue => None,
+                    tolerance => Some(Tolerance::from_yaml(tolerance)?),
+                },
+            }),
+            golden => Ok(Golden {
+                path: as_string(golden).ok_or(ParsingError::InvalidField("golden"))?,
+                output: None,
+                tolerance: None,
+            }),
+        }
+    }
+}
+
+impl ToYamlDocument for Golden {
+    fn to_yaml(&self) -> Yaml {
+        match (&self.output, &self.tolerance) {
+            (None, None) => Yaml::String(self.path.to_owned()),
+            _ => {
+                let mut yaml = Hash::new();
+                yaml.insert(key("path"), Yaml::String(self.path.to_owned()));
+                insert_optional(&mut yaml, "output", &self.output);
+                if let Some(tolerance) = &self.tolerance {
+                    yaml.insert(key("tolerance"), tolerance.to_yaml());
+                }
+                Yaml::Hash(yaml)
+            }
+        }
+    }
+}
+
+/// How far a number of the output may be from the golden one: up to `absolute`, or up to
+/// `relative` times the golden number, whichever is larger. Both default to 0 but one must be
+/// given.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl FromYamlDocument for Tolerance {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        let bound = |field: &'static str| match optional_string(yaml, field)? {
+            Some(bound) => bound.parse::<f64>().ok()
+                .filter(|bound| bound.is_finite() && *bound >= 0.0)
+                .map(Some)
+                .ok_or(ParsingError::InvalidField(field)),
+            None => Ok(None),
+        };
+        match (bound("absolute")?, bound("relative")?) {
+            (None, None) => Err(ParsingError::InvalidField("tolerance")),
+            (absolute, relative) => Ok(Tolerance { absolute: absolute.unwrap_or(0.0), relative: relative.unwrap_or(0.0) }),
+        }
+    }
+}
+
+impl ToYamlDocument for Tolerance {
+    fn to_yaml(&self) -> Yaml {
+        let mut yaml = Hash::new();
+        // An exact comparison of the numbers still needs one of them.
+        if self.absolute > 0.0 || self.relative == 0.0 {
+            yaml.insert(key("absolute"), Yaml::Real(self.absolute.to_string()));
+        }
+        if self.relative > 0.0 {
+            yaml.insert(key("relative"), Yaml::Real(self.relative.to_string()));
+        }
+        Yaml::Hash(yaml)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Input {
+    /// Relative to the experiment's working directory.
+    pub path: String,
+    /// Expected SHA-256 as lowercase hexadecimal.
+    pub sha256: String,
+    /// Downloaded to `path` when the file is missing.
+    pub url: Option<String>,
+}
+
+impl FromYamlDocument for Input {
+    fn from_yaml(yaml: &Yaml) -> Result<Self, ParsingError> {
+        let sha256 = required_string(yaml, "sha256")?.to_lowercase();
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParsingError::InvalidField("sha256"));
+        }
+
+        Ok(Input {
+            path: required_string(yaml, "path")?,
+            sha256,
+            url: optional_string(yaml, "url")?,
+        })
+    }
+}
+
+impl ToYamlDocument for Input {
+    fn to_yaml(&self) -> Yaml {
+        let mut yaml = Hash::new();
+        yaml.insert(key("path"), Yaml::String(self.path.to_owned()));
+        yaml.insert(key("sha256"), Yaml::String(self.sha256.to_owned()));
+        insert_optional(&mut yaml, "url", &self.url);
+        Yaml::Hash(yaml)
+    }
+}
+
+fn as_string(yaml: &Yaml) -> Option<String> {
+    match yaml {
+        Yaml::String(s) => Some(s.to_owned()),
+        Yaml::Real(s) => Some(s.to_owned()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Scalar-or-sequence leniency: a field expecting a list accepts a lone value, read as a list
+/// of one item. This applies to every list field: `preflight`, `setup`, `teardown`, `aggregate`,
+/// `git_args`, `requirements`, `any_of`, `repositories`, `build_depends_on`, `experiments`,
+/// `experiments_from`, `cpus`, `outputs`, `tags`, `inputs`, `depends_on`, `requires` and
+/// `retry_on`. Conversely, a field expecting a single string, such as `command`, accepts a list
+/// holding exactly one.
+fn items(value: &Yaml) -> &[Yaml] {
+    match value {
+        Yaml::BadValue => &[],
+        Yaml::Array(items) => items,
+        value => std::slice::from_ref(value),
+    }
+}
+
+/// String of a scalar field, or of the single item of a list.
+fn scalar_string(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::Array(items) if items.len() == 1 => as_string(&items[0]),
+        value => as_string(value),
+    }
+}
+
+fn required_string(yaml: &Yaml, field: &'static str) -> Result<String, ParsingError> {
+    match &yaml[field] {
+        Yaml::BadValue => Err(ParsingError::MissingField(field)),
+        value => scalar_string(value).ok_or(ParsingError::InvalidField(field)),
+    }
+}
+
+fn optional_string(yaml: &Yaml, field: &'static str) -> Result<Option<String>, ParsingError> {
+    match &yaml[field] {
+        Yaml::BadValue => Ok(None),
+        value => scalar_string(value).map(Some).ok_or(ParsingError::InvalidField(field)),
+    }
+}
+
+fn string_list(yaml: &Yaml, field: &'static str) -> Result<Vec<String>, ParsingError> {
+    items(&yaml[field]).iter()
+        .map(|item| as_string(item).ok_or(ParsingError::InvalidField(field)))
+        .collect()
+}
+
+fn string_map(yaml: &Yaml, field: &'static str) -> Result<BTreeMap<String, String>, ParsingError> {
+    match &yaml[field] {
+        Yaml::BadValue => Ok(BTreeMap::new()),
+        Yaml::Hash(entries) => entries.iter()
+            .map(|(name, value)| match (name.as_str(), as_string(value)) {
+                (Some(name), Some(value)) => Ok((name.to_owned(), value)),
+                _ => Err(ParsingError::InvalidField(field)),
+            })
+            .collect(),
+        _ => Err(ParsingError::InvalidField(field)),
+    }
+}
+
+/// `resources` as a map from names to non-negative amounts.
+fn resource_map(yaml: &Yaml) -> Result<BTreeMap<String, u64>, ParsingError> {
+    match &yaml["resources"] {
+        Yaml::BadValue => Ok(BTreeMap::new()),
+        Yaml::Hash(entries) => entries.iter()
+            .map(|(name, amount)| match (name.as_str(), amount) {
+                (Some(name), Yaml::Integer(amount)) if *amount >= 0 => Ok((name.to_owned(), *amount as u64)),
+                _ => Err(ParsingError::InvalidField("resources")),
+            })
+            .collect(),
+        _ => Err(ParsingError::InvalidField("resources")),
+    }
+}
+
+/// `groups` as a map from names to their hooks.
+fn group_map(yaml: &Yaml) -> Result<BTreeMap<String, Group>, ParsingError> {
+    match &yaml["groups"] {
+        Yaml::BadValue => Ok(BTreeMap::new()),
+        Yaml::Hash(entries) => entries.iter()
+            .map(|(name, group)| match (name.as_str(), group) {
+                (Some(name), Yaml::Hash(_)) => Ok((name.to_owned(), Group::from_yaml(group)?)),
+                _ => Err(ParsingError::InvalidField("groups")),
+            })
+            .collect(),
+        _ => Err(ParsingError::InvalidField("groups")),
+    }
+}
+
+fn resources_to_yaml(resources: &BTreeMap<String, u64>) -> Yaml {
+    Yaml::Hash(resources.iter().map(|(name, amount)| (key(name), Yaml::Integer(*amount as i64))).collect())
+}
+
+fn list_of<T: FromYamlDocument>(yaml: &Yaml, field: &'static str) -> Result<Vec<T>, ParsingError> {
+    items(&yaml[field]).iter().map(T::from_yaml).collect()
+}
+
+fn key(name: &str) -> Yaml {
+    Yaml::String(name.to_owned())
+}
+
+fn insert_optional(yaml: &mut Hash, field: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        yaml.insert(key(field), Yaml::String(value.to_owned()));
+    }
+}
+
+fn list_to_yaml<T: ToYamlDocument>(items: &[T]) -> Yaml {
+    Yaml::Array(items.iter().map(T::to_yaml).collect())
+}
+
+fn strings_to_yaml(items: &[String]) -> Yaml {
+    Yaml::Array(items.iter().map(|item| Yaml::String(item.to_owned())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, path::Path};
+    use yaml_rust::{YamlEmitter, YamlLoader};
+    use super::{Experiment, FromYamlDocument, ParsingError, Project, Repository, for_each_expanded, ToYamlDocument, fill_templates, graph_levels, is_path, split_words};
+
+    fn params(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn fill_template(experiment: &str, template: &str, params: &BTreeMap<String, String>) -> Result<String, ParsingError> {
+        let mut template = template.to_owned();
+        fill_templates(experiment, &mut [(&mut template, params)], true).map(|_| template)
+    }
+
+    #[test]
+    fn fills_placeholders_and_keeps_other_braces() {
+        let params = params(&[("input", "data.txt"), ("n_runs", "3")]);
+        assert_eq!(
+            fill_template("exp", "bench --runs {n_runs} {input} {} {{input}} {1", &params).unwrap(),
+            "bench --runs 3 data.txt {} {input} {1"
+        );
+    }
+
+    #[test]
+    fn rejects_undefined_and_unused_params() {
+        assert!(matches!(
+            fill_template("exp", "bench {input}", &params(&[])),
+            Err(ParsingError::UndefinedParam(_, param)) if param == "input"
+        ));
+        assert!(matches!(
+            fill_template("exp", "bench", &params(&[("input", "data.txt")])),
+            Err(ParsingError::UnusedParam(_, param)) if param == "input"
+        ));
+    }
+
+    #[test]
+    fn fills_the_check_and_golden_files_too() {
+        let config = "name: exp\ncommand: bench {input}\ncheck: verify out/{input}.txt\n\
+            golden:\n  path: expected/{mode}.txt\n  output: out/{input}.txt\nparams:\n  input: data\n  mode: fast\n";
+        let exp = Experiment::from_yaml(&YamlLoader::load_from_str(config).unwrap()[0]).unwrap();
+        assert_eq!(exp.check.as_deref(), Some("verify out/data.txt"));
+        let golden = exp.golden.unwrap();
+        assert_eq!((golden.path.as_str(), golden.output.as_deref()), ("expected/fast.txt", Some("out/data.txt")));
+    }
+
+    #[test]
+    fn leaves_scripts_untemplated() {
+        let config = "name: exp\nscript: |\n  awk '{ print $1 }' \"${REPLIKATE_PARAM_INPUT}\" }}\nparams:\n  input: data.txt\n";
+        let exp = Experiment::from_yaml(&YamlLoader::load_from_str(config).unwrap()[0]).unwrap();
+        assert_eq!(exp.script.as_deref(), Some("awk '{ print $1 }' \"${REPLIKATE_PARAM_INPUT}\" }}\n"));
+    }
+
+    #[test]
+    fn splits_commands_as_a_shell_would() {
+        let words = |command| split_words(command).unwrap();
+        assert_eq!(words("grep -c 'a  b' \"my file.txt\""), vec!["grep", "-c", "a  b", "my file.txt"]);
+        assert_eq!(words("echo \"say \\\"hi\\\" \\n\" it\\'s ''"), vec!["echo", "say \"hi\" \\n", "it's", ""]);
+        assert_eq!(words("  run\t--fast  "), vec!["run", "--fast"]);
+        assert_eq!(split_words("echo 'open"), None);
+
+        let yaml = &YamlLoader::load_from_str("name: exp\ncommand: echo \"open\n").unwrap()[0];
+        assert!(matches!(Experiment::from_yaml(yaml), Err(ParsingError::InvalidField("command"))));
+    }
+
+    #[test]
+    fn levels_follow_dependencies_and_report_cycles() {
+        let (a, b) = (["b".to_owned()], ["c".to_owned()]);
+        assert_eq!(graph_levels(&["a", "b", "c"], &[&a, &b, &[]]).unwrap(), vec![2, 1, 0]);
+        let c = ["a".to_owned()];
+        assert_eq!(graph_levels(&["a", "b", "c"], &[&a, &b, &c]).unwrap_err(), vec!["a", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn accepts_a_lone_value_for_lists_and_a_single_item_for_scalars() {
+        let yaml = &YamlLoader::load_from_str("name: exp\ncommand: [echo hi]\ntags: slow\ncpus: 2\n").unwrap()[0];
+        let exp = Experiment::from_yaml(yaml).unwrap();
+        assert_eq!(exp.command, "echo hi");
+        assert_eq!(exp.tags, vec!["slow"]);
+        assert_eq!(exp.cpus, vec![2]);
+
+        let yaml = &YamlLoader::load_from_str("name: exp\ncommand: [echo, hi]\n").unwrap()[0];
+        assert!(matches!(Experiment::from_yaml(yaml), Err(ParsingError::InvalidField("command"))));
+    }
+
+    #[test]
+    fn parses_cgroup_limits_and_writes_them_back() {
+        let yaml = &YamlLoader::load_from_str("name: exp\ncommand: run\nmemory_limit: 512M\ncpu_limit: 1.5\n").unwrap()[0];
+        let exp = Experiment::from_yaml(yaml).unwrap();
+        assert_eq!((exp.memory_limit, exp.cpu_limit), (Some(512 * 1024 * 1024), Some(1.5)));
+        let again = Experiment::from_yaml(&exp.to_yaml()).unwrap();
+        assert_eq!((again.memory_limit, again.cpu_limit), (exp.memory_limit, exp.cpu_limit));
+
+        let yaml = &YamlLoader::load_from_str("name: exp\ncommand: run\ncpu_limit: 0\n").unwrap()[0];
+        assert!(matches!(Experiment::from_yaml(yaml), Err(ParsingError::InvalidField("cpu_limit"))));
+    }
+
+    #[test]
+    fn prints_the_params_of_scripts_to_read_them_back() {
+        let config = "name: exp\nscript: echo $REPLIKATE_PARAM_RUNS\nparams:\n  runs: 3\n  pattern: \"a{b}\"\n\
+            check: grep {pattern} out-{runs}.log\ngolden: expected-{runs}.txt\n";
+        let exp = Experiment::from_yaml(&YamlLoader::load_from_str(config).unwrap()[0]).unwrap();
+        let mut printed = String::new();
+        YamlEmitter::new(&mut printed).dump(&exp.to_yaml()).unwrap();
+        let again = Experiment::from_yaml(&YamlLoader::load_from_str(&printed).unwrap()[0]).unwrap();
+        assert_eq!(again.params, exp.params);
+        assert_eq!(again.script, exp.script);
+        assert_eq!(again.check.as_deref(), Some("grep a{b} out-3.log"));
+        assert_eq!(again.golden.map(|golden| golden.path), Some("expected-3.txt".to_owned()));
+    }
+
+    #[test]
+    fn tells_local_paths_from_remote_urls() {
+        for path in ["/home/me/solver", "../solver", "solver", "./dir:with/colon"] {
+            assert!(is_path(path), "{}", path);
+        }
+        for url in ["https://github.com/owner/solver.git", "git@github.com:owner/solver.git", "file:///srv/solver"] {
+            assert!(!is_path(url), "{}", url);
+        }
+
+        let yaml = &YamlLoader::load_from_str("url: ../solver\nlocal: false\n").unwrap()[0];
+        let repository = Repository::from_yaml(yaml).unwrap();
+        assert!(!repository.local);
+        assert!(!Repository::from_yaml(&repository.to_yaml()).unwrap().local);
+
+        let yaml = &YamlLoader::load_from_str("url: ../solver\ncommit: v1.0\n").unwrap()[0];
+        let repository = Repository::from_yaml(yaml).unwrap();
+        assert!(!repository.local);
+        let yaml = &YamlLoader::load_from_str("url: ../solver\ncommit: v1.0\nlocal: true\n").unwrap()[0];
+        assert!(Repository::from_yaml(&Repository::from_yaml(yaml).unwrap().to_yaml()).unwrap().local);
+    }
+
+    #[test]
+    fn resolves_repository_paths_from_the_config_folder() {
+        let config = "repositories:\n  - url: ../solver\n  - url: /srv/tool\n  - url: https://github.com/owner/lib.git\n";
+        let project = Project::from_config(&YamlLoader::load_from_str(config).unwrap()[0], Path::new("configs")).unwrap();
+        let urls = project.repositories.iter().map(|repository| repository.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls, vec!["configs/../solver", "/srv/tool", "https://github.com/owner/lib.git"]);
+        assert_eq!(project.repositories[0].name, "solver");
+    }
+
+    #[test]
+    fn checks_the_group_of_experiments() {
+        let config = "groups:\n  db:\n    setup: start-db\nexperiments:\n  - name: a\n    command: run\n    group: db\n";
+        let project = Project::from_yaml(&YamlLoader::load_from_str(config).unwrap()[0]).unwrap();
+        assert_eq!(project.groups["db"].setup, vec!["start-db"]);
+        assert!(project.groups["db"].teardown.is_empty());
+        assert_eq!(Project::from_yaml(&project.to_yaml()).unwrap().experiments[0].group.as_deref(), Some("db"));
+
+        let config = config.replace("group: db", "group: cache");
+        assert!(matches!(
+            Project::from_yaml(&YamlLoader::load_from_str(&config).unwrap()[0]),
+            Err(ParsingError::UnknownGroup(exp, group)) if exp == "a" && group == "cache"
+        ));
+    }
+
+    #[test]
+    fn expands_for_each_into_one_experiment_per_file() {
+        let dir = std::env::temp_dir().join(format!("replikate-for-each-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("inputs")).unwrap();
+        for file in ["b.txt", "a.txt", "a.csv"] {
+            std::fs::write(dir.join("inputs").join(file), "").unwrap();
+        }
+
+        let items = YamlLoader::load_from_str("- name: count\n  for_each: inputs/*.txt\n  command: wc -l {file}\n").unwrap();
+        let experiments = for_each_expanded(items[0].as_vec().unwrap(), &dir, &mut BTreeMap::new()).unwrap();
+        let names = experiments.iter().map(|exp| exp.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["count-a", "count-b"]);
+        let file = std::path::absolute(dir.join("inputs/a.txt")).unwrap();
+        assert_eq!(experiments[0].command, format!("wc -l {}", file.display()));
+
+        let items = YamlLoader::load_from_str("- name: count\n  for_each: inputs/a.*\n  command: wc -l {file}\n").unwrap();
+        assert!(matches!(
+            for_each_expanded(items[0].as_vec().unwrap(), &dir, &mut BTreeMap::new()),
+            Err(ParsingError::ForEachCollision(exp, stem)) if exp == "count" && stem == "a"
+        ));
+        let items = YamlLoader::load_from_str("- name: count\n  for_each: inputs/*.json\n  command: wc -l {file}\n").unwrap();
+        assert!(matches!(for_each_expanded(items[0].as_vec().unwrap(), &dir, &mut BTreeMap::new()), Err(ParsingError::EmptyForEach(_, _))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn depends_on_every_expansion_of_a_for_each() {
+        let dir = std::env::temp_dir().join(format!("replikate-for-each-deps-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("inputs")).unwrap();
+        for file in ["a.txt", "b.txt"] {
+            std::fs::write(dir.join("inputs").join(file), "").unwrap();
+        }
+
+        let config = "experiments:\n  - name: count\n    for_each: inputs/*.txt\n    command: wc -l {file}\n  \
+            - name: total\n    command: sum\n    depends_on: count\n";
+        let project = Project::from_config(&YamlLoader::load_from_str(config).unwrap()[0], &dir).unwrap();
+        assert_eq!(project.experiments[2].depends_on, vec!["count-a", "count-b"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resolves_the_variables_of_included_experiments() {
+        let dir = std::env::temp_dir().join(format!("replikate-included-vars-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("experiments")).unwrap();
+        std::fs::write(dir.join("experiments/bench.yml"), "- name: bench\n  command: ${tool:solver} --runs ${runs}\n").unwrap();
+
+        let config = "vars:\n  runs: 3\ntools:\n  solver: /opt/solver\nexperiments_from: experiments/*.yml\n";
+        let project = Project::from_config(&YamlLoader::load_from_str(config).unwrap()[0], &dir).unwrap();
+        assert_eq!(project.experiments[0].command, "/opt/solver --runs 3");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn quotes_the_for_each_paths_filling_commands() {
+        let dir = std::env::temp_dir().join(format!("replikate-for-each-quotes-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("it's here")).unwrap();
+        std::fs::write(dir.join("it's here/c d.txt"), "").unwrap();
+        let items = YamlLoader::load_from_str("- name: count\n  for_each: it's here/*.txt\n  command: wc -l {file}\n  golden: \"{file}.expected\"\n").unwrap();
+        let experiments = for_each_expanded(items[0].as_vec().unwrap(), &dir, &mut BTreeMap::new()).unwrap();
+        let path = std::path::absolute(dir.join("it's here/c d.txt")).unwrap().to_string_lossy().into_owned();
+        assert_eq!(split_words(&experiments[0].command), Some(vec!["wc".to_owned(), "-l".to_owned(), path.to_owned()]));
+        assert_eq!(experiments[0].params["file"], path);
+        assert_eq!(experiments[0].golden.as_ref().map(|golden| golden.path.to_owned()), Some(format!("{}.expected", path)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, atomic::{AtomicBool, Ordering}},
+};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// How many warnings of each kind were raised so far.
+static RAISED: Mutex<BTreeMap<WarningKind, usize>> = Mutex::new(BTreeMap::new());
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// `println!` that stays silent when replikate runs with `--quiet`.
+#[macro_export]
+macro_rules! info {
+    ($($arg: tt)*) => {
+        if !$crate::output::is_quiet() {
+            println!($($arg)*);
+        }
+    }
+}
+
+/// Prints a `Warning:` line as `info!` does and records it under the `WarningKind` variant named
+/// first, as in `warning!(NoOutput, "...", ...)`.
+#[macro_export]
+macro_rules! warning {
+    ($kind: ident, $($arg: tt)*) => {
+        $crate::output::warn($crate::output::WarningKind::$kind, format!($($arg)*))
+    }
+}
+
+/// What a warning is about, by which `--fail-on-warning` makes some of them fatal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WarningKind {
+    /// A setting of the configuration does not apply and is ignored.
+    IgnoredSetting,
+    /// A feature replikate was asked for is not available on this system.
+    UnsupportedPlatform,
+    /// The `memory_limit` or `cpu_limit` of an experiment could not be enforced.
+    UnenforcedLimits,
+    UnknownDiskSpace,
+    /// An experiment appears in several merged reports.
+    DuplicateResult,
+    OptionalRequirement,
+    NoInstallCommand,
+    TeardownFailed,
+    /// Experiments missing from the report given to `--replay`.
+    ReplayMismatch,
+    DeadlineReached,
+    SlowExperiment,
+    /// An experiment succeeded without any output.
+    NoOutput,
+    LeftoverProcesses,
+    /// The `result_file` of an experiment could not be read or recorded.
+    UnreadResults,
+    /// A declared output was not produced.
+    MissingOutput,
+    /// The result or the logs of an experiment could not be written as it ended, as into
+    /// `result.json`, `summary.tsv` or its tables.
+    UnflushedResult,
+    /// A table of the logs written with another header was moved aside to start over.
+    RotatedTable,
+}
+
+impl WarningKind {
+    pub const ALL: &'static [WarningKind] = &[
+        WarningKind::IgnoredSetting,
+        WarningKind::UnsupportedPlatform,
+        WarningKind::UnenforcedLimits,
+        WarningKind::UnknownDiskSpace,
+        WarningKind::DuplicateResult,
+        WarningKind::OptionalRequirement,
+        WarningKind::NoInstallCommand,
+        WarningKind::TeardownFailed,
+        WarningKind::ReplayMismatch,
+        WarningKind::DeadlineReached,
+        WarningKind::SlowExperiment,
+        WarningKind::NoOutput,
+        WarningKind::LeftoverProcesses,
+        WarningKind::UnreadResults,
+        WarningKind::MissingOutput,
+        WarningKind::UnflushedResult,
+        WarningKind::RotatedTable,
+    ];
+
+    /// Name given to `--fail-on-warning`.
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningKind::IgnoredSetting => "ignored-setting",
+            WarningKind::UnsupportedPlatform => "unsupported-platform",
+            WarningKind::UnenforcedLimits => "unenforced-limits",
+            WarningKind::UnknownDiskSpace => "unknown-disk-space",
+            WarningKind::DuplicateResult => "duplicate-result",
+            WarningKind::OptionalRequirement => "optional-requirement",
+            WarningKind::NoInstallCommand => "no-install-command",
+            WarningKind::TeardownFailed => "teardown-failed",
+            WarningKind::ReplayMismatch => "replay-mismatch",
+            WarningKind::DeadlineReached => "deadline-reached",
+            WarningKind::SlowExperiment => "slow-experiment",
+            WarningKind::NoOutput => "no-output",
+            WarningKind::LeftoverProcesses => "leftover-processes",
+            WarningKind::UnreadResults => "unread-results",
+            WarningKind::MissingOutput => "missing-output",
+            WarningKind::UnflushedResult => "unflushed-result",
+            WarningKind::RotatedTable => "rotated-table",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<WarningKind> {
+        WarningKind::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+}
+
+/// Prints `message` as a warning of `kind`, and counts it even under `--quiet`.
+pub fn warn(kind: WarningKind, message: String) {
+    *RAISED.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).entry(kind).or_insert(0) += 1;
+    info!("Warning: {}", message);
+}
+
+/// Warnings raised so far, counted by kind.
+pub fn raised_warnings() -> BTreeMap<WarningKind, usize> {
+    RAISED.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
@@ -0,0 +1,52 @@
+use std::{
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C set a flag read with `interrupted` instead of killing replikate, so it can stop
+/// what it runs and clean up first.
+#[cfg(unix)]
+pub fn catch_interrupt() {
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    // SAFETY: the handler only stores into an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, on_interrupt as *const () as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn catch_interrupt() {}
+
+/// Whether Ctrl-C was pressed since `catch_interrupt`.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Starts `command` in a process group of its own, so the processes it forks and detaches can be
+/// killed with it by `kill_group`. They no longer receive Ctrl-C from the terminal.
+#[cfg(unix)]
+pub fn isolate(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+#[cfg(not(unix))]
+pub fn isolate(_command: &mut Command) {}
+
+/// Kills every process left in the group of the isolated process `leader`, and returns whether
+/// there was any.
+#[cfg(unix)]
+pub fn kill_group(leader: u32) -> bool {
+    // SAFETY: killpg has no memory effect, it fails when the group no longer exists.
+    unsafe { libc::killpg(leader as libc::pid_t, libc::SIGKILL) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn kill_group(_leader: u32) -> bool {
+    false
+}
@@ -0,0 +1,73 @@
+use std::{
+    io,
+    process::{Child, ExitStatus},
+    time::Duration,
+};
+
+/// Whether `--profile` can measure experiments on this platform.
+pub const PROFILING_SUPPORTED: bool = cfg!(unix);
+
+#[derive(Clone, Copy, Debug)]
+pub struct ResourceUsage {
+    /// Peak resident set size, in kilobytes.
+    pub max_rss_kb: u64,
+    pub user_time: Duration,
+    pub system_time: Duration,
+}
+
+impl ResourceUsage {
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+}
+
+/// Reaps `child` if it exited, returning its exit status and the resources it used.
+pub fn try_wait_with_usage(child: &Child) -> io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    wait4(child, true)
+}
+
+/// Blocks until `child` exits, then reaps it like `try_wait_with_usage`.
+pub fn wait_with_usage(child: &Child) -> io::Result<(ExitStatus, ResourceUsage)> {
+    wait4(child, false).map(|exited| exited.expect("a blocking wait4 returns once the child exited"))
+}
+
+#[cfg(unix)]
+fn wait4(child: &Child, no_hang: bool) -> io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    use std::{mem::MaybeUninit, os::unix::process::ExitStatusExt};
+
+    let mut status = 0;
+    let mut usage = MaybeUninit::<libc::rusage>::zeroed();
+    loop {
+        // SAFETY: `status` and `usage` outlive the call, which only writes into them.
+        let pid = unsafe {
+            libc::wait4(child.id() as libc::pid_t, &mut status, if no_hang { libc::WNOHANG } else { 0 }, usage.as_mut_ptr())
+        };
+        match pid {
+            0 => return Ok(None),
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            _ => break,
+        }
+    }
+
+    // SAFETY: filled in by the successful `wait4` above.
+    let usage = unsafe { usage.assume_init() };
+    let time = |time: libc::timeval| Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64);
+    // macOS reports the peak resident set size in bytes, other Unixes in kilobytes.
+    let max_rss_kb = if cfg!(target_os = "macos") { usage.ru_maxrss as u64 / 1024 } else { usage.ru_maxrss as u64 };
+
+    Ok(Some((ExitStatus::from_raw(status), ResourceUsage {
+        max_rss_kb,
+        user_time: time(usage.ru_utime),
+        system_time: time(usage.ru_stime),
+    })))
+}
+
+#[cfg(not(unix))]
+fn wait4(_child: &Child, _no_hang: bool) -> io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    Err(io::Error::new(io::ErrorKind::Other, "resource usage is only available on Unix"))
+}
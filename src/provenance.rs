@@ -0,0 +1,68 @@
+use std::{
+    env::consts,
+    fs::read_to_string,
+    thread::available_parallelism,
+};
+use crate::{
+    git::resolved_commit,
+    json::Json,
+    model::Project,
+};
+
+/// Describes the machine and sources producing a run: OS, kernel, hostname, CPU, replikate
+/// version and the commit checked out in each repository. Values that cannot be determined are
+/// `null`.
+pub fn provenance(p: &Project) -> Json {
+    let (kernel, hostname) = uname();
+    let src = p.src_path();
+    let repositories = p.repositories.iter()
+        .map(|repository| Json::object(vec![
+            ("name", Json::string(&repository.name)),
+            ("url", Json::string(&repository.url)),
+            ("commit", Json::optional(repository.commit.as_deref(), Json::string)),
+            ("resolved_commit", Json::optional(resolved_commit(p, &src.join(&repository.name)), Json::String)),
+        ]))
+        .collect();
+
+    Json::object(vec![
+        ("replikate_version", Json::string(env!("CARGO_PKG_VERSION"))),
+        ("os", Json::string(consts::OS)),
+        ("arch", Json::string(consts::ARCH)),
+        ("kernel", Json::optional(kernel, Json::String)),
+        ("hostname", Json::optional(hostname, Json::String)),
+        ("cpu_model", Json::optional(cpu_model(), Json::String)),
+        ("cpu_count", Json::optional(available_parallelism().ok(), |count| Json::Number(count.get() as f64))),
+        ("repositories", Json::Array(repositories)),
+    ])
+}
+
+/// Kernel release and hostname.
+#[cfg(unix)]
+fn uname() -> (Option<String>, Option<String>) {
+    use std::{ffi::CStr, mem::MaybeUninit};
+
+    let mut names = MaybeUninit::<libc::utsname>::uninit();
+    // SAFETY: `names` is only read once filled in, its fields are NUL terminated strings.
+    unsafe {
+        if libc::uname(names.as_mut_ptr()) != 0 {
+            return (None, None);
+        }
+        let names = names.assume_init();
+        let field = |field: &[libc::c_char]| CStr::from_ptr(field.as_ptr()).to_string_lossy().into_owned();
+        (Some(field(&names.release)), Some(field(&names.nodename)))
+    }
+}
+
+#[cfg(not(unix))]
+fn uname() -> (Option<String>, Option<String>) {
+    (None, std::env::var("COMPUTERNAME").ok())
+}
+
+/// First `model name` of `/proc/cpuinfo`, only available on Linux.
+fn cpu_model() -> Option<String> {
+    read_to_string("/proc/cpuinfo").ok()?
+        .lines()
+        .find(|line| line.starts_with("model name"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, model)| model.trim().to_owned())
+}
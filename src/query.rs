@@ -0,0 +1,112 @@
+use yaml_rust::{Yaml, YamlEmitter};
+use crate::{
+    AppError,
+    model::{Project, ToYamlDocument},
+};
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    Field(String),
+    Index(usize),
+    /// Every item of a list, or every value of a mapping.
+    All,
+}
+
+/// Prints the values at `path` in the resolved configuration of `p`, as `--print-config` shows
+/// it: scalars as they are, lists and mappings as YAML, one match after the other. Fields left
+/// to their default are not part of it. Fails when nothing matches.
+pub fn print_query(p: &Project, path: &str) -> Result<(), AppError> {
+    let invalid = |reason: String| AppError::ExternalError(format!("Invalid query {}: {}.", path, reason));
+    let steps = parse(path).map_err(invalid)?;
+    let config = p.to_yaml();
+    let matches = select(&config, &steps);
+    if matches.is_empty() {
+        return Err(AppError::ExternalError(format!("Nothing matches {} in {}.", path, p.name)));
+    }
+    for value in matches {
+        println!("{}", render(value));
+    }
+    Ok(())
+}
+
+/// Steps of a dotted path such as `experiments[*].name` or `repositories[0]`.
+fn parse(path: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (index, part) in path.split('.').enumerate() {
+        let (field, mut rest) = match part.find('[') {
+            Some(bracket) => (&part[..bracket], &part[bracket..]),
+            None => (part, ""),
+        };
+        match field {
+            "" if index > 0 || rest.is_empty() => return Err(format!("empty field in '{}'", part)),
+            "" => {}
+            field => steps.push(Step::Field(field.to_owned())),
+        }
+        while !rest.is_empty() {
+            let end = rest.find(']').filter(|_| rest.starts_with('[')).ok_or_else(|| format!("unclosed index in '{}'", part))?;
+            steps.push(match &rest[1..end] {
+                "*" => Step::All,
+                index => Step::Index(index.parse().map_err(|_| format!("'{}' is not an index", index))?),
+            });
+            rest = &rest[end + 1..];
+        }
+    }
+    Ok(steps)
+}
+
+fn select<'a>(yaml: &'a Yaml, steps: &[Step]) -> Vec<&'a Yaml> {
+    let (step, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => return vec![yaml],
+    };
+    let next: Vec<&Yaml> = match (step, yaml) {
+        (Step::Field(field), Yaml::Hash(hash)) => hash.get(&Yaml::String(field.to_owned())).into_iter().collect(),
+        (Step::Index(index), Yaml::Array(items)) => items.get(*index).into_iter().collect(),
+        (Step::All, Yaml::Array(items)) => items.iter().collect(),
+        (Step::All, Yaml::Hash(hash)) => hash.values().collect(),
+        _ => Vec::new(),
+    };
+    next.into_iter().flat_map(|value| select(value, rest)).collect()
+}
+
+fn render(value: &Yaml) -> String {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => s.to_owned(),
+        Yaml::Integer(n) => n.to_string(),
+        Yaml::Boolean(b) => b.to_string(),
+        Yaml::Null => "null".to_owned(),
+        value => {
+            let mut output = String::new();
+            // Only fails on writing to the string, which cannot happen.
+            let _ = YamlEmitter::new(&mut output).dump(value);
+            output.trim_start_matches("---").trim_start_matches('\n').to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yaml_rust::{Yaml, YamlLoader};
+    use super::{Step, parse, select};
+
+    #[test]
+    fn parses_fields_indexes_and_wildcards() {
+        assert_eq!(parse("experiments[*].name").unwrap(), vec![
+            Step::Field("experiments".to_owned()), Step::All, Step::Field("name".to_owned()),
+        ]);
+        assert_eq!(parse("[0][1]").unwrap(), vec![Step::Index(0), Step::Index(1)]);
+        assert!(parse("experiments.").is_err());
+        assert!(parse("experiments[x]").is_err());
+        assert!(parse("experiments[0").is_err());
+    }
+
+    #[test]
+    fn selects_every_match_in_order() {
+        let yaml = &YamlLoader::load_from_str("name: p\nexperiments:\n  - name: a\n    timeout: 3\n  - name: b\n").unwrap()[0];
+        assert_eq!(select(yaml, &parse("experiments[*].name").unwrap()), vec![&Yaml::String("a".to_owned()), &Yaml::String("b".to_owned())]);
+        assert_eq!(select(yaml, &parse("experiments[*].timeout").unwrap()), vec![&Yaml::Integer(3)]);
+        assert_eq!(select(yaml, &parse("experiments[1].name").unwrap()), vec![&Yaml::String("b".to_owned())]);
+        assert!(select(yaml, &parse("experiments[2]").unwrap()).is_empty());
+        assert!(select(yaml, &parse("name.first").unwrap()).is_empty());
+    }
+}
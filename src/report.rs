@@ -0,0 +1,287 @@
+use std::{
+    fs::{File, read_to_string, rename},
+    io::Write,
+    path::Path,
+};
+use crate::{
+    warning,
+    AppError,
+    execute::ExperimentResult,
+    json::Json,
+    model::{CONFIG_VERSION, Project},
+    provenance::provenance,
+};
+use std::sync::Arc;
+
+/// Writes a JSON report with a `provenance` header describing where the run happened, followed
+/// by one entry per experiment. `shard` is the `--shard` index and count the run was limited to.
+/// The `start_order` of the experiments that ran tells in which order they started, from 0, for
+/// `--replay`.
+pub fn write_report(path: &str, p: &Project, results: &[ExperimentResult], shard: Option<(usize, usize)>) -> Result<(), AppError> {
+    let mut started = results.iter().enumerate()
+        .filter_map(|(index, result)| result.slot.map(|slot| (slot.start, index)))
+        .collect::<Vec<_>>();
+    started.sort();
+    let start_order = |index: usize| started.iter().position(|(_, other)| *other == index);
+
+    let experiments = results.iter()
+        .enumerate()
+        .map(|(index, result)| experiment_json(result, start_order(index)))
+        .collect();
+
+    let report = Json::object(vec![
+        ("project", Json::string(&p.name)),
+        ("config_version", Json::Number(p.version.unwrap_or(CONFIG_VERSION) as f64)),
+        // As a string, JSON numbers cannot hold every 64 bits seed exactly.
+        ("seed", Json::optional(p.seed, |seed| Json::String(seed.to_string()))),
+        ("shard", Json::optional(shard, |(index, count)| Json::string(&format!("{}/{}", index, count)))),
+        ("run_id", Json::optional(p.run_id.as_ref(), |run_id| Json::string(run_id))),
+        ("provenance", provenance(p)),
+        ("experiments", Json::Array(experiments)),
+    ]);
+    write_json(path, &report)
+}
+
+/// Writes the report entry of a single experiment to `path`, as soon as it finished. It has no
+/// `start_order`, only known once the whole run ended.
+pub fn write_result(path: &Path, result: &ExperimentResult) -> Result<(), AppError> {
+    write_json(&path.to_string_lossy(), &experiment_json(result, None))
+}
+
+/// Report entry of `result`, the `start_order`-th experiment to start.
+fn experiment_json(result: &ExperimentResult, start_order: Option<usize>) -> Json {
+    Json::object(vec![
+        ("name", Json::string(&result.name)),
+        ("command", Json::string(&result.command)),
+        ("params", Json::object(result.params.iter().map(|(name, value)| (name.as_str(), Json::string(value))).collect())),
+        ("script", Json::optional(result.script.as_ref(), |script| Json::string(script))),
+        ("start", Json::string(&result.start.to_rfc3339())),
+        ("start_order", Json::optional(start_order, |order| Json::Number(order as f64))),
+        ("duration_ms", Json::Number(result.duration.as_millis() as f64)),
+        ("status", Json::optional(result.status, |code| Json::Number(code as f64))),
+        ("outcome", Json::string(result.outcome.kind())),
+        ("outcome_message", Json::string(&result.outcome.to_string())),
+        ("stdin", Json::Bool(result.stdin)),
+        ("capture", Json::string(result.capture.name())),
+        ("attempts", Json::Number(result.attempts as f64)),
+        ("nice", Json::optional(result.nice, |nice| Json::Number(nice as f64))),
+        ("cpus", Json::Array(result.cpus.iter().map(|core| Json::Number(*core as f64)).collect())),
+        ("no_output", Json::Bool(result.no_output)),
+        ("leftovers_killed", Json::Bool(result.leftovers_killed)),
+        ("slow", Json::Bool(result.slow.is_some())),
+        ("limits_hit", Json::Array(result.limits_hit.iter().map(|limit| Json::string(limit)).collect())),
+        ("metrics", Json::Object(result.metrics.iter().map(|(name, value)| (name.to_owned(), value.clone())).collect())),
+        ("max_rss_kb", Json::optional(result.usage, |usage| Json::Number(usage.max_rss_kb as f64))),
+        ("cpu_time_ms", Json::optional(result.usage, |usage| Json::Number(usage.cpu_time().as_millis() as f64))),
+        ("peak_disk_bytes", Json::optional(result.disk, |disk| Json::Number(disk.peak as f64))),
+        ("final_disk_bytes", Json::optional(result.disk, |disk| Json::Number(disk.last as f64))),
+        ("success", Json::Bool(result.success())),
+        ("allow_failure", Json::Bool(result.allow_failure)),
+    ])
+}
+
+/// Combines the reports of the shards or roots of a run into one at `path`. Its `shards` list
+/// the shard and provenance of each report, followed by all their experiments and a `summary`
+/// counting them by outcome. The reports must come from the same project and config version.
+/// Experiments reported more than once, as by several `--out-each` roots, are kept and marked
+/// `duplicate`. The merged report keeps their `run_id` when they all share it.
+pub fn merge_reports(path: &str, reports: &[String]) -> Result<(), AppError> {
+    let mut project = None;
+    let mut config_version: Option<(f64, &str)> = None;
+    let mut seed = Json::Null;
+    let mut run_ids = Vec::new();
+    let mut shards = Vec::new();
+    let mut experiments: Vec<Json> = Vec::new();
+
+    for report_path in reports {
+        let report = read_report(report_path)?;
+        let invalid = || AppError::ExternalError(format!("{} is not a replikate report.", report_path));
+
+        let name = report.get("project").ok_or_else(invalid)?;
+        match &project {
+            Some(project) if project != name => return Err(AppError::ExternalError(format!(
+                "{} reports on project {} while the previous reports are about {}.", report_path, name, project
+            ))),
+            _ => project = Some(name.clone()),
+        }
+        // Reports written before the config version was recorded match any version.
+        if let Some(version) = report.get("config_version").and_then(Json::as_f64) {
+            match config_version {
+                Some((expected, first)) if expected != version => return Err(AppError::ExternalError(format!(
+                    "{} comes from a version {} config while {} comes from a version {} one.", report_path, version, first, expected
+                ))),
+                _ => config_version = Some((version, report_path)),
+            }
+        }
+        seed = report.get("seed").cloned().unwrap_or(Json::Null);
+        let run_id = report.get("run_id").cloned().unwrap_or(Json::Null);
+        if !run_ids.contains(&run_id) {
+            run_ids.push(run_id);
+        }
+        shards.push(Json::object(vec![
+            ("report", Json::string(report_path)),
+            ("shard", report.get("shard").cloned().unwrap_or(Json::Null)),
+            ("provenance", report.get("provenance").cloned().unwrap_or(Json::Null)),
+        ]));
+
+        match report.get("experiments") {
+            Some(Json::Array(entries)) => for entry in entries {
+                entry.get("name").ok_or_else(invalid)?;
+                let mut entry = entry.clone();
+                if let Json::Object(fields) = &mut entry {
+                    fields.push(("report".to_owned(), Json::string(report_path)));
+                }
+                experiments.push(entry);
+            },
+            _ => return Err(invalid()),
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for entry in &experiments {
+        let name = entry.get("name").and_then(Json::as_str).unwrap_or_default();
+        if experiments.iter().filter(|other| other.get("name") == entry.get("name")).count() > 1 && !duplicates.contains(&name) {
+            duplicates.push(name);
+        }
+    }
+    if !duplicates.is_empty() {
+        warning!(DuplicateResult, "{} reported more than once, all their results are kept", duplicates.join(", "));
+    }
+    let duplicates = duplicates.into_iter().map(str::to_owned).collect::<Vec<_>>();
+    for entry in &mut experiments {
+        let duplicate = entry.get("name").and_then(Json::as_str).map(|name| duplicates.iter().any(|other| other == name));
+        if let (Json::Object(fields), Some(true)) = (&mut *entry, duplicate) {
+            fields.push(("duplicate".to_owned(), Json::Bool(true)));
+        }
+    }
+
+    let merged = Json::Object(vec![
+        ("project".to_owned(), project.unwrap_or(Json::Null)),
+        ("config_version".to_owned(), Json::optional(config_version, |(version, _)| Json::Number(version))),
+        ("seed".to_owned(), seed),
+        ("run_id".to_owned(), if run_ids.len() == 1 { run_ids.remove(0) } else { Json::Null }),
+        ("shards".to_owned(), Json::Array(shards)),
+        ("summary".to_owned(), summary(&experiments, &duplicates)),
+        ("experiments".to_owned(), Json::Array(experiments)),
+    ]);
+    write_json(path, &merged)
+}
+
+/// Number of `experiments` per outcome and their total duration.
+fn summary(experiments: &[Json], duplicates: &[String]) -> Json {
+    let mut outcomes: Vec<(String, Json)> = Vec::new();
+    for entry in experiments {
+        // Only reports written before outcomes were recorded lack them.
+        let outcome = match (entry.get("outcome").and_then(Json::as_str), entry.get("success")) {
+            (Some(outcome), _) => outcome,
+            (None, Some(Json::Bool(true))) => "succeeded",
+            (None, _) => "failed",
+        };
+        match outcomes.iter_mut().find(|(known, _)| known == outcome) {
+            Some((_, Json::Number(count))) => *count += 1.0,
+            _ => outcomes.push((outcome.to_owned(), Json::Number(1.0))),
+        }
+    }
+    let duration = experiments.iter()
+        .filter_map(|entry| entry.get("duration_ms").and_then(Json::as_f64))
+        .sum();
+
+    Json::object(vec![
+        ("experiments", Json::Number(experiments.len() as f64)),
+        ("outcomes", Json::Object(outcomes)),
+        ("duration_ms", Json::Number(duration)),
+        ("duplicates", Json::Array(duplicates.iter().map(|name| Json::string(name)).collect())),
+    ])
+}
+
+/// Names of the experiments of the report at `path` in the order they started, for `--replay`.
+pub fn recorded_order(path: &str) -> Result<Vec<String>, AppError> {
+    let report = read_report(path)?;
+    let mut started = match report.get("experiments") {
+        Some(Json::Array(entries)) => entries.iter()
+            .filter_map(|entry| Some((entry.get("start_order")?.as_f64()?, entry.get("name")?.as_str()?.to_owned())))
+            .collect::<Vec<_>>(),
+        _ => Vec::new(),
+    };
+    if started.is_empty() {
+        return Err(AppError::ExternalError(format!("{} records no start order to replay.", path)));
+    }
+    started.sort_by(|(order, _), (other, _)| order.total_cmp(other));
+    Ok(started.into_iter().map(|(_, name)| name).collect())
+}
+
+/// Reads a report written by `write_report` or `merge_reports`.
+pub fn read_report(path: &str) -> Result<Json, AppError> {
+    let content = read_to_string(path)
+        .map_err(|e| AppError::IOError(path.to_owned(), Arc::new(e)))?;
+    let report = Json::parse(&content)
+        .map_err(|err| AppError::ExternalError(format!("{} is not valid JSON: {}.", path, err)))?;
+    match report.get("experiments") {
+        Some(Json::Array(_)) => Ok(report),
+        _ => Err(AppError::ExternalError(format!("{} is not a replikate report.", path))),
+    }
+}
+
+/// Writes `document` into a temporary file next to `path`, then renames it over `path`: a run
+/// killed meanwhile leaves the previous file whole rather than a truncated one.
+fn write_json(path: &str, document: &Json) -> Result<(), AppError> {
+    let temporary = format!("{}.tmp", path);
+    File::create(&temporary)
+        .and_then(|mut file| {
+            writeln!(file, "{}", document)?;
+            file.sync_all()
+        })
+        .and_then(|_| rename(&temporary, path))
+        .map_err(|e| AppError::IOError(path.to_owned(), Arc::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use crate::{AppError, json::Json};
+    use super::{merge_reports, read_report};
+
+    #[test]
+    fn merges_reports_of_one_config_version_flagging_duplicates() {
+        let dir = std::env::temp_dir().join(format!("replikate-merge-{}", std::process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        let report = |name: &str, config_version: u32, experiments: &str| {
+            let path = dir.join(name).to_str().unwrap().to_owned();
+            write(&path, format!(
+                "{{\"project\": \"bench\", \"config_version\": {}, \"run_id\": \"r1\", \"experiments\": [{}]}}", config_version, experiments
+            )).unwrap();
+            path
+        };
+        let first = report("first.json", 2, r#"{"name": "a", "outcome": "succeeded", "duration_ms": 10}"#);
+        let second = report("second.json", 2, r#"{"name": "a", "outcome": "failed", "duration_ms": 20},
+            {"name": "b", "outcome": "succeeded", "duration_ms": 5}"#);
+        let other_version = report("other.json", 3, r#"{"name": "c", "outcome": "succeeded"}"#);
+        let merged = dir.join("merged.json").to_str().unwrap().to_owned();
+
+        match merge_reports(&merged, &[first.clone(), other_version]) {
+            Err(AppError::ExternalError(message)) => assert!(message.contains("version 3 config"), "{}", message),
+            _ => panic!("reports of different config versions were merged"),
+        }
+
+        merge_reports(&merged, &[first, second]).unwrap();
+        let merged = read_report(&merged).unwrap();
+        assert_eq!(merged.get("run_id").and_then(Json::as_str), Some("r1"));
+        let duplicate = match merged.get("experiments") {
+            Some(Json::Array(entries)) => entries.iter()
+                .map(|entry| (entry.get("name").and_then(Json::as_str).unwrap(), entry.get("duplicate").is_some()))
+                .collect::<Vec<_>>(),
+            _ => panic!("the merged report lists no experiments"),
+        };
+        assert_eq!(duplicate, vec![("a", true), ("a", true), ("b", false)]);
+
+        let summary = merged.get("summary").unwrap();
+        assert_eq!(summary.get("experiments").and_then(Json::as_f64), Some(3.0));
+        let outcomes = summary.get("outcomes").unwrap();
+        assert_eq!(outcomes.get("succeeded").and_then(Json::as_f64), Some(2.0));
+        assert_eq!(outcomes.get("failed").and_then(Json::as_f64), Some(1.0));
+        assert_eq!(summary.get("duration_ms").and_then(Json::as_f64), Some(35.0));
+        assert_eq!(summary.get("duplicates"), Some(&Json::Array(vec![Json::string("a")])));
+        remove_dir_all(&dir).unwrap();
+    }
+}
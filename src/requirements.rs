@@ -0,0 +1,345 @@
+use std::{
+    collections::BTreeMap,
+    fs::create_dir_all,
+    path::Path,
+    process::{Command, Stdio},
+};
+use crate::{
+    info,
+    warning,
+    AppError,
+    hooks::run_hook,
+    json::Json,
+    model::{Project, Requirement, program_exists},
+    trace::traced_output,
+    version::parse_version,
+};
+use std::sync::Arc;
+
+/// A requirement is available when its program, or the program of one of its alternatives, is
+/// on the `PATH` or at its explicit `path`.
+pub fn is_available(requirement: &Requirement, project_path: &Path) -> bool {
+    if requirement.any_of.is_empty() {
+        program_exists(requirement.program(), project_path)
+    } else {
+        requirement.any_of.iter().any(|alternative| program_exists(alternative.program(), project_path))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RequirementStatus {
+    /// The detected version is at least the declared one.
+    Satisfied(String),
+    /// The detected version is older than the declared one.
+    Mismatch(String),
+    /// The program is available but `<name> --version` reported no recognizable version.
+    Unknown,
+    Missing,
+}
+
+impl RequirementStatus {
+    /// Name of the status in the requirements report.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RequirementStatus::Satisfied(_) => "ok",
+            RequirementStatus::Mismatch(_) => "mismatch",
+            RequirementStatus::Unknown => "unknown",
+            RequirementStatus::Missing => "missing",
+        }
+    }
+
+    /// Version detected, if the program was found and reported one.
+    pub fn detected(&self) -> Option<&str> {
+        match self {
+            RequirementStatus::Satisfied(found) | RequirementStatus::Mismatch(found) => Some(found),
+            RequirementStatus::Unknown | RequirementStatus::Missing => None,
+        }
+    }
+
+    /// Orders statuses from the least to the most usable, to pick the best alternative.
+    fn rank(&self) -> u8 {
+        match self {
+            RequirementStatus::Missing => 0,
+            RequirementStatus::Mismatch(_) => 1,
+            RequirementStatus::Unknown => 2,
+            RequirementStatus::Satisfied(_) => 3,
+        }
+    }
+}
+
+/// Checks `requirement`, or each of its alternatives in order, and returns the one that decided
+/// the status: the first satisfied alternative, otherwise the most usable one.
+pub fn check<'a>(requirement: &'a Requirement, project_path: &Path) -> (&'a Requirement, RequirementStatus) {
+    let mut best: Option<(&Requirement, RequirementStatus)> = None;
+    for alternative in &requirement.any_of {
+        let status = check_program(alternative, project_path);
+        if let RequirementStatus::Satisfied(_) = status {
+            return (alternative, status);
+        }
+        if best.as_ref().map(|(_, best)| status.rank() > best.rank()).unwrap_or(true) {
+            best = Some((alternative, status));
+        }
+    }
+    best.unwrap_or_else(|| (requirement, check_program(requirement, project_path)))
+}
+
+/// Detects the installed version by running `<name> --version`, the declared version being
+/// the minimum accepted one.
+fn check_program(requirement: &Requirement, project_path: &Path) -> RequirementStatus {
+    if !program_exists(requirement.program(), project_path) {
+        return RequirementStatus::Missing;
+    }
+
+    let detected = match detect_version(requirement.program()) {
+        Some(detected) => detected,
+        None => return RequirementStatus::Unknown,
+    };
+
+    let satisfied = match (parse_version(&detected), parse_version(&requirement.version)) {
+        (Some(found), Some(declared)) => found >= declared,
+        _ => detected.starts_with(&requirement.version),
+    };
+
+    if satisfied {
+        RequirementStatus::Satisfied(detected)
+    } else {
+        RequirementStatus::Mismatch(detected)
+    }
+}
+
+fn detect_version(program: &str) -> Option<String> {
+    let output = traced_output(Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null()))
+        .ok()
+        .filter(|output| output.status.success())?;
+    let text = format!("{}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+
+    let candidates = text.split_whitespace()
+        .map(|token| token.trim_matches(|c: char| !c.is_ascii_alphanumeric()))
+        .filter(|token| parse_version(token).is_some())
+        .collect::<Vec<_>>();
+
+    candidates.iter()
+        .find(|token| token.contains('.'))
+        .or_else(|| candidates.first())
+        .map(|token| token.to_string())
+}
+
+pub fn print_requirements(p: &Project) {
+    let project_path = Path::new(&p.path);
+    info!("Requirements: ");
+    for requirement in &p.requirements {
+        let (program, status) = check(requirement, project_path);
+        let found = status != RequirementStatus::Missing;
+        let status = match status {
+            RequirementStatus::Satisfied(found) => format!("found {}", found),
+            RequirementStatus::Mismatch(found) => format!("found {}, too old", found),
+            RequirementStatus::Unknown => "found, unknown version".to_owned(),
+            RequirementStatus::Missing => "missing".to_owned(),
+        };
+        if requirement.any_of.is_empty() {
+            info!("  {}, version: {} ({})", requirement.name, requirement.version, status);
+        } else if found {
+            info!("  {} (using {}, version: {}, {})", requirement.name, program.name, program.version, status);
+        } else {
+            info!("  {} ({})", requirement.name, status);
+        }
+    }
+}
+
+/// Structured form of what `print_requirements` prints, for `--report-format json`: for each
+/// requirement its declared version, the alternative checked, the detected version and the
+/// status, `ok`, `mismatch`, `unknown` or `missing`.
+pub fn requirements_json(p: &Project) -> Json {
+    let project_path = Path::new(&p.path);
+    let requirements = p.requirements.iter()
+        .map(|requirement| {
+            let (used, status) = check(requirement, project_path);
+            let alternative = !requirement.any_of.is_empty();
+            Json::object(vec![
+                ("name", Json::string(&requirement.name)),
+                ("version", Json::optional(Some(used.version.as_str()).filter(|version| !version.is_empty()), Json::string)),
+                ("using", Json::optional(Some(used.name.as_str()).filter(|_| alternative), Json::string)),
+                ("detected", Json::optional(status.detected(), Json::string)),
+                ("status", Json::string(status.name())),
+                ("required", Json::Bool(requirement.required)),
+                ("installable", Json::Bool(used.install.is_some() || requirement.install.is_some())),
+            ])
+        })
+        .collect();
+    Json::object(vec![
+        ("project", Json::string(&p.name)),
+        ("requirements", Json::Array(requirements)),
+    ])
+}
+
+/// Fails when a required requirement is missing or too old, listing all of them. Optional ones
+/// only produce a warning, unless `for_build` is set: builds then need every requirement.
+pub fn check_requirements(p: &Project, for_build: bool) -> Result<(), AppError> {
+    let project_path = Path::new(&p.path);
+    let mut unsatisfied = Vec::new();
+    for requirement in &p.requirements {
+        let problem = match check(requirement, project_path) {
+            (_, RequirementStatus::Missing) => "missing".to_owned(),
+            (used, RequirementStatus::Mismatch(found)) => format!("found {} {}, expected {}", used.name, found, used.version),
+            _ => continue,
+        };
+        if requirement.required || for_build {
+            unsatisfied.push(format!("{} ({})", requirement.name, problem));
+        } else {
+            warning!(OptionalRequirement, "optional requirement {} is not satisfied ({})", requirement.name, problem);
+        }
+    }
+
+    match (unsatisfied.is_empty(), for_build) {
+        (true, _) => Ok(()),
+        (false, false) => Err(AppError::ExternalError(format!("Unsatisfied requirements: {}.", unsatisfied.join(", ")))),
+        (false, true) => Err(AppError::ExternalError(format!(
+            "Unsatisfied requirements, --require-for-build needs them all: {}.", unsatisfied.join(", ")
+        ))),
+    }
+}
+
+/// Experiments to skip, with the reason: those that `requires` an optional requirement that is
+/// not satisfied, missing or with an older or unknown version, and those depending on them. Each
+/// gets a notice, and is reported as skipped rather than failed.
+pub fn skip_missing_optional(p: &Project) -> BTreeMap<String, String> {
+    let project_path = Path::new(&p.path);
+    let unsatisfied = p.requirements.iter()
+        .filter(|requirement| !requirement.required)
+        .filter_map(|requirement| match check(requirement, project_path) {
+            (_, RequirementStatus::Satisfied(_)) => None,
+            (_, RequirementStatus::Missing) => Some((requirement.name.as_str(), "missing".to_owned())),
+            (used, RequirementStatus::Mismatch(found)) => Some((requirement.name.as_str(), format!("found {} {}, expected {}", used.name, found, used.version))),
+            (used, RequirementStatus::Unknown) => Some((requirement.name.as_str(), format!("found {} of an unknown version", used.name))),
+        })
+        .collect::<Vec<_>>();
+
+    let mut skipped = BTreeMap::new();
+    if unsatisfied.is_empty() {
+        return skipped;
+    }
+    loop {
+        let before = skipped.len();
+        for exp in &p.experiments {
+            if skipped.contains_key(&exp.name) {
+                continue;
+            }
+            let reason = if let Some((requirement, problem)) = unsatisfied.iter().find(|(name, _)| exp.requires.iter().any(|other| other == name)) {
+                format!("its optional requirement {} is not satisfied ({})", requirement, problem)
+            } else if let Some(dependency) = exp.depends_on.iter().find(|name| skipped.contains_key(*name)) {
+                format!("its dependency {} is skipped", dependency)
+            } else {
+                continue;
+            };
+            info!("Notice: skipping {}, {}", exp.name, reason);
+            skipped.insert(exp.name.to_owned(), reason);
+        }
+        if skipped.len() == before {
+            break;
+        }
+    }
+    skipped
+}
+
+/// Runs the `install` command of every missing requirement, or of every requirement when `all`
+/// is set, logging into `logs/install/<name>.log`. Installed requirements are checked again.
+pub fn install(p: &Project, all: bool) -> Result<(), AppError> {
+    let project_path = Path::new(&p.path);
+    let install_logs = p.logs_path().join("install");
+    create_dir_all(&install_logs)
+        .map_err(|e| AppError::IOError(install_logs.to_str().unwrap().to_owned(), Arc::new(e)))?;
+
+    for requirement in &p.requirements {
+        if !all && is_available(requirement, project_path) {
+            continue;
+        }
+        let command = match &requirement.install {
+            Some(command) => command,
+            None => {
+                warning!(NoInstallCommand, "{} has no install command", requirement.name);
+                continue;
+            }
+        };
+
+        info!("Installing {}", requirement.name);
+        let log = install_logs.join(format!("{}.log", requirement.name));
+        run_hook("install", &[command.to_owned()], &[], project_path, &log)?;
+
+        if !is_available(requirement, project_path) {
+            return Err(AppError::ExternalError(format!(
+                "{} is still missing after running its install command, see {}.",
+                requirement.name, log.display()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use yaml_rust::YamlLoader;
+    use crate::{json::Json, model::Project};
+    use super::{requirements_json, skip_missing_optional};
+
+    /// Writes in `dir` a program named `name` reporting `version`.
+    fn tool(dir: &Path, name: &str, version: &str) -> std::path::PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let tool = dir.join(name);
+        std::fs::write(&tool, format!("#!/bin/sh\necho {} {}\n", name, version)).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        tool
+    }
+
+    #[test]
+    fn skips_the_experiments_of_an_optional_requirement_too_old() {
+        let dir = std::env::temp_dir().join(format!("replikate-optional-{}", std::process::id()));
+        let tool = tool(&dir, "tool", "1.2.0");
+
+        let config = format!(
+            "requirements:\n  - name: tool\n    version: \"2.0\"\n    path: {}\n    required: false\n\
+            experiments:\n  - name: a\n    command: tool\n    requires: tool\n  - name: b\n    command: run\n    depends_on: a\n  - name: c\n    command: run\n",
+            tool.display()
+        );
+        let p = Project::from_config(&YamlLoader::load_from_str(&config).unwrap()[0], Path::new(".")).unwrap();
+        let skipped = skip_missing_optional(&p);
+        assert_eq!(skipped.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(skipped["a"], "its optional requirement tool is not satisfied (found tool 1.2.0, expected 2.0)");
+        assert_eq!(skipped["b"], "its dependency a is skipped");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_the_alternative_used_and_the_version_detected() {
+        let dir = std::env::temp_dir().join(format!("replikate-requirements-json-{}", std::process::id()));
+        let tool = tool(&dir, "tool", "2.1.0");
+        let absent = dir.join("absent");
+        let config = format!(
+            "requirements:\n  - name: absent\n    version: \"1.0\"\n    path: {absent}\n\
+            \x20 - name: builder\n    any_of:\n      - name: absent\n        version: \"1.0\"\n        path: {absent}\n\
+            \x20     - name: tool\n        version: \"2.0\"\n        path: {tool}\n\
+            experiments: []\n",
+            absent = absent.display(), tool = tool.display()
+        );
+        let p = Project::from_config(&YamlLoader::load_from_str(&config).unwrap()[0], Path::new(".")).unwrap();
+        let report = requirements_json(&p);
+        let requirements = match report.get("requirements") {
+            Some(Json::Array(requirements)) => requirements,
+            _ => panic!("the report lists no requirements"),
+        };
+        let fields = |index: usize| ["name", "version", "using", "detected", "status"].iter()
+            .map(|field| requirements[index].get(field).and_then(Json::as_str))
+            .collect::<Vec<_>>();
+
+        assert_eq!(fields(0), vec![Some("absent"), Some("1.0"), None, None, Some("missing")]);
+        assert_eq!(fields(1), vec![Some("builder"), Some("2.0"), Some("tool"), Some("2.1.0"), Some("ok")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
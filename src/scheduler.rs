@@ -0,0 +1,449 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Condvar, Mutex, Weak, atomic::{AtomicBool, Ordering}},
+    thread,
+    time::Instant,
+};
+
+/// Shared flag telling tasks to stop. Cancelling prevents queued tasks from starting, running
+/// tasks are expected to poll `is_cancelled` and stop early.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<TokenState>);
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    /// Woken up on cancellation, for the workers waiting for a task to start to give up.
+    waiting: Mutex<Vec<Weak<Condvar>>>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        for waiting in self.0.waiting.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter() {
+            if let Some(waiting) = waiting.upgrade() {
+                waiting.notify_all();
+            }
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Makes `cancel` wake up the threads waiting on `condvar`.
+    fn notify_on_cancel(&self, condvar: &Arc<Condvar>) {
+        let mut waiting = self.0.waiting.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        waiting.retain(|waiting| waiting.strong_count() > 0);
+        waiting.push(Arc::downgrade(condvar));
+    }
+}
+
+pub type Task<'a, T> = Box<dyn FnOnce(&CancellationToken) -> T + Send + 'a>;
+
+/// When and on which worker a task ran.
+#[derive(Clone, Copy, Debug)]
+pub struct Slot {
+    /// Index of the worker thread, from 0.
+    pub worker: usize,
+    pub start: Instant,
+    pub end: Instant,
+}
+
+/// Amounts of named resources, such as `gpus`, held or requested.
+pub type Resources = BTreeMap<String, u64>;
+
+/// Counting semaphore capping the external processes replikate runs at once, shared by the
+/// phases. Each holder of a permit may run one process at a time.
+#[derive(Debug)]
+pub struct ProcessLimit {
+    available: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl ProcessLimit {
+    pub fn new(max: usize) -> Arc<Self> {
+        Arc::new(ProcessLimit { available: Mutex::new(max.max(1)), freed: Condvar::new() })
+    }
+
+    /// Waits for a permit, given back when dropped.
+    pub fn acquire(&self) -> ProcessPermit<'_> {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.freed.wait(available).unwrap();
+        }
+        *available -= 1;
+        ProcessPermit(self)
+    }
+}
+
+pub struct ProcessPermit<'a>(&'a ProcessLimit);
+
+impl Drop for ProcessPermit<'_> {
+    fn drop(&mut self) {
+        *self.0.available.lock().unwrap() += 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/// Runs tasks on a fixed pool of worker threads. Tasks are started in submission order, at most
+/// `workers` at a time. A task requesting resources waits until they are available, the tasks
+/// after it that fit starting meanwhile. With a process limit, a worker also needs one of its
+/// permits to start a task.
+pub struct Scheduler {
+    workers: usize,
+    token: CancellationToken,
+    /// Capacities shared by the tasks, missing resources having none.
+    resources: Resources,
+    limit: Option<Arc<ProcessLimit>>,
+    /// Tasks start exactly in submission order, none overtaking one waiting for resources.
+    strict_order: bool,
+}
+
+/// Tasks not started yet and the resources left, shared by the workers.
+struct Admission<'a, T> {
+    queue: VecDeque<(usize, Task<'a, T>, Resources)>,
+    available: Resources,
+    running: usize,
+    strict_order: bool,
+}
+
+impl<'a, T> Admission<'a, T> {
+    fn fits(&self, requests: &Resources) -> bool {
+        requests.iter().all(|(name, amount)| self.available.get(name).copied().unwrap_or(0) >= *amount)
+    }
+
+    /// Takes the first queued task whose requests fit, `Err` when there is none for now. With
+    /// nothing running, a task that can never fit is started anyway rather than waiting forever.
+    fn next(&mut self) -> Result<Option<(usize, Task<'a, T>, Resources)>, ()> {
+        let candidates = if self.strict_order { self.queue.len().min(1) } else { self.queue.len() };
+        let position = match self.queue.iter().take(candidates).position(|(_, _, requests)| self.fits(requests)) {
+            Some(position) => position,
+            None if self.queue.is_empty() => return Ok(None),
+            None if self.running == 0 => 0,
+            None => return Err(()),
+        };
+        let (index, task, requests) = self.queue.remove(position).expect("position is within the queue");
+        for (name, amount) in &requests {
+            if let Some(available) = self.available.get_mut(name) {
+                *available = available.saturating_sub(*amount);
+            }
+        }
+        self.running += 1;
+        Ok(Some((index, task, requests)))
+    }
+
+    fn release(&mut self, requests: &Resources) {
+        for (name, amount) in requests {
+            if let Some(available) = self.available.get_mut(name) {
+                *available += amount;
+            }
+        }
+        self.running -= 1;
+    }
+}
+
+/// Gives the resources of a running task back when dropped, even when the task panics, and
+/// wakes up the workers waiting for them.
+struct Running<'s, 'a, T> {
+    admission: &'s Mutex<Admission<'a, T>>,
+    released: &'s Condvar,
+    requests: Resources,
+}
+
+impl<T> Drop for Running<'_, '_, T> {
+    fn drop(&mut self) {
+        self.admission.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).release(&self.requests);
+        self.released.notify_all();
+    }
+}
+
+impl Scheduler {
+    pub fn new(workers: usize) -> Self {
+        Scheduler {
+            workers: workers.max(1),
+            token: CancellationToken::default(),
+            resources: Resources::new(),
+            limit: None,
+            strict_order: false,
+        }
+    }
+
+    pub fn with_resources(mut self, resources: Resources) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: Option<Arc<ProcessLimit>>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_strict_order(mut self, strict_order: bool) -> Self {
+        self.strict_order = strict_order;
+        self
+    }
+
+    /// Runs every task and returns their results in submission order. Tasks that never started
+    /// because the scheduler was cancelled have no result.
+    pub fn run<'a, T: Send>(&self, tasks: Vec<Task<'a, T>>) -> Vec<Option<T>> {
+        self.run_timed(tasks).into_iter().map(|result| result.map(|(result, _)| result)).collect()
+    }
+
+    /// Like `run`, also telling when and on which worker each task ran.
+    pub fn run_timed<'a, T: Send>(&self, tasks: Vec<Task<'a, T>>) -> Vec<Option<(T, Slot)>> {
+        self.run_requesting(tasks.into_iter().map(|task| (task, Resources::new())).collect())
+    }
+
+    /// Like `run_timed`, each task holding the resources it requests while it runs.
+    pub fn run_requesting<'a, T: Send>(&self, tasks: Vec<(Task<'a, T>, Resources)>) -> Vec<Option<(T, Slot)>> {
+        let count = tasks.len();
+        let admission = Mutex::new(Admission {
+            queue: tasks.into_iter().enumerate().map(|(index, (task, requests))| (index, task, requests)).collect(),
+            available: self.resources.clone(),
+            running: 0,
+            strict_order: self.strict_order,
+        });
+        let released = Arc::new(Condvar::new());
+        self.token.notify_on_cancel(&released);
+        let results = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<(T, Slot)>>>());
+
+        thread::scope(|scope| {
+            for worker in 0..self.workers.min(count) {
+                let (admission, released, results) = (&admission, &released, &results);
+                scope.spawn(move || loop {
+                    let permit = self.limit.as_deref().map(ProcessLimit::acquire);
+                    let next = {
+                        let mut admission = admission.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                        loop {
+                            if self.token.is_cancelled() {
+                                break None;
+                            }
+                            match admission.next() {
+                                Ok(next) => break next,
+                                Err(()) => admission = released.wait(admission).unwrap_or_else(|poisoned| poisoned.into_inner()),
+                            }
+                        }
+                    };
+                    match next {
+                        Some((index, task, requests)) => {
+                            let running = Running { admission, released, requests };
+                            let start = Instant::now();
+                            let result = task(&self.token);
+                            let slot = Slot { worker, start, end: Instant::now() };
+                            results.lock().unwrap_or_else(|poisoned| poisoned.into_inner())[index] = Some((result, slot));
+                            drop(permit);
+                            drop(running);
+                        }
+                        None => break,
+                    }
+                });
+            }
+        });
+
+        results.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CancellationToken, ProcessLimit, Resources, Scheduler, Task};
+    use std::{
+        sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}, mpsc::channel},
+        thread::sleep,
+        time::Duration,
+    };
+
+    #[test]
+    fn returns_results_in_submission_order() {
+        let tasks: Vec<Task<usize>> = (0..8usize)
+            .map(|i| Box::new(move |_: &_| {
+                sleep(Duration::from_millis(((8 - i) * 2) as u64));
+                i
+            }) as Task<usize>)
+            .collect();
+
+        let results = Scheduler::new(4).run(tasks);
+        assert_eq!(results, (0..8).map(Some).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn single_worker_runs_tasks_sequentially() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let tasks: Vec<Task<()>> = (0..5)
+            .map(|i| {
+                let order = order.clone();
+                Box::new(move |_: &_| order.lock().unwrap().push(i)) as Task<()>
+            })
+            .collect();
+
+        Scheduler::new(1).run(tasks);
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn never_exceeds_the_worker_limit() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<Task<()>> = (0..12)
+            .map(|_| {
+                let (running, peak) = (running.clone(), peak.clone());
+                Box::new(move |_: &_| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    sleep(Duration::from_millis(5));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                }) as Task<()>
+            })
+            .collect();
+
+        Scheduler::new(3).run(tasks);
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn schedulers_sharing_a_limit_never_exceed_it_together() {
+        let limit = ProcessLimit::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tasks = || (0..6)
+            .map(|_| {
+                let (running, peak) = (running.clone(), peak.clone());
+                Box::new(move |_: &_| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    sleep(Duration::from_millis(5));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                }) as Task<()>
+            })
+            .collect::<Vec<_>>();
+
+        std::thread::scope(|scope| {
+            for _ in 0..2 {
+                let (limit, tasks) = (limit.clone(), tasks());
+                scope.spawn(move || Scheduler::new(3).with_limit(Some(limit)).run(tasks));
+            }
+        });
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn slots_of_a_worker_do_not_overlap() {
+        let tasks: Vec<Task<()>> = (0..6)
+            .map(|_| Box::new(|_: &_| sleep(Duration::from_millis(5))) as Task<()>)
+            .collect();
+
+        let slots = Scheduler::new(2).run_timed(tasks).into_iter()
+            .map(|result| result.unwrap().1)
+            .collect::<Vec<_>>();
+        assert!(slots.iter().all(|slot| slot.worker < 2 && slot.start <= slot.end));
+        for (i, slot) in slots.iter().enumerate() {
+            for other in &slots[i + 1..] {
+                assert!(slot.worker != other.worker || slot.end <= other.start || other.end <= slot.start);
+            }
+        }
+    }
+
+    #[test]
+    fn cancellation_skips_queued_tasks() {
+        let tasks: Vec<Task<usize>> = (0..4usize)
+            .map(|i| Box::new(move |token: &CancellationToken| {
+                if i == 1 {
+                    token.cancel();
+                }
+                i
+            }) as Task<usize>)
+            .collect();
+
+        let results = Scheduler::new(1).run(tasks);
+        assert_eq!(results, vec![Some(0), Some(1), None, None]);
+    }
+
+    #[test]
+    fn never_exceeds_the_resource_capacities() {
+        let holding = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let gpus = |amount: u64| vec![("gpus".to_owned(), amount)].into_iter().collect::<Resources>();
+        let tasks: Vec<(Task<()>, Resources)> = (0..8)
+            .map(|i| {
+                let (holding, peak) = (holding.clone(), peak.clone());
+                // Every fourth task requests nothing and is only bound by the workers.
+                let requests = if i % 4 == 3 { Resources::new() } else { gpus(1) };
+                let counted = !requests.is_empty();
+                let task = Box::new(move |_: &_| {
+                    if counted {
+                        let now = holding.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                    }
+                    sleep(Duration::from_millis(5));
+                    if counted {
+                        holding.fetch_sub(1, Ordering::SeqCst);
+                    }
+                }) as Task<()>;
+                (task, requests)
+            })
+            .collect();
+
+        let results = Scheduler::new(4).with_resources(gpus(2)).run_requesting(tasks);
+        assert!(results.iter().all(Option::is_some));
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn strict_order_keeps_tasks_from_overtaking() {
+        let gpus = vec![("gpus".to_owned(), 1)].into_iter().collect::<Resources>();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        // The second task waits for the gpu of the first, the third needs nothing. Without strict
+        // order, the first task holds the gpu until the third one started beside it.
+        let tasks = |strict: bool| {
+            let (overtaken, overtook) = channel::<()>();
+            let mut overtook = Some(overtook);
+            (0..3)
+                .map(|i| {
+                    let events = events.clone();
+                    let requests = if i < 2 { gpus.clone() } else { Resources::new() };
+                    let overtaken = if i == 2 { Some(overtaken.clone()) } else { None };
+                    let overtook = if i == 0 { overtook.take() } else { None };
+                    (Box::new(move |_: &_| {
+                        events.lock().unwrap().push(format!("start {}", i));
+                        if let Some(overtaken) = overtaken.filter(|_| !strict) {
+                            overtaken.send(()).unwrap();
+                        }
+                        if let Some(overtook) = overtook.filter(|_| !strict) {
+                            // Only bounds the wait of a scheduler which wrongly kept the third task queued.
+                            let _ = overtook.recv_timeout(Duration::from_secs(10));
+                        }
+                        events.lock().unwrap().push(format!("end {}", i));
+                    }) as Task<()>, requests)
+                })
+                .collect::<Vec<_>>()
+        };
+        let position = |event: &str| events.lock().unwrap().iter().position(|e| e == event).unwrap();
+
+        Scheduler::new(2).with_resources(gpus.clone()).run_requesting(tasks(false));
+        assert!(position("start 2") < position("end 0"));
+        assert!(position("end 0") < position("start 1"));
+        events.lock().unwrap().clear();
+        Scheduler::new(2).with_resources(gpus.clone()).with_strict_order(true).run_requesting(tasks(true));
+        assert!(position("end 0") < position("start 1"));
+        assert!(position("end 0") < position("start 2"));
+    }
+
+    #[test]
+    fn a_panicking_task_gives_its_resources_back() {
+        let gpus = vec![("gpus".to_owned(), 1)].into_iter().collect::<Resources>();
+        let ran = Arc::new(AtomicUsize::new(0));
+        let after = ran.clone();
+        let tasks: Vec<(Task<()>, Resources)> = vec![
+            (Box::new(|_: &_| panic!("the task failed")), gpus.clone()),
+            (Box::new(move |_: &_| {
+                after.fetch_add(1, Ordering::SeqCst);
+            }), gpus.clone()),
+        ];
+
+        let scheduler = Scheduler::new(2).with_resources(gpus.clone());
+        let run = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| scheduler.run_requesting(tasks)));
+        assert!(run.is_err());
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+    }
+}
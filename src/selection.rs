@@ -0,0 +1,153 @@
+use crate::{
+    AppError,
+    model::{Experiment, Project},
+};
+
+/// Experiments picked by `--only`, `--skip`, `--tag` and `--exclude-tag`. An experiment is
+/// selected when it is listed by `--only` (if given), carries one of the `--tag` tags (if
+/// given), and is neither skipped nor carries an excluded tag.
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    pub only: Vec<String>,
+    pub skip: Vec<String>,
+    pub tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+}
+
+impl Selection {
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.skip.is_empty() && self.tags.is_empty() && self.exclude_tags.is_empty()
+    }
+
+    fn selects(&self, name: &str, tags: &[String]) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|only| only == name))
+            && (self.tags.is_empty() || tags.iter().any(|tag| self.tags.contains(tag)))
+            && !self.skip.iter().any(|skip| skip == name)
+            && !tags.iter().any(|tag| self.exclude_tags.contains(tag))
+    }
+
+    /// Keeps the selected experiments of `p` and the repositories they need. Repositories that
+    /// no experiment references are always kept.
+    pub fn apply(&self, mut p: Project) -> Result<Project, AppError> {
+        for name in self.only.iter().chain(self.skip.iter()) {
+            if !p.experiments.iter().any(|exp| &exp.name == name) {
+                return Err(AppError::UnknownExperiment(name.to_owned()));
+            }
+        }
+        if self.is_empty() {
+            return Ok(p);
+        }
+
+        retain_experiments(&mut p, |_, exp| self.selects(&exp.name, &exp.tags));
+        Ok(p)
+    }
+}
+
+/// Keeps the experiments of `p` for which `keep` holds, given their index, and the repositories
+/// they need. Repositories that no experiment references are always kept.
+pub fn retain_experiments(p: &mut Project, keep: impl Fn(usize, &Experiment) -> bool) {
+    let referenced = |p: &Project, repository: &str| p.experiments.iter()
+        .any(|exp| exp.repository.as_deref() == Some(repository));
+    let unreferenced = p.repositories.iter()
+        .filter(|repository| !referenced(p, &repository.name))
+        .map(|repository| repository.name.to_owned())
+        .collect::<Vec<_>>();
+
+    let mut index = 0;
+    p.experiments.retain(|exp| {
+        index += 1;
+        keep(index - 1, exp)
+    });
+    let selected = p.clone();
+    p.repositories.retain(|repository| {
+        unreferenced.contains(&repository.name) || referenced(&selected, &repository.name)
+    });
+}
+
+/// Keeps the experiments of shard `index` out of `count`, counting from 1, for `--shard`.
+/// Experiments linked by `depends_on` form a group that always lands in a single shard, so no
+/// dependency crosses shards. Groups are dealt in config order to the shard with the fewest
+/// experiments so far, which only depends on the config and the selection.
+pub fn shard(mut p: Project, index: usize, count: usize) -> Project {
+    // Union-find whose roots are the first experiment of each group.
+    fn root(groups: &mut [usize], mut member: usize) -> usize {
+        while groups[member] != member {
+            groups[member] = groups[groups[member]];
+            member = groups[member];
+        }
+        member
+    }
+    let mut groups = (0..p.experiments.len()).collect::<Vec<_>>();
+    for (member, exp) in p.experiments.iter().enumerate() {
+        for dependency in &exp.depends_on {
+            if let Some(dependency) = p.experiments.iter().position(|other| other.name == *dependency) {
+                let (a, b) = (root(&mut groups, member), root(&mut groups, dependency));
+                groups[a.max(b)] = a.min(b);
+            }
+        }
+    }
+    let roots = (0..groups.len()).map(|member| root(&mut groups, member)).collect::<Vec<_>>();
+
+    let mut sizes = vec![0; count];
+    let mut shards = vec![0; roots.len()];
+    for (member, group) in roots.iter().enumerate() {
+        if *group == member {
+            let size = roots.iter().filter(|other| *other == group).count();
+            let lightest = (0..count).min_by_key(|shard| sizes[*shard]).unwrap_or(0);
+            sizes[lightest] += size;
+            shards[member] = lightest;
+        } else {
+            shards[member] = shards[*group];
+        }
+    }
+
+    retain_experiments(&mut p, |member, _| shards[member] == index - 1);
+    p
+}
+
+/// Checks that the dependencies of the experiments selected in `selected` were selected too.
+/// When `auto` is set the missing ones are taken back from `full` along with their repositories
+/// and returned as (dependent, dependency) pairs, otherwise they are reported as an error.
+pub fn include_dependencies(
+    full: &Project,
+    mut selected: Project,
+    auto: bool,
+) -> Result<(Project, Vec<(String, String)>), AppError> {
+    let mut missing: Vec<(String, String)> = Vec::new();
+    let mut pending = selected.experiments.iter().map(|exp| exp.name.to_owned()).collect::<Vec<_>>();
+    while let Some(name) = pending.pop() {
+        let exp = full.experiments.iter().find(|exp| exp.name == name).unwrap();
+        for dependency in &exp.depends_on {
+            let known = selected.experiments.iter().any(|exp| exp.name == *dependency)
+                || missing.iter().any(|(_, missing)| missing == dependency);
+            if !known {
+                missing.push((name.to_owned(), dependency.to_owned()));
+                pending.push(dependency.to_owned());
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok((selected, missing));
+    }
+    if !auto {
+        return Err(AppError::MissingDependencies(missing));
+    }
+
+    let needed = |name: &str| selected.experiments.iter().any(|exp| exp.name == name)
+        || missing.iter().any(|(_, dependency)| dependency == name);
+    let experiments = full.experiments.iter()
+        .filter(|exp| needed(&exp.name))
+        .cloned()
+        .collect::<Vec<_>>();
+    let repositories = full.repositories.iter()
+        .filter(|repository| {
+            selected.repositories.iter().any(|kept| kept.name == repository.name)
+                || experiments.iter().any(|exp| exp.repository.as_deref() == Some(&repository.name))
+        })
+        .cloned()
+        .collect();
+    selected.experiments = experiments;
+    selected.repositories = repositories;
+    Ok((selected, missing))
+}
@@ -0,0 +1,41 @@
+use std::{
+    fs::File,
+    io::Write,
+};
+use crate::{
+    AppError,
+    execute::ExperimentResult,
+};
+use std::sync::Arc;
+
+/// Writes a CSV with one `experiment,worker,start_offset,duration` row per scheduled experiment,
+/// ordered by start. Workers are numbered from 1, offsets count from the first start and times are
+/// in seconds. Experiments skipped or never started are left out.
+pub fn write_concurrency_report(path: &str, results: &[ExperimentResult]) -> Result<(), AppError> {
+    let mut scheduled = results.iter()
+        .filter_map(|result| result.slot.map(|slot| (result, slot)))
+        .collect::<Vec<_>>();
+    scheduled.sort_by_key(|(_, slot)| (slot.start, slot.worker));
+
+    let mut csv = String::from("experiment,worker,start_offset,duration\n");
+    if let Some(first) = scheduled.first().map(|(_, slot)| slot.start) {
+        for (result, slot) in scheduled {
+            csv.push_str(&format!(
+                "{},{},{:.3},{:.3}\n",
+                escape(&result.name), slot.worker + 1, (slot.start - first).as_secs_f64(), (slot.end - slot.start).as_secs_f64()
+            ));
+        }
+    }
+
+    File::create(path)
+        .and_then(|mut file| file.write_all(csv.as_bytes()))
+        .map_err(|e| AppError::IOError(path.to_owned(), Arc::new(e)))
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
@@ -1,4 +1,13 @@
-use std::ops::Deref;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs::{File, OpenOptions, read_to_string},
+    io::Write,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+use crate::AppError;
+use std::sync::Arc;
 
 pub trait TSVSerializable {
     fn to_tsv_format(&self) -> String;
@@ -46,11 +55,11 @@ impl <T> TSVSerializable for Vec<T> where T: TSVSerializable {
     fn to_tsv_format(&self) -> String {
         let mut result = String::new();
 
-        if self.len() != 0 {
+        if !self.is_empty() {
             result.push_str(&self[0].to_tsv_format());
-            for i in 1 .. self.len() {
+            for item in &self[1..] {
                 result.push('\t');
-                result.push_str(&self[i].to_tsv_format());
+                result.push_str(&item.to_tsv_format());
             }
         }
 
@@ -58,6 +67,72 @@ impl <T> TSVSerializable for Vec<T> where T: TSVSerializable {
     }
 }
 
+/// Values in key order, matching `TSVHeader::to_tsv_header`.
+impl <K, V> TSVSerializable for BTreeMap<K, V> where K: Display, V: TSVSerializable {
+    fn to_tsv_format(&self) -> String {
+        self.values().map(|value| value.to_tsv_format()).collect::<Vec<_>>().join("\t")
+    }
+}
+
+/// Values sorted by key, so the output does not depend on the hash order.
+impl <K, V> TSVSerializable for HashMap<K, V> where K: Display + Ord, V: TSVSerializable {
+    fn to_tsv_format(&self) -> String {
+        sorted(self).into_iter().map(|(_, value)| value.to_tsv_format()).collect::<Vec<_>>().join("\t")
+    }
+}
+
+/// Header line naming the columns of a record, in the order its values are serialized.
+pub trait TSVHeader {
+    fn to_tsv_header(&self) -> String;
+    fn tsv_columns(&self) -> usize;
+}
+
+impl TSVHeader for Vec<&str> {
+    fn to_tsv_header(&self) -> String {
+        self.to_tsv_format()
+    }
+
+    fn tsv_columns(&self) -> usize {
+        self.len()
+    }
+}
+
+impl <const N: usize> TSVHeader for [&str; N] {
+    fn to_tsv_header(&self) -> String {
+        self.to_vec().to_tsv_format()
+    }
+
+    fn tsv_columns(&self) -> usize {
+        N
+    }
+}
+
+impl <K, V> TSVHeader for BTreeMap<K, V> where K: Display {
+    fn to_tsv_header(&self) -> String {
+        self.keys().map(|key| key.to_string().to_tsv_format()).collect::<Vec<_>>().join("\t")
+    }
+
+    fn tsv_columns(&self) -> usize {
+        self.len()
+    }
+}
+
+impl <K, V> TSVHeader for HashMap<K, V> where K: Display + Ord {
+    fn to_tsv_header(&self) -> String {
+        sorted(self).into_iter().map(|(key, _)| key.to_string().to_tsv_format()).collect::<Vec<_>>().join("\t")
+    }
+
+    fn tsv_columns(&self) -> usize {
+        self.len()
+    }
+}
+
+fn sorted<K: Ord, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
+}
+
 impl TSVSerializable for Box<dyn TSVSerializable> {
     fn to_tsv_format(&self) -> String {
         self.deref().to_tsv_format()
@@ -68,4 +143,125 @@ impl <T> TSVSerializable for Box<T> where T: TSVSerializable {
     fn to_tsv_format(&self) -> String {
         self.deref().to_tsv_format()
     }
-}
\ No newline at end of file
+}
+
+/// TSV file whose rows are checked against its header before being appended.
+pub struct TSVTable {
+    path: PathBuf,
+    columns: usize,
+}
+
+impl TSVTable {
+    /// Truncates `path` and writes `header` as its first line.
+    pub fn create<H: TSVHeader>(path: &Path, header: &H) -> Result<Self, AppError> {
+        let mut file = File::create(path).map_err(io_error(path))?;
+        writeln!(file, "{}", header.to_tsv_header()).map_err(io_error(path))?;
+        Ok(TSVTable { path: path.to_owned(), columns: header.tsv_columns() })
+    }
+
+    /// Appends to `path`, whose first line must be `header`. A missing or empty file is created
+    /// with `header`.
+    pub fn open<H: TSVHeader>(path: &Path, header: &H) -> Result<Self, AppError> {
+        let content = match read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(io_error(path)(err)),
+        };
+        let expected = header.to_tsv_header();
+        match content.lines().next() {
+            None => Self::create(path, header),
+            Some(found) if found == expected => Ok(TSVTable { path: path.to_owned(), columns: header.tsv_columns() }),
+            Some(found) => Err(AppError::TableMismatch(
+                path.to_str().unwrap().to_owned(),
+                format!("expected the header {} but found {}", expected, found),
+            )),
+        }
+    }
+
+    /// Appends `row`, which must have one value per column.
+    pub fn append(&mut self, row: &[Box<dyn TSVSerializable>]) -> Result<(), AppError> {
+        if row.len() != self.columns {
+            return Err(AppError::TableMismatch(
+                self.path.to_str().unwrap().to_owned(),
+                format!("expected {} values per row but got {}", self.columns, row.len()),
+            ));
+        }
+
+        let line = row.iter().map(|value| value.to_tsv_format()).collect::<Vec<_>>().join("\t");
+        OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(io_error(&self.path))
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> AppError {
+    let path = path.to_str().unwrap().to_owned();
+    |e| AppError::IOError(path, Arc::new(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TSVHeader, TSVSerializable, TSVTable};
+    use std::{
+        collections::{BTreeMap, HashMap},
+        env,
+        fs::{read_to_string, remove_file},
+        path::PathBuf,
+        process,
+    };
+
+    fn table_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("replikate-tsv-{}-{}.tsv", name, process::id()));
+        let _ = remove_file(&path);
+        path
+    }
+
+    fn row(values: Vec<i32>) -> Vec<Box<dyn TSVSerializable>> {
+        values.into_iter().map(|value| Box::new(value) as Box<dyn TSVSerializable>).collect()
+    }
+
+    #[test]
+    fn appends_rows_across_openings() {
+        let path = table_path("append");
+        TSVTable::open(&path, &["a", "b"]).unwrap().append(&row(vec![1, 2])).unwrap();
+        TSVTable::open(&path, &["a", "b"]).unwrap().append(&row(vec![3, 4])).unwrap();
+
+        assert_eq!(read_to_string(&path).unwrap(), "\"a\"\t\"b\"\n1\t2\n3\t4\n");
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_rows_of_the_wrong_arity() {
+        let path = table_path("arity");
+        let mut table = TSVTable::create(&path, &["a", "b"]).unwrap();
+
+        assert!(table.append(&row(vec![1])).is_err());
+        assert!(table.append(&row(vec![1, 2, 3])).is_err());
+        assert_eq!(read_to_string(&path).unwrap(), "\"a\"\t\"b\"\n");
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_different_existing_header() {
+        let path = table_path("header");
+        TSVTable::create(&path, &["a", "b"]).unwrap();
+
+        assert!(TSVTable::open(&path, &["b", "a"]).is_err());
+        assert!(TSVTable::open(&path, &["a", "b", "c"]).is_err());
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn maps_serialize_in_key_order() {
+        let metrics = [("time", 3.5), ("accuracy", 0.9), ("memory", 12.0)];
+        let tree = metrics.iter().cloned().collect::<BTreeMap<_, _>>();
+        let hash = metrics.iter().cloned().collect::<HashMap<_, _>>();
+
+        assert_eq!(tree.to_tsv_header(), "\"accuracy\"\t\"memory\"\t\"time\"");
+        assert_eq!(tree.to_tsv_format(), "0.9\t12\t3.5");
+        assert_eq!(hash.to_tsv_header(), tree.to_tsv_header());
+        assert_eq!(hash.to_tsv_format(), tree.to_tsv_format());
+    }
+}
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, env};
+use yaml_rust::{Yaml, yaml::Hash};
+use crate::model::ParsingError;
+
+/// Replaces every `${name}` in the string values of `yaml` by the variable `name` of its
+/// top-level `vars` map, or else by the environment variable `name`, and every `${tool:name}` by
+/// the path of `name` in its top-level `tools` map. Variables may reference each other in any
+/// order as long as they do not form a cycle, tool paths may reference variables. `$${` stands
+/// for a literal `${`. The returned document no longer has `vars` nor `tools` fields. Without
+/// `vars`, plain `${...}` are kept as is so the expansions of shell hooks keep working.
+pub fn resolve_variables(yaml: &Yaml) -> Result<Yaml, ParsingError> {
+    if yaml["vars"].is_badvalue() && yaml["tools"].is_badvalue() {
+        return Ok(yaml.clone());
+    }
+
+    // Tools are resolved like variables named `tool:<name>`.
+    let mut declared = scalar_map(yaml, "vars")?;
+    for (name, path) in scalar_map(yaml, "tools")? {
+        declared.insert(format!("tool:{}", name), path);
+    }
+    let mut resolver = Resolver {
+        declared,
+        resolved: HashMap::new(),
+        stack: Vec::new(),
+        interpolate: !yaml["vars"].is_badvalue(),
+    };
+    let mut names = resolver.declared.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    for name in &names {
+        resolver.resolve(name)?;
+    }
+
+    let mut document = yaml.clone();
+    if let Yaml::Hash(fields) = &mut document {
+        fields.remove(&Yaml::String("vars".to_owned()));
+        fields.remove(&Yaml::String("tools".to_owned()));
+    }
+    substitute_all(&document, &mut |reference| resolver.resolve(reference))
+}
+
+/// `document`, read from a file that `config` includes, with its variables resolved like those of
+/// `config` itself, from the `vars` and `tools` of `config`.
+pub fn resolve_included(document: &Yaml, config: &Yaml) -> Result<Yaml, ParsingError> {
+    let mut fields = Hash::new();
+    for field in ["vars", "tools"] {
+        if !config[field].is_badvalue() {
+            fields.insert(Yaml::String(field.to_owned()), config[field].clone());
+        }
+    }
+    fields.insert(Yaml::String("included".to_owned()), document.clone());
+    Ok(resolve_variables(&Yaml::Hash(fields))?["included"].clone())
+}
+
+fn scalar_map(yaml: &Yaml, field: &'static str) -> Result<HashMap<String, String>, ParsingError> {
+    match &yaml[field] {
+        Yaml::BadValue => Ok(HashMap::new()),
+        Yaml::Hash(entries) => entries.iter()
+            .map(|(name, value)| match (name.as_str(), scalar(value)) {
+                (Some(name), Some(value)) => Ok((name.to_owned(), value)),
+                _ => Err(ParsingError::InvalidField(field)),
+            })
+            .collect(),
+        _ => Err(ParsingError::InvalidField(field)),
+    }
+}
+
+struct Resolver {
+    declared: HashMap<String, String>,
+    resolved: HashMap<String, String>,
+    /// Variables being resolved, to detect cycles.
+    stack: Vec<String>,
+    /// Whether `vars` was declared, otherwise references are left untouched.
+    interpolate: bool,
+}
+
+impl Resolver {
+    fn resolve(&mut self, name: &str) -> Result<String, ParsingError> {
+        let tool = name.strip_prefix("tool:");
+        if tool.is_none() && !self.interpolate {
+            return Ok(format!("${{{}}}", name));
+        }
+        if let Some(value) = self.resolved.get(name) {
+            return Ok(value.to_owned());
+        }
+        let raw = match (self.declared.get(name), tool) {
+            (Some(raw), _) => raw.to_owned(),
+            (None, Some(tool)) => return Err(ParsingError::UnknownTool(tool.to_owned())),
+            (None, None) => return from_env(name),
+        };
+        if let Some(start) = self.stack.iter().position(|other| other == name) {
+            let mut cycle = self.stack[start..].to_vec();
+            cycle.push(name.to_owned());
+            return Err(ParsingError::CyclicVariables(cycle));
+        }
+
+        self.stack.push(name.to_owned());
+        let value = substitute(&raw, &mut |reference| self.resolve(reference));
+        self.stack.pop();
+
+        let value = value?;
+        self.resolved.insert(name.to_owned(), value.to_owned());
+        Ok(value)
+    }
+}
+
+fn from_env(name: &str) -> Result<String, ParsingError> {
+    env::var(name).map_err(|_| ParsingError::UndefinedVariable(name.to_owned()))
+}
+
+fn scalar(yaml: &Yaml) -> Option<String> {
+    match yaml {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.to_owned()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn substitute_all(
+    yaml: &Yaml,
+    lookup: &mut dyn FnMut(&str) -> Result<String, ParsingError>,
+) -> Result<Yaml, ParsingError> {
+    Ok(match yaml {
+        Yaml::String(text) => Yaml::String(substitute(text, lookup)?),
+        Yaml::Array(items) => Yaml::Array(items.iter()
+            .map(|item| substitute_all(item, lookup))
+            .collect::<Result<_, _>>()?),
+        Yaml::Hash(fields) => {
+            let mut substituted = Hash::new();
+            for (key, value) in fields {
+                substituted.insert(key.clone(), substitute_all(value, lookup)?);
+            }
+            Yaml::Hash(substituted)
+        }
+        other => other.clone(),
+    })
+}
+
+/// Expands the `${name}` references of `text` with `lookup`.
+pub fn substitute(
+    text: &str,
+    lookup: &mut dyn FnMut(&str) -> Result<String, ParsingError>,
+) -> Result<String, ParsingError> {
+    let mut output = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        if let Some(escaped) = candidate.strip_prefix("$${") {
+            output.push_str("${");
+            rest = escaped;
+        } else if let Some(reference) = candidate.strip_prefix("${") {
+            let end = reference.find('}').ok_or_else(|| ParsingError::UnterminatedVariable(text.to_owned()))?;
+            output.push_str(&lookup(&reference[..end])?);
+            rest = &reference[end + 1..];
+        } else {
+            output.push('$');
+            rest = &candidate[1..];
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+use crate::{
+    AppError,
+    dotenv::experiment_env,
+    model::{Project, program_exists, split_words},
+    requirements::{RequirementStatus, check},
+    sha256::sha256_file,
+};
+
+/// Runs every check that has no side effect, beyond those already done while parsing `p`, and
+/// prints each problem found: malformed repository urls, missing requirements, experiment
+/// programs that cannot be found, unreadable env files and missing or altered inputs. Nothing is
+/// cloned, downloaded nor run. Fails when any problem was found.
+pub fn verify(p: &Project) -> Result<(), AppError> {
+    let project_path = Path::new(&p.path);
+    let mut problems = Vec::new();
+
+    for repository in &p.repositories {
+        if !is_well_formed_url(&repository.url) {
+            problems.push(format!("repository {}: '{}' is not a git url", repository.name, repository.url));
+        }
+    }
+
+    // Missing optional requirements only skip the experiments needing them.
+    for requirement in p.requirements.iter().filter(|requirement| requirement.required) {
+        match check(requirement, project_path) {
+            (_, RequirementStatus::Missing) => problems.push(format!("requirement {}: not found", requirement.name)),
+            (used, RequirementStatus::Mismatch(found)) => problems.push(format!(
+                "requirement {}: found version {} of {}, expected at least {}", requirement.name, found, used.name, used.version
+            )),
+            _ => {}
+        }
+    }
+
+    for exp in &p.experiments {
+        let exp = &p.wrapped(exp);
+        let working_dir = p.working_dir(exp);
+        // The project folder itself is created by any phase, a repository only by `--git`.
+        if exp.repository.is_some() && !working_dir.exists() {
+            problems.push(format!(
+                "experiment {}: its working directory {} does not exist yet, run --git first", exp.name, working_dir.display()
+            ));
+        } else if !program_exists(&exp.program(), &working_dir) {
+            problems.push(format!("experiment {}: program '{}' not found", exp.name, exp.program()));
+        }
+        if let Some(check) = exp.check.as_deref().and_then(split_words).and_then(|words| words.into_iter().next()) {
+            if working_dir.exists() && !program_exists(&check, &working_dir) {
+                problems.push(format!("experiment {}: check program '{}' not found", exp.name, check));
+            }
+        }
+
+        // Without prefix, the project env file would be reported once per experiment.
+        if let Err(err) = experiment_env(p, exp, 0, &p.run_logs_path().join(exp.log_folder()), &[]) {
+            let problem = err.to_string();
+            if !problems.contains(&problem) {
+                problems.push(problem);
+            }
+        }
+
+        for input in &exp.inputs {
+            let path = working_dir.join(&input.path);
+            if !path.exists() {
+                if input.url.is_none() {
+                    problems.push(format!("experiment {}: input '{}' is missing and has no url", exp.name, input.path));
+                }
+                continue;
+            }
+            match sha256_file(&path) {
+                Ok(actual) if actual != input.sha256 => problems.push(format!(
+                    "experiment {}: input '{}' has checksum {}, expected {}", exp.name, input.path, actual, input.sha256
+                )),
+                Ok(_) => {}
+                Err(err) => problems.push(format!("experiment {}: cannot read input '{}': {}", exp.name, input.path, err)),
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        println!("{} looks runnable: no problem found.", p.name);
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("  - {}", problem);
+    }
+    Err(AppError::ExternalError(format!("{} problem(s) found in {}.", problems.len(), p.name)))
+}
+
+/// Urls `git clone` accepts: `scheme://host/path` with a known scheme, `[user@]host:path` or a
+/// local path.
+fn is_well_formed_url(url: &str) -> bool {
+    if url.trim().is_empty() || url.chars().any(char::is_whitespace) {
+        return false;
+    }
+    if let Some((scheme, rest)) = url.split_once("://") {
+        return ["https", "http", "ssh", "git", "file"].contains(&scheme) && !rest.is_empty();
+    }
+    match url.split_once(':') {
+        Some((host, path)) => !host.is_empty() && !host.contains('/') && !path.is_empty(),
+        None => true,
+    }
+}
@@ -0,0 +1,132 @@
+use std::cmp::Ordering;
+
+/// A dotted version such as `1.10.2-rc1+build5`. Numeric components compare numerically and
+/// missing components count as zero, so `1.10 > 1.9` and `1.2 == 1.2.0`. A pre-release
+/// (`-rc1`) sorts before the release it precedes, build metadata (`+build5`) is ignored.
+#[derive(Clone, Debug)]
+pub struct Version {
+    pub components: Vec<u64>,
+    pub pre_release: Option<String>,
+}
+
+pub fn parse_version(text: &str) -> Option<Version> {
+    let text = text.trim().trim_start_matches(['v', 'V']);
+    let text = text.split('+').next().unwrap();
+    let (release, pre_release) = match text.split_once('-') {
+        Some((release, pre_release)) => (release, Some(pre_release.to_owned())),
+        None => (text, None),
+    };
+
+    let components = release.split('.')
+        .map(|component| component.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Version { components, pre_release })
+}
+
+impl Version {
+    fn component(&self, index: usize) -> u64 {
+        self.components.get(index).copied().unwrap_or(0)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let length = self.components.len().max(other.components.len());
+        (0..length)
+            .map(|index| self.component(index).cmp(&other.component(index)))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or_else(|| match (&self.pre_release, &other.pre_release) {
+                (None, None) => Ordering::Equal,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(a), Some(b)) => compare_pre_releases(a, b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+/// Compares pre-release identifiers chunk by chunk, digits numerically, so `rc2 < rc10`.
+fn compare_pre_releases(a: &str, b: &str) -> Ordering {
+    let (a, b) = (chunks(a), chunks(b));
+    for (x, y) in a.iter().zip(b.iter()) {
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+fn chunks(text: &str) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    for c in text.chars().filter(|c| *c != '.' && *c != '-') {
+        match chunks.last_mut() {
+            Some(last) if last.chars().last().unwrap().is_ascii_digit() == c.is_ascii_digit() => last.push(c),
+            _ => chunks.push(c.to_string()),
+        }
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_version;
+
+    fn v(text: &str) -> super::Version {
+        parse_version(text).unwrap()
+    }
+
+    #[test]
+    fn compares_components_numerically() {
+        assert!(v("1.10") > v("1.9"));
+        assert!(v("2.0") > v("1.99.99"));
+        assert!(v("0.4.11") < v("0.4.12"));
+    }
+
+    #[test]
+    fn missing_components_are_zero() {
+        assert_eq!(v("1.2"), v("1.2.0"));
+        assert!(v("1.2.1") > v("1.2"));
+    }
+
+    #[test]
+    fn pre_releases_sort_before_releases() {
+        assert!(v("1.0-rc1") < v("1.0"));
+        assert!(v("1.0-rc1") < v("1.0-rc2"));
+        assert!(v("1.0-rc2") < v("1.0-rc10"));
+        assert!(v("1.0-alpha") < v("1.0-beta"));
+        assert!(v("1.0-rc1") > v("0.9"));
+    }
+
+    #[test]
+    fn ignores_build_metadata_and_prefix() {
+        assert_eq!(v("1.4.0+build5"), v("1.4"));
+        assert_eq!(v("v2.25"), v("2.25"));
+    }
+
+    #[test]
+    fn rejects_non_numeric_components() {
+        assert!(parse_version("abc").is_none());
+        assert!(parse_version("1.x").is_none());
+        assert!(parse_version("").is_none());
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    fs::{metadata, read_dir},
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::{Duration, SystemTime},
+};
+use crate::{
+    info,
+    AppError,
+    model::Project,
+    process::{catch_interrupt, interrupted},
+};
+
+/// Interval between two scans of the watched files.
+const SCAN_INTERVAL: Duration = Duration::from_millis(500);
+/// Changes are only acted upon once the files stayed untouched for this long.
+const DEBOUNCE: Duration = Duration::from_millis(1000);
+
+/// Modification times of every file under the watched paths.
+type Snapshot = HashMap<PathBuf, SystemTime>;
+
+/// Calls `run` with `project`, then again each time the config or a repository's sources change,
+/// until interrupted with Ctrl-C. A config change reloads it with `reload` and re-runs every
+/// experiment; a source change re-runs the experiments using that repository. Experiments without
+/// a repository only re-run when the config changes.
+pub fn watch(
+    config: &Path,
+    mut project: Project,
+    reload: impl Fn() -> Result<Project, AppError>,
+    run: impl Fn(&Project),
+) -> Result<(), AppError> {
+    catch_interrupt();
+    run(&project);
+
+    let mut snapshot = scan(config, &project);
+    loop {
+        info!("Watching {} and {} repository folder(s), press Ctrl-C to stop...", config.display(), project.repositories.len());
+
+        let changed = match wait_for_changes(config, &project, &snapshot) {
+            Some(changed) => changed,
+            None => break,
+        };
+
+        if changed.iter().any(|path| path == config) {
+            info!("{} changed, reloading", config.display());
+            match reload() {
+                Ok(reloaded) => {
+                    project = reloaded;
+                    run(&project);
+                }
+                Err(err) => info!("{}", err),
+            }
+        } else {
+            let affected = affected(&project, &changed);
+            if affected.experiments.is_empty() {
+                info!("No experiment uses the changed files");
+            } else {
+                run(&affected);
+            }
+        }
+        snapshot = scan(config, &project);
+    }
+
+    info!("Stopped watching");
+    Ok(())
+}
+
+/// Waits until the watched files differ from `snapshot` and stay stable for `DEBOUNCE`, returning
+/// the changed paths, or `None` when interrupted.
+fn wait_for_changes(config: &Path, project: &Project, snapshot: &Snapshot) -> Option<Vec<PathBuf>> {
+    let mut latest = snapshot.clone();
+    let mut stable_since = None;
+    loop {
+        sleep(SCAN_INTERVAL);
+        if interrupted() {
+            return None;
+        }
+
+        let current = scan(config, project);
+        if current != latest {
+            latest = current;
+            stable_since = Some(SystemTime::now());
+        } else if latest != *snapshot && stable_since.map(|since| since.elapsed().unwrap_or_default() >= DEBOUNCE).unwrap_or(false) {
+            let mut changed = latest.iter()
+                .filter(|(path, modified)| snapshot.get(*path) != Some(modified))
+                .map(|(path, _)| path.to_owned())
+                .chain(snapshot.keys().filter(|path| !latest.contains_key(*path)).cloned())
+                .collect::<Vec<_>>();
+            changed.sort();
+            return Some(changed);
+        }
+    }
+}
+
+/// The part of `project` impacted by `changed`: the repositories holding a changed file and the
+/// experiments running in them.
+fn affected(project: &Project, changed: &[PathBuf]) -> Project {
+    let src = project.src_path();
+    let mut affected = project.clone();
+    affected.repositories.retain(|repo| changed.iter().any(|path| path.starts_with(src.join(&repo.name))));
+    let repositories = &affected.repositories;
+    affected.experiments.retain(|exp| match &exp.repository {
+        Some(name) => repositories.iter().any(|repo| repo.name == *name),
+        None => false,
+    });
+    affected
+}
+
+fn scan(config: &Path, project: &Project) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    collect(config, &mut snapshot);
+    let src = project.src_path();
+    for repo in &project.repositories {
+        collect(&src.join(&repo.name), &mut snapshot);
+    }
+    snapshot
+}
+
+/// Records the modification time of `path` and, for folders, of everything below it except
+/// hidden entries such as `.git`.
+fn collect(path: &Path, snapshot: &mut Snapshot) {
+    let metadata = match metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+    if !metadata.is_dir() {
+        if let Ok(modified) = metadata.modified() {
+            snapshot.insert(path.to_owned(), modified);
+        }
+        return;
+    }
+
+    if let Ok(entries) = read_dir(path) {
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                collect(&entry.path(), snapshot);
+            }
+        }
+    }
+}